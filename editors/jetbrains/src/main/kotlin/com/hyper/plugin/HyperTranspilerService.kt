@@ -136,7 +136,7 @@ class HyperTranspilerService(private val project: Project) : Disposable {
 
     @Serializable
     data class Segment(
-        val language: String,  // "python" or "html"
+        val language: String,  // "python", "html", or "template"
         val source_start: Int,
         val source_end: Int,
         val compiled_start: Int,
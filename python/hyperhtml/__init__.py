@@ -15,6 +15,7 @@ from hyperhtml.helpers import (
     safe,
     escape_html,
     render_attr,
+    render_optional_attr,
     render_class,
     render_style,
     render_data,
@@ -36,6 +37,7 @@ __all__ = [
     "escape_html",
     # Attribute rendering
     "render_attr",
+    "render_optional_attr",
     "render_class",
     "render_style",
     "render_data",
@@ -2,6 +2,7 @@
 
 from __future__ import annotations
 
+import copy
 import functools
 import inspect
 from collections.abc import Callable, Iterable
@@ -29,7 +30,16 @@ class Component:
         subcomponents: Iterable[Component] = (),
     ) -> None:
         signature = inspect.signature(render)
-        for parameter in signature.parameters.values():
+        parameters = list(signature.parameters.values())
+        # `def render(self, *, ...)` in a class binds like a method.
+        is_method = (
+            bool(parameters)
+            and parameters[0].name == "self"
+            and parameters[0].kind is inspect.Parameter.POSITIONAL_OR_KEYWORD
+        )
+        if is_method:
+            parameters = parameters[1:]
+        for parameter in parameters:
             if parameter.kind not in {
                 inspect.Parameter.KEYWORD_ONLY,
                 inspect.Parameter.VAR_KEYWORD,
@@ -40,10 +50,11 @@ class Component:
 
         self.stream = render
         self._is_async = inspect.isasyncgenfunction(render)
+        self._is_method = is_method
         self._subcomponent_names = frozenset()
 
         functools.update_wrapper(self, render, updated=())
-        self.__signature__ = signature
+        self.__signature__ = signature.replace(parameters=parameters)
         self.__hyper__ = True
         self.do_not_call_in_templates = True
 
@@ -67,6 +78,13 @@ class Component:
 
         self._subcomponent_names = frozenset(names)
 
+    def __get__(self, instance: Any, owner: type | None = None) -> Component:
+        if instance is None or not self._is_method:
+            return self
+        bound = copy.copy(self)
+        object.__setattr__(bound, "stream", functools.partial(self.stream, instance))
+        return bound
+
     def __call__(self, **props: Any) -> HtmlResult | Any:
         if self._is_async:
             return self._buffer_async(**props)
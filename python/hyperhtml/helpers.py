@@ -15,6 +15,7 @@ __all__ = [
     'safe',
     'escape_html',
     'render_attr',
+    'render_optional_attr',
     'render_class',
     'render_style',
     'render_data',
@@ -23,6 +24,31 @@ __all__ = [
 ]
 
 
+# Mirrors BOOLEAN_ATTRIBUTES in rust/src/html.rs.
+BOOLEAN_ATTRIBUTES = frozenset({
+    'disabled',
+    'checked',
+    'readonly',
+    'required',
+    'autofocus',
+    'autoplay',
+    'controls',
+    'loop',
+    'muted',
+    'selected',
+    'open',
+    'hidden',
+    'async',
+    'defer',
+    'novalidate',
+    'formnovalidate',
+    'ismap',
+    'multiple',
+    'reversed',
+    'scoped',
+})
+
+
 class Safe(str):
     """A string marked as safe HTML (will not be escaped).
 
@@ -109,6 +135,10 @@ def render_attr(name: str, value) -> str:
     - False/None: renders nothing
     - Other values: renders name="escaped_value"
 
+    Known boolean attributes (disabled, checked, ...) are also omitted for
+    other falsy values such as 0. Strings keep their value, since some of
+    these attributes take one (hidden="until-found").
+
     Args:
         name: The attribute name.
         value: The attribute value.
@@ -121,9 +151,13 @@ def render_attr(name: str, value) -> str:
         ' disabled'
         >>> render_attr("disabled", False)
         ''
+        >>> render_attr("hidden", "until-found")
+        ' hidden="until-found"'
         >>> render_attr("id", "main")
         ' id="main"'
     """
+    if name in BOOLEAN_ATTRIBUTES and not value and not isinstance(value, str):
+        return ''
     if value is True:
         return f' {name}'
     if value is False or value is None:
@@ -131,6 +165,23 @@ def render_attr(name: str, value) -> str:
     return f' {name}="{escape_html(value)}"'
 
 
+def render_optional_attr(name: str, value) -> str:
+    """Render an attribute written as `name?={value}`.
+
+    Like render_attr, but an empty string also leaves the attribute out.
+    Other falsy values such as 0 still render.
+
+    Example:
+        >>> render_optional_attr("title", "")
+        ''
+        >>> render_optional_attr("tabindex", 0)
+        ' tabindex="0"'
+    """
+    if isinstance(value, str) and not value:
+        return ''
+    return render_attr(name, value)
+
+
 def render_class(*values) -> str:
     """Render a class attribute value from various inputs.
 
@@ -91,3 +91,17 @@ def test_component_is_a_callable_object():
 
     assert not isinstance(MyComponent, type)
     assert callable(MyComponent)
+
+
+def test_component_method_binds_to_instance():
+    class Page:
+        heading = "Home"
+
+        @component
+        def render(self, *, title: str = ""):
+            yield f"<h1>{self.heading}: {title}</h1>"
+
+    page = Page()
+    assert page.render(title="Hi") == "<h1>Home: Hi</h1>"
+    assert list(page.render.stream(title="Hi")) == ["<h1>Home: Hi</h1>"]
+    assert "self" not in inspect.signature(page.render).parameters
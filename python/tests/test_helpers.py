@@ -1,7 +1,7 @@
 """Escape contract. Output must be identical whether the C fast path or the
 pure-Python fallback runs, so these lock the exact bytes."""
 
-from hyperhtml.helpers import escape_html, safe
+from hyperhtml.helpers import escape_html, render_attr, render_optional_attr, safe
 
 
 def test_escapes_all_five_special_chars():
@@ -23,3 +23,24 @@ def test_safe_value_passes_through_unescaped():
 
 def test_clean_string_is_unchanged():
     assert escape_html('no specials here') == 'no specials here'
+
+
+def test_boolean_attribute_renders_bare_only_for_true():
+    assert render_attr('disabled', True) == ' disabled'
+    assert render_attr('disabled', False) == ''
+    assert render_attr('disabled', 0) == ''
+    assert render_attr('hidden', 'until-found') == ' hidden="until-found"'
+    assert render_attr('hidden', '') == ' hidden=""'
+
+
+def test_value_attribute_keeps_name_value_form():
+    assert render_attr('title', 'a "b"') == ' title="a &#34;b&#34;"'
+    assert render_attr('title', None) == ''
+
+
+def test_optional_attribute_is_left_out_when_unset():
+    assert render_optional_attr('title', None) == ''
+    assert render_optional_attr('title', False) == ''
+    assert render_optional_attr('title', '') == ''
+    assert render_optional_attr('title', 'Hi') == ' title="Hi"'
+    assert render_optional_attr('tabindex', 0) == ' tabindex="0"'
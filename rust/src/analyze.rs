@@ -0,0 +1,88 @@
+//! What a template declares and uses, without generating any code
+//!
+//! Editors use this for completion and docs: the props a component takes,
+//! the slots it renders and the components it calls.
+
+use std::collections::BTreeSet;
+
+use crate::ast::{Function, Node};
+use crate::error::{CompileError, Warning};
+use crate::generate::CompileOptions;
+use crate::plugins::{Flow, Plugin, slot_param_name, walk};
+
+/// Summary of a template's component, as [`analyze`] reports it
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TemplateInfo {
+    /// Props in declaration order. Slots are listed in `slots` instead.
+    pub parameters: Vec<ParameterInfo>,
+    /// Python argument of each slot the template renders: `content` for the
+    /// default slot `{...}`, the slot's own name otherwise
+    pub slots: BTreeSet<String>,
+    pub is_async: bool,
+    /// Every component the file calls, by the name written in its tag
+    pub referenced_components: BTreeSet<String>,
+    pub warnings: Vec<Warning>,
+}
+
+/// A prop declared above the `---` separator
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ParameterInfo {
+    pub name: String,
+    pub type_hint: Option<String>,
+    pub default: Option<String>,
+}
+
+/// Parse and transform `source` as [`compile`](crate::compile) would, then
+/// describe its component instead of generating Python. Files made only of
+/// `component` definitions have no component of their own, so they report
+/// no parameters or slots.
+pub fn analyze(source: &str) -> Result<TemplateInfo, CompileError> {
+    let mut transformed = crate::transform(source, &CompileOptions::default(), &mut [])?;
+    let function = &mut transformed.ast.function;
+
+    let mut slots = SlotsUsed::default();
+    slots.run(function)?;
+    let parameters = parameters(function)
+        .filter(|param| !slots.names.contains(&param.name))
+        .collect();
+
+    Ok(TemplateInfo {
+        parameters,
+        slots: slots.names,
+        is_async: function.is_async,
+        referenced_components: transformed.referenced_components,
+        warnings: transformed.warnings,
+    })
+}
+
+fn parameters(function: &Function) -> impl Iterator<Item = ParameterInfo> + '_ {
+    function.params.iter().filter_map(|node| match node {
+        Node::Parameter(param) => Some(ParameterInfo {
+            name: param.name.clone(),
+            type_hint: param.type_hint.clone(),
+            default: param.default.clone(),
+        }),
+        _ => None,
+    })
+}
+
+/// Slots rendered in a component body; fills passed to calls don't count.
+#[derive(Default)]
+struct SlotsUsed {
+    names: BTreeSet<String>,
+}
+
+impl Plugin for SlotsUsed {
+    fn run(&mut self, function: &mut Function) -> Result<(), CompileError> {
+        walk(&mut function.body, self)
+    }
+
+    fn enter(&mut self, node: &mut Node) -> Result<Flow, CompileError> {
+        if let Node::Slot(slot) = node
+            && !slot.is_fill
+        {
+            self.names.insert(slot_param_name(slot.name.as_deref()));
+        }
+        Ok(Flow::Continue)
+    }
+}
@@ -5,6 +5,7 @@
 //! separate tree.
 
 use std::collections::HashMap;
+use std::ops::Range;
 use std::sync::Arc;
 
 // Re-export Position and TextRange from tokenizer to avoid duplication
@@ -26,6 +27,8 @@ pub struct Ast {
     pub definitions: Vec<FunctionDefinition>,
     pub function: Function,
     pub source: Arc<str>,
+    /// Text of a leading `---` ... `---` block, for tooling; never compiled
+    pub front_matter: Option<String>,
 }
 
 impl Ast {
@@ -35,6 +38,7 @@ impl Ast {
             definitions: Vec::new(),
             function,
             source,
+            front_matter: None,
         }
     }
 }
@@ -112,9 +116,31 @@ pub struct ExpressionNode {
     pub expr: String,
     pub range: TextRange,
     pub escape: bool,                // true = escape HTML, false = raw
+    pub force_escape: bool,          // true if {&value}, escaped regardless of default
     pub format_spec: Option<String>, // e.g. "03d", ".2f", ">20"
     pub conversion: Option<char>,    // 'r', 's', or 'a'
     pub debug: bool,                 // true if {value=}
+    pub trim_left: bool,             // true if {- value}
+    pub trim_right: bool,            // true if {value -}
+    pub padding: (usize, usize),     // whitespace between the trim markers and the code
+}
+
+impl ExpressionNode {
+    /// Source byte span of the code between the braces, excluding the `{!`
+    /// or `{&` escape marker and any `{-`/`-}` whitespace-control markers
+    /// with the whitespace that sets them off.
+    pub fn code_span(&self) -> (usize, usize) {
+        let marker = !self.escape || self.force_escape;
+        let (padding_left, padding_right) = self.padding;
+        (
+            self.range.start.byte
+                + 1
+                + usize::from(marker)
+                + usize::from(self.trim_left)
+                + padding_left,
+            self.range.end.byte - 1 - usize::from(self.trim_right) - padding_right,
+        )
+    }
 }
 
 /// HTML element
@@ -137,6 +163,12 @@ pub struct ComponentNode {
     pub attributes: Vec<Attribute>,
     pub children: Vec<Node>,
     pub slots: HashMap<String, Vec<Node>>,
+    /// The component is async: defined async in this file, or marked with an
+    /// `async` attribute. Streamed with `async for` instead of `yield from`.
+    pub is_async: bool,
+    /// The children or a slot fill await or call an async component, so the
+    /// slot generators are `async def`
+    pub async_bodies: bool,
     pub range: TextRange,
     pub close_range: Option<TextRange>,
 }
@@ -165,8 +197,13 @@ pub struct IfNode {
     pub condition: String,
     pub condition_range: TextRange,
     pub then_branch: Vec<Node>,
-    pub elif_branches: Vec<(String, TextRange, Vec<Node>)>,
+    /// Condition, its range, body and the trailing `# comment` on its line
+    pub elif_branches: Vec<(String, TextRange, Vec<Node>, Option<String>)>,
     pub else_branch: Option<Vec<Node>>,
+    /// Trailing `# comment` on the opening line
+    pub comment: Option<String>,
+    /// Trailing `# comment` on the `else:` line
+    pub else_comment: Option<String>,
     pub range: TextRange,
 }
 
@@ -178,7 +215,13 @@ pub struct ForNode {
     pub iterable: String, // The Python expression
     pub iterable_range: TextRange,
     pub body: Vec<Node>,
+    /// Runs when the loop finishes without `break`
+    pub else_branch: Option<Vec<Node>>,
     pub is_async: bool, // async for
+    /// Trailing `# comment` on the opening line
+    pub comment: Option<String>,
+    /// Trailing `# comment` on the `else:` line
+    pub else_comment: Option<String>,
     pub range: TextRange,
 }
 
@@ -188,6 +231,8 @@ pub struct MatchNode {
     pub expr: String,
     pub expr_range: TextRange,
     pub cases: Vec<CaseNode>,
+    /// Trailing `# comment` on the opening line
+    pub comment: Option<String>,
     pub range: TextRange,
 }
 
@@ -196,6 +241,8 @@ pub struct CaseNode {
     pub pattern: String,
     pub pattern_range: TextRange,
     pub body: Vec<Node>,
+    /// Trailing `# comment` on the `case` line
+    pub comment: Option<String>,
     pub range: TextRange,
 }
 
@@ -205,6 +252,12 @@ pub struct WhileNode {
     pub condition: String,
     pub condition_range: TextRange,
     pub body: Vec<Node>,
+    /// Runs when the loop finishes without `break`
+    pub else_branch: Option<Vec<Node>>,
+    /// Trailing `# comment` on the opening line
+    pub comment: Option<String>,
+    /// Trailing `# comment` on the `else:` line
+    pub else_comment: Option<String>,
     pub range: TextRange,
 }
 
@@ -215,6 +268,8 @@ pub struct WithNode {
     pub items_range: TextRange,
     pub body: Vec<Node>,
     pub is_async: bool, // async with
+    /// Trailing `# comment` on the opening line
+    pub comment: Option<String>,
     pub range: TextRange,
 }
 
@@ -225,14 +280,22 @@ pub struct TryNode {
     pub except_clauses: Vec<ExceptClause>,
     pub else_clause: Option<Vec<Node>>,
     pub finally_clause: Option<Vec<Node>>,
+    /// Trailing `# comment` on the `try:`, `else:` and `finally:` lines
+    pub comment: Option<String>,
+    pub else_comment: Option<String>,
+    pub finally_comment: Option<String>,
     pub range: TextRange,
 }
 
 #[derive(Debug, Clone)]
 pub struct ExceptClause {
-    pub exception: Option<String>, // None for bare "except:"
+    /// Exception types and optional `as` binding, without the colon; None
+    /// for a bare `except:`, which must come last
+    pub exception: Option<String>,
     pub exception_range: Option<TextRange>,
     pub body: Vec<Node>,
+    /// Trailing `# comment` on the `except` line
+    pub comment: Option<String>,
     pub range: TextRange,
 }
 
@@ -241,6 +304,9 @@ pub struct ExceptClause {
 pub struct StatementNode {
     pub stmt: String,
     pub range: TextRange,
+    /// Written as `{ name = expr }` on a line with other content, so the
+    /// whitespace around it is content too
+    pub inline: bool,
 }
 
 /// Function or class definition
@@ -312,7 +378,19 @@ pub enum AttributeKind {
     Expression {
         name: String,
         expr: String,
+        /// `expr_range` covers `expr` alone: no braces, and no `!` or `&`
+        /// marker
         expr_range: TextRange,
+        /// `{expr}` from its opening brace through its closing one
+        brace_range: TextRange,
+        /// False for `{!expr}`: the value is inserted without HTML escaping
+        escape: bool,
+        /// True for `{&expr}`: the value is escaped even when
+        /// `CompileOptions::default_escape` is off
+        force_escape: bool,
+        /// `name?={expr}`: the attribute is left out when `expr` is `None`,
+        /// `False` or an empty string
+        optional: bool,
     },
 
     /// Template: class="{expr} static" (mixed expressions in quoted value)
@@ -333,9 +411,82 @@ pub enum AttributeKind {
     /// Slot assignment: {...name}
     SlotAssignment {
         name: String,
+        /// Always `None`: `{...name}` only names a slot and carries no Python
+        /// expression, so generation records no Python range for it.
         expr: Option<String>,
+        /// The `{...name}` marker, for brace matching and HTML gaps
         expr_range: Option<TextRange>,
         /// Set when the component-slot plugin moves this node into a named fill.
         bound: bool,
     },
 }
+
+/// A piece of a quoted attribute value
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplatePart<'a> {
+    /// Literal text, with `{{` and `}}` unescaped to single braces
+    Text(String),
+    /// `{code}`; `range` is where `code` sits in the value, braces excluded
+    Expression { code: &'a str, range: Range<usize> },
+}
+
+/// Split a quoted attribute value into text and `{expr}` interpolations.
+/// Strings inside an expression may hold braces; an unclosed `{` is text.
+pub fn template_parts(value: &str) -> Vec<TemplatePart<'_>> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut rest = value.char_indices().peekable();
+    while let Some((i, ch)) = rest.next() {
+        match ch {
+            '{' | '}' if rest.peek().is_some_and(|&(_, next)| next == ch) => {
+                rest.next();
+                text.push(ch);
+            }
+            '{' => match closing_brace(&value[i + 1..]) {
+                Some(len) => {
+                    if !text.is_empty() {
+                        parts.push(TemplatePart::Text(std::mem::take(&mut text)));
+                    }
+                    let range = i + 1..i + 1 + len;
+                    parts.push(TemplatePart::Expression {
+                        code: &value[range.clone()],
+                        range,
+                    });
+                    while rest.next_if(|&(j, _)| j <= i + 1 + len).is_some() {}
+                }
+                None => text.push(ch),
+            },
+            _ => text.push(ch),
+        }
+    }
+    if !text.is_empty() {
+        parts.push(TemplatePart::Text(text));
+    }
+    parts
+}
+
+/// Byte length of an expression up to its closing `}`, skipping nested
+/// brackets and string literals
+fn closing_brace(code: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut string = None;
+    let mut chars = code.char_indices();
+    while let Some((i, ch)) = chars.next() {
+        if let Some(quote) = string {
+            if ch == '\\' {
+                chars.next();
+            } else if ch == quote {
+                string = None;
+            }
+            continue;
+        }
+        match ch {
+            '"' | '\'' => string = Some(ch),
+            '(' | '[' | '{' => depth += 1,
+            '}' if depth == 0 => return Some(i),
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
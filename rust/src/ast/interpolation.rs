@@ -0,0 +1,157 @@
+//! The parts of an interpolation's code: `{x!r:>8}` is the value `x`, the
+//! conversion `r` and the format spec `>8`. The parser splits content
+//! expressions with this and lowering splits attribute values.
+
+/// Parse an expression string into (expr, format_spec, conversion, debug).
+/// Handles `{count:03d}`, `{items!r}`, `{value=}` syntax.
+pub(crate) fn parse_expression_parts(code: &str) -> (String, Option<String>, Option<char>, bool) {
+    let mut expr = code.to_string();
+    let mut format_spec = None;
+    let mut conversion = None;
+    let mut debug = false;
+
+    // 1. Check for debug format: trailing `=` (but not ==, !=, <=, >=)
+    let trimmed = expr.trim_end();
+    if trimmed.ends_with('=')
+        && !trimmed.ends_with("==")
+        && !trimmed.ends_with("!=")
+        && !trimmed.ends_with("<=")
+        && !trimmed.ends_with(">=")
+    {
+        debug = true;
+        expr = trimmed[..trimmed.len() - 1].to_string();
+        // Debug format skips escape and uses Python's = format directly
+        return (expr, format_spec, conversion, debug);
+    }
+
+    // 2. Check for format spec: the first `:` at depth 0, as in Python, so
+    //    `{ts:%H:%M}` has the spec `%H:%M`. Must not be inside brackets,
+    //    parens, strings, or dict literals
+    if let Some(colon_pos) = find_format_colon(&expr) {
+        format_spec = Some(expr[colon_pos + 1..].trim_end().to_string());
+        expr = expr[..colon_pos].to_string();
+    }
+
+    // 3. Check for conversion flag: !r, !s, !a at depth 0, at the end of
+    //    what precedes the format spec (`{x!r:>8}`)
+    let trimmed = expr.trim_end();
+    for flag in ['r', 's', 'a'] {
+        let Some(before) = trimmed.strip_suffix(flag).and_then(|t| t.strip_suffix('!')) else {
+            continue;
+        };
+        // Verify the '!' is at depth 0
+        if depth_at_position(trimmed, before.len()) == 0 {
+            conversion = Some(flag);
+            expr = before.to_string();
+        }
+        break;
+    }
+
+    (expr, format_spec, conversion, debug)
+}
+
+/// Calculate nesting depth at a given byte position in an expression.
+fn depth_at_position(expr: &str, target: usize) -> usize {
+    let mut depth: usize = 0;
+    let mut in_string = false;
+    let mut string_char = ' ';
+    let bytes = expr.as_bytes();
+    let mut i = 0;
+
+    while i < target && i < bytes.len() {
+        let ch = bytes[i] as char;
+        if in_string {
+            if ch == '\\' {
+                i += 1; // skip escaped char
+            } else if ch == string_char {
+                in_string = false;
+            }
+        } else {
+            match ch {
+                '"' | '\'' => {
+                    in_string = true;
+                    string_char = ch;
+                }
+                '(' | '[' | '{' => {
+                    depth += 1;
+                }
+                ')' | ']' | '}' => {
+                    depth = depth.saturating_sub(1);
+                }
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+    depth
+}
+
+/// Find the position of the format spec colon: the first `:` at depth 0
+/// that doesn't belong to a `cond ? a : b`. Returns None if no format spec
+/// colon is found.
+fn find_format_colon(expr: &str) -> Option<usize> {
+    let mut depth: usize = 0;
+    let mut in_string = false;
+    let mut string_char = ' ';
+    let mut first_colon_at_depth_0 = None;
+    // `?`s still waiting for the `:` of their `cond ? a : b`
+    let mut open_ternaries: usize = 0;
+    let bytes = expr.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let ch = bytes[i] as char;
+        if in_string {
+            if ch == '\\' {
+                i += 2; // skip escaped char
+                continue;
+            }
+            if ch == string_char {
+                in_string = false;
+            }
+        } else {
+            match ch {
+                '"' | '\'' => {
+                    in_string = true;
+                    string_char = ch;
+                }
+                '(' | '[' | '{' => {
+                    depth += 1;
+                }
+                ')' | ']' | '}' => {
+                    depth = depth.saturating_sub(1);
+                }
+                '?' if depth == 0 => {
+                    open_ternaries += 1;
+                }
+                ':' if depth == 0 && open_ternaries > 0 => {
+                    open_ternaries -= 1;
+                }
+                ':' if depth == 0 => {
+                    first_colon_at_depth_0 = Some(i);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+
+    // Validate: the part after `:` should look like a format spec
+    // (not like a dict value or slice). Format specs are typically short
+    // and contain format characters like d, f, s, >, <, ^, 0, etc.
+    if let Some(pos) = first_colon_at_depth_0 {
+        let after = expr[pos + 1..].trim();
+        // Reject if empty or if it looks like a dict/ternary (contains spaces with keywords)
+        if after.is_empty() {
+            return None;
+        }
+        // Simple heuristic: format specs don't contain unquoted spaces or start with keywords
+        // Format specs: "03d", ".2f", ">20", "#x", ",", "+.2f"
+        if !after.contains(' ') && !after.contains('\t') {
+            return Some(pos);
+        }
+    }
+
+    None
+}
@@ -2,6 +2,8 @@
 //! what the generator prints. Callers say `crate::ast::*` for template types.
 
 mod hyper;
+mod interpolation;
 pub mod python;
 
 pub use hyper::*;
+pub(crate) use interpolation::parse_expression_parts;
@@ -45,6 +45,7 @@ pub struct Code {
 #[derive(Debug, Clone)]
 pub enum Expr {
     Call(ExprCall),
+    Dict(ExprDict),
     Name(ExprName),
     StringLiteral(StringLiteral),
     Code(Code),
@@ -61,6 +62,17 @@ pub struct ExprCall {
     pub arguments: Arguments,
 }
 
+#[derive(Debug, Clone)]
+pub struct ExprDict {
+    pub items: Vec<DictItem>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DictItem {
+    pub key: Expr,
+    pub value: Expr,
+}
+
 #[derive(Debug, Clone)]
 pub struct Arguments {
     pub args: Vec<Expr>,
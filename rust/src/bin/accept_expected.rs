@@ -101,6 +101,7 @@ fn process_file(path: &Path, write: bool) -> bool {
         &CompileOptions {
             function_name: Some(name.to_string()),
             include_ranges: false,
+            ..Default::default()
         },
     );
 
@@ -122,6 +123,7 @@ fn process_file(path: &Path, write: bool) -> bool {
                 &CompileOptions {
                     function_name: Some(name.to_string()),
                     include_ranges: true,
+                    ..Default::default()
                 },
             );
             if let Ok(r) = result_with_ranges
@@ -2,17 +2,20 @@ use crate::parse::tokenizer::TextRange;
 use std::fmt;
 
 /// Kind of parse error
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
 pub enum ErrorKind {
     UnclosedElement,
     UnclosedComponent,
     UnclosedSlot,
     UnclosedBlock,
+    UnexpectedEnd,
     MismatchedCloseTag,
     UnexpectedToken,
     InvalidSyntax,
     VoidElementWithContent,
+    VoidElementCloseTag,
     DuplicateAttribute,
+    DuplicateSlot,
     InvalidNesting,
 }
 
@@ -23,18 +26,21 @@ impl ErrorKind {
             ErrorKind::UnclosedComponent => "Unclosed component",
             ErrorKind::UnclosedSlot => "Unclosed slot",
             ErrorKind::UnclosedBlock => "Unclosed block",
+            ErrorKind::UnexpectedEnd => "Unexpected end",
             ErrorKind::MismatchedCloseTag => "Mismatched close tag",
             ErrorKind::UnexpectedToken => "Unexpected token",
             ErrorKind::InvalidSyntax => "Invalid syntax",
             ErrorKind::VoidElementWithContent => "Void element with content",
+            ErrorKind::VoidElementCloseTag => "Void element close tag",
             ErrorKind::DuplicateAttribute => "Duplicate attribute",
+            ErrorKind::DuplicateSlot => "Duplicate slot",
             ErrorKind::InvalidNesting => "Invalid nesting",
         }
     }
 }
 
 /// Error during parsing
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct ParseError {
     pub kind: ErrorKind,
     pub message: String,
@@ -167,7 +173,7 @@ impl ParseError {
                 source_line.len().saturating_sub(underline_start).max(1)
             };
 
-            let spaces = " ".repeat(underline_start);
+            let spaces = caret_padding(source_line, underline_start);
             let carets = "^".repeat(underline_len);
             output.push_str(&format!(
                 "{}{:>width$} |{} {}{}{}{}\n",
@@ -211,7 +217,7 @@ impl ParseError {
                         .max(1)
                 };
 
-                let spaces = " ".repeat(underline_start);
+                let spaces = caret_padding(related_source_line, underline_start);
                 let carets = "^".repeat(underline_len);
                 let label = self.related_label.as_deref().unwrap_or("opened here");
                 output.push_str(&format!(
@@ -253,6 +259,16 @@ impl ParseError {
     }
 }
 
+/// Whitespace that lines a caret up under column `col` of `line`. Tabs are
+/// kept as tabs so the caret lands where the terminal draws the column.
+fn caret_padding(line: &str, col: usize) -> String {
+    line.chars()
+        .chain(std::iter::repeat(' '))
+        .take(col)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect()
+}
+
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.message)
@@ -261,6 +277,19 @@ impl fmt::Display for ParseError {
 
 impl std::error::Error for ParseError {}
 
+/// A diagnostic that doesn't stop compilation
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Warning {
+    pub message: String,
+    pub range: TextRange,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
 /// Error during compilation (parsing or generation)
 #[derive(Debug)]
 pub enum CompileError {
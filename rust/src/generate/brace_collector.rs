@@ -81,7 +81,7 @@ fn collect_braces_node(node: &Node, braces: &mut Vec<(usize, usize)>) {
             for child in &if_node.then_branch {
                 collect_braces_node(child, braces);
             }
-            for (_, _, body) in &if_node.elif_branches {
+            for (_, _, body, _) in &if_node.elif_branches {
                 for child in body {
                     collect_braces_node(child, braces);
                 }
@@ -96,6 +96,11 @@ fn collect_braces_node(node: &Node, braces: &mut Vec<(usize, usize)>) {
             for child in &for_node.body {
                 collect_braces_node(child, braces);
             }
+            if let Some(else_branch) = &for_node.else_branch {
+                for child in else_branch {
+                    collect_braces_node(child, braces);
+                }
+            }
         }
         Node::Match(match_node) => {
             for case in &match_node.cases {
@@ -108,6 +113,11 @@ fn collect_braces_node(node: &Node, braces: &mut Vec<(usize, usize)>) {
             for child in &while_node.body {
                 collect_braces_node(child, braces);
             }
+            if let Some(else_branch) = &while_node.else_branch {
+                for child in else_branch {
+                    collect_braces_node(child, braces);
+                }
+            }
         }
         Node::With(with_node) => {
             for child in &with_node.body {
@@ -146,9 +156,9 @@ fn collect_braces_node(node: &Node, braces: &mut Vec<(usize, usize)>) {
 #[allow(clippy::while_let_on_iterator)]
 fn collect_braces_attr(attr: &Attribute, braces: &mut Vec<(usize, usize)>) {
     match &attr.kind {
-        AttributeKind::Expression { expr_range, .. } => {
-            // expr_range covers {expr} with exclusive end
-            braces.push((expr_range.start.byte, expr_range.end.byte - 1));
+        AttributeKind::Expression { brace_range, .. } => {
+            // brace_range.end is past the closing brace
+            braces.push((brace_range.start.byte, brace_range.end.byte - 1));
         }
         AttributeKind::Shorthand { expr_range, .. } | AttributeKind::Spread { expr_range, .. } => {
             // expr_range.end points TO closing brace (not past it)
@@ -162,30 +172,13 @@ fn collect_braces_attr(attr: &Attribute, braces: &mut Vec<(usize, usize)>) {
             braces.push((range.start.byte, range.end.byte));
         }
         AttributeKind::Template { name, value } => {
-            // Walk value to find {expr} brace positions
             let value_start_byte = attr.range.start.byte + name.len() + 2; // skip `name="`
-            let mut byte_offset = 0;
-            let mut chars = value.chars().peekable();
-            while let Some(ch) = chars.next() {
-                if ch == '{' {
-                    let open_byte = value_start_byte + byte_offset;
-                    byte_offset += ch.len_utf8();
-                    let mut depth = 1;
-                    while let Some(inner) = chars.next() {
-                        byte_offset += inner.len_utf8();
-                        if inner == '{' {
-                            depth += 1;
-                        } else if inner == '}' {
-                            depth -= 1;
-                            if depth == 0 {
-                                let close_byte = value_start_byte + byte_offset - 1;
-                                braces.push((open_byte, close_byte));
-                                break;
-                            }
-                        }
-                    }
-                } else {
-                    byte_offset += ch.len_utf8();
+            for part in template_parts(value) {
+                if let TemplatePart::Expression { range, .. } = part {
+                    braces.push((
+                        value_start_byte + range.start - 1,
+                        value_start_byte + range.end,
+                    ));
                 }
             }
         }
@@ -10,10 +10,9 @@ pub fn collect_component_attr_expr_spans(attrs: &[Attribute]) -> Vec<(usize, usi
     let mut spans = Vec::new();
     for attr in attrs {
         match &attr.kind {
-            AttributeKind::Expression { expr_range, .. } => {
+            AttributeKind::Expression { brace_range, .. } => {
                 // Include `={…}` — gap starts at the `=` before `{`
-                let gap_start = expr_range.start.byte.saturating_sub(1);
-                spans.push((gap_start, expr_range.end.byte));
+                spans.push(expression_attr_gap(brace_range));
             }
             AttributeKind::Shorthand { expr_range, .. }
             | AttributeKind::Spread { expr_range, .. } => {
@@ -46,10 +45,9 @@ pub fn html_segments_for_element(el: &ElementNode) -> Vec<Segment> {
     let mut expr_spans = Vec::new();
     for attr in &el.attributes {
         match &attr.kind {
-            AttributeKind::Expression { expr_range, .. } => {
+            AttributeKind::Expression { brace_range, .. } => {
                 // Include the = sign before { so virtual HTML sees a boolean attr
-                let gap_start = expr_range.start.byte.saturating_sub(1);
-                expr_spans.push((gap_start, expr_range.end.byte));
+                expr_spans.push(expression_attr_gap(brace_range));
             }
             AttributeKind::Shorthand { expr_range, .. }
             | AttributeKind::Spread { expr_range, .. } => {
@@ -64,31 +62,14 @@ pub fn html_segments_for_element(el: &ElementNode) -> Vec<Segment> {
                 expr_spans.push((gap_start, range.end.byte + 1));
             }
             AttributeKind::Template { name, value } => {
-                // Walk value to find {expr} positions, exclude them from HTML segments
+                // Exclude each {expr}, braces included, from HTML segments
                 let value_start_byte = attr.range.start.byte + name.len() + 2;
-                let mut byte_offset = 0;
-                let mut chars = value.chars().peekable();
-                #[allow(clippy::while_let_on_iterator)]
-                while let Some(ch) = chars.next() {
-                    if ch == '{' {
-                        let gap_start = value_start_byte + byte_offset;
-                        byte_offset += ch.len_utf8();
-                        let mut depth = 1;
-                        while let Some(inner) = chars.next() {
-                            byte_offset += inner.len_utf8();
-                            if inner == '{' {
-                                depth += 1;
-                            } else if inner == '}' {
-                                depth -= 1;
-                                if depth == 0 {
-                                    break;
-                                }
-                            }
-                        }
-                        let gap_end = value_start_byte + byte_offset;
-                        expr_spans.push((gap_start, gap_end));
-                    } else {
-                        byte_offset += ch.len_utf8();
+                for part in template_parts(value) {
+                    if let TemplatePart::Expression { range, .. } = part {
+                        expr_spans.push((
+                            value_start_byte + range.start - 1,
+                            value_start_byte + range.end + 1,
+                        ));
                     }
                 }
             }
@@ -114,6 +95,7 @@ pub fn html_segments_for_element(el: &ElementNode) -> Vec<Segment> {
                 compiled_end: 0,
                 needs_injection: true,
                 html_prefix: None,
+                bytes: None,
             });
         }
         if *expr_end > pos {
@@ -131,6 +113,7 @@ pub fn html_segments_for_element(el: &ElementNode) -> Vec<Segment> {
             compiled_end: 0,
             needs_injection: true,
             html_prefix: None,
+            bytes: None,
         });
     }
 
@@ -144,6 +127,7 @@ pub fn html_segments_for_element(el: &ElementNode) -> Vec<Segment> {
             compiled_end: 0,
             needs_injection: true,
             html_prefix: None,
+            bytes: None,
         });
     }
 
@@ -205,6 +189,7 @@ pub fn html_segments_for_component(
                     } else {
                         None
                     },
+                    bytes: None,
                 });
             }
             if *expr_end > pos {
@@ -222,6 +207,7 @@ pub fn html_segments_for_component(
                 compiled_end: 0,
                 needs_injection: true,
                 html_prefix: if first { Some("<x".into()) } else { None },
+                bytes: None,
             });
         }
     }
@@ -234,3 +220,12 @@ pub fn html_segments_for_component(
 
     segments
 }
+
+/// The `={expr}` part of an expression attribute as `(start, exclusive_end)`,
+/// from the `=` through the closing `}`
+fn expression_attr_gap(brace_range: &TextRange) -> (usize, usize) {
+    (
+        brace_range.start.byte.saturating_sub(1),
+        brace_range.end.byte,
+    )
+}
@@ -3,34 +3,125 @@ mod html_segments;
 mod output;
 mod print;
 mod python;
+mod stub;
 
 pub use brace_collector::collect_expression_braces;
 pub use html_segments::{
     collect_component_attr_expr_spans, html_segments_for_component, html_segments_for_element,
 };
 pub use output::{
-    ExpressionBrace, Language, Output, Segment, convert_braces_to_utf16, segments_source_to_utf16,
+    ExpressionBrace, Language, Output, Segment, SegmentBytes, Utf16Position, Utf16Range,
+    convert_braces_to_utf16, map_generated_to_source, ranges_to_utf16, segments_source_to_utf16,
     validate_python_segments,
 };
 pub use python::PythonGenerator;
+pub use stub::generate_stub;
 
 use crate::ast::{Ast, FileMode};
+use crate::error::Warning;
+use std::collections::{BTreeSet, HashMap};
 
 /// Generator options
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct CompileOptions {
     pub function_name: Option<String>,
     pub include_ranges: bool,
+    /// One level of indentation in generated Python (spaces or tabs)
+    pub indent: String,
+    /// Props accepted by components defined in other files, keyed by component
+    /// name. Calls passing anything else produce a warning.
+    pub component_props: HashMap<String, Vec<String>>,
+    /// Decorator applied to each generated component function, with or
+    /// without the `@`. `None` emits plain functions (e.g. for a standalone
+    /// page), and calls to the file's own components call them directly
+    /// rather than through `.stream`.
+    pub decorator: Option<String>,
+    /// Text placed verbatim at the top of the generated module, such as a
+    /// "generated file, do not edit" comment
+    pub banner: Option<String>,
+    /// Warn about lines whose indentation mixes tabs and spaces
+    pub strict_indent: bool,
+    /// Collapse whitespace runs in text to one space and drop whitespace
+    /// against block-level tags. Text inside `<pre>`, `<textarea>`,
+    /// `<script>` and `<style>` is kept as written.
+    pub minify: bool,
+    /// End the generated module with exactly one `\n` when true, or with no
+    /// newline at all when false (e.g. for concatenating fragments)
+    pub trailing_newline: bool,
+    /// Generate the file's own component as a `render(self, ...)` method of
+    /// a class with this name, for frameworks that render through objects.
+    /// Its decorators go on the method, and `@component` binds it to the
+    /// instance, so `Page().render(title=...)` renders. Components defined
+    /// with `component` stay module-level functions.
+    pub wrap_in_class: Option<String>,
+    /// Also render a `.pyi` stub of the file's components into
+    /// `CompileResult::stub`
+    pub stub: bool,
+    /// HTML-escape `{expr}` values in content and attributes. Turn off for
+    /// templates that only render trusted data; `{&expr}` still escapes.
+    pub default_escape: bool,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            function_name: None,
+            include_ranges: false,
+            indent: DEFAULT_INDENT.to_string(),
+            component_props: HashMap::new(),
+            decorator: Some(DEFAULT_DECORATOR.to_string()),
+            banner: None,
+            strict_indent: false,
+            minify: false,
+            trailing_newline: true,
+            wrap_in_class: None,
+            stub: false,
+            default_escape: true,
+        }
+    }
+}
+
+impl CompileOptions {
+    /// `decorator` without its leading `@`
+    pub fn decorator_name(&self) -> Option<&str> {
+        self.decorator
+            .as_deref()
+            .map(|name| name.strip_prefix('@').unwrap_or(name))
+    }
 }
 
+/// Indentation unit used when `CompileOptions::indent` is not set
+pub const DEFAULT_INDENT: &str = "    ";
+
+/// The runtime's `component` decorator, the default `CompileOptions::decorator`
+pub const DEFAULT_DECORATOR: &str = "component";
+
 /// Generation result
 #[derive(Debug, Clone)]
 pub struct CompileResult {
     pub code: String,
     pub file_mode: FileMode,
     pub component_name: Option<String>,
+    /// Components the file defines with `component`, in source order
+    pub defined_components: Vec<String>,
+    /// Text of the file's front-matter block, passed through untouched
+    pub front_matter: Option<String>,
     pub segments: Vec<Segment>,
     pub expression_braces: Vec<ExpressionBrace>,
+    pub warnings: Vec<Warning>,
+    /// Every component the file calls, by the name written in its tag
+    pub referenced_components: BTreeSet<String>,
+    /// The `.pyi` stub, when `CompileOptions::stub` is set
+    pub stub: Option<String>,
+}
+
+impl CompileResult {
+    /// The range of each warning in UTF-16 code units, as editors expect,
+    /// in the same order as `warnings`. `source` is the compiled template.
+    /// Warnings themselves keep byte offsets.
+    pub fn warning_ranges_utf16(&self, source: &str) -> Vec<Option<Utf16Range>> {
+        ranges_to_utf16(source, self.warnings.iter().map(|warning| &warning.range))
+    }
 }
 
 /// Generator trait - converts AST to code
@@ -1,9 +1,13 @@
+use crate::ast::{Position, TextRange};
+
 /// Injection language for IDE language injection
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Language {
     Python,
     Html,
+    /// Literal template text, for editor scoping only; never injected
+    Template,
 }
 
 /// Source-to-compiled span. Source offsets are UTF-16 (after
@@ -22,6 +26,19 @@ pub struct Segment {
     /// that need a synthetic tag name so the HTML parser can highlight attributes).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub html_prefix: Option<String>,
+    /// The same spans as UTF-8 byte offsets, for slicing the source and
+    /// compiled strings directly. Filled in by `segments_source_to_utf16`.
+    #[serde(flatten)]
+    pub bytes: Option<SegmentBytes>,
+}
+
+/// Byte offsets of a `Segment`, alongside its UTF-16 ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct SegmentBytes {
+    pub source_byte_start: usize,
+    pub source_byte_end: usize,
+    pub compiled_byte_start: usize,
+    pub compiled_byte_end: usize,
 }
 
 /// Expression brace position in source (UTF-16 offsets)
@@ -74,15 +91,136 @@ fn normalize_indent(s: &str) -> String {
 }
 
 /// Convert each segment's `source_start`/`source_end` from byte to UTF-16 offsets.
-/// Compiled offsets are left untouched (already UTF-16).
-pub fn segments_source_to_utf16(source: &str, segments: &mut [Segment]) {
+/// Compiled offsets are left untouched (already UTF-16). Both sides' byte
+/// offsets are kept in `Segment::bytes`.
+pub fn segments_source_to_utf16(source: &str, compiled: &str, segments: &mut [Segment]) {
     let byte_to_utf16 = build_byte_to_utf16_map(source);
+    let compiled_utf16_to_byte = build_utf16_to_byte_map(compiled);
     for seg in segments {
+        seg.bytes = Some(SegmentBytes {
+            source_byte_start: seg.source_start,
+            source_byte_end: seg.source_end,
+            compiled_byte_start: compiled_utf16_to_byte[seg.compiled_start],
+            compiled_byte_end: compiled_utf16_to_byte[seg.compiled_end],
+        });
         seg.source_start = byte_to_utf16[seg.source_start];
         seg.source_end = byte_to_utf16[seg.source_end];
     }
 }
 
+/// A source position in editor (LSP) coordinates. `line` is 0-based;
+/// `offset` and `character` count UTF-16 code units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct Utf16Position {
+    pub offset: usize,
+    pub line: usize,
+    pub character: usize,
+}
+
+/// A `TextRange` converted to UTF-16 code units
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct Utf16Range {
+    pub start: Utf16Position,
+    pub end: Utf16Position,
+}
+
+/// Convert byte-based `ranges` over `source` to UTF-16, in the same order.
+/// Synthetic ranges have no place in the source and convert to `None`.
+pub fn ranges_to_utf16<'a>(
+    source: &str,
+    ranges: impl IntoIterator<Item = &'a TextRange>,
+) -> Vec<Option<Utf16Range>> {
+    let byte_to_utf16 = build_byte_to_utf16_map(source);
+    let position = |pos: Position| {
+        let line_start = source[..pos.byte].rfind('\n').map_or(0, |i| i + 1);
+        Utf16Position {
+            offset: byte_to_utf16[pos.byte],
+            line: pos.line,
+            character: byte_to_utf16[pos.byte] - byte_to_utf16[line_start],
+        }
+    };
+    ranges
+        .into_iter()
+        .map(|range| {
+            (!range.is_synthetic()).then(|| Utf16Range {
+                start: position(range.start),
+                end: position(range.end),
+            })
+        })
+        .collect()
+}
+
+/// Map a position in compiled Python back to the `.hyper` source, e.g. to
+/// rewrite the line numbers of a runtime traceback.
+///
+/// Lines are 0-based and columns are UTF-16 code units, in both directions.
+/// `segments` are those of a `CompileResult` built with `include_ranges`.
+/// Positions that fall between segments map to the nearest Python segment
+/// before them, unless that segment sits on an earlier line and one starts
+/// later on the same line: a generated line such as a component call then
+/// maps to its own tag. Positions before the first segment or past the end of
+/// `compiled` return `None`.
+pub fn map_generated_to_source(
+    source: &str,
+    compiled: &str,
+    segments: &[Segment],
+    gen_line: usize,
+    gen_col: usize,
+) -> Option<(usize, usize)> {
+    let offset = line_col_to_utf16(compiled, gen_line, gen_col)?;
+
+    let mut python: Vec<&Segment> = segments
+        .iter()
+        .filter(|s| s.language == Language::Python && s.compiled_end > s.compiled_start)
+        .collect();
+    python.sort_by_key(|s| (s.compiled_start, s.compiled_end));
+
+    let line_start = offset - gen_col;
+    let line_end = line_start + compiled.split('\n').nth(gen_line)?.encode_utf16().count();
+    let index = python.partition_point(|s| s.compiled_start <= offset);
+    let later_on_line = python.get(index).filter(|s| s.compiled_start < line_end);
+    let segment = match index.checked_sub(1).map(|i| python[i]) {
+        Some(before) if before.compiled_end >= line_start => before,
+        before => match later_on_line {
+            Some(next) => return Some(utf16_to_line_col(source, next.source_start)),
+            None => before?,
+        },
+    };
+    let delta = (offset - segment.compiled_start).min(segment.source_end - segment.source_start);
+    Some(utf16_to_line_col(source, segment.source_start + delta))
+}
+
+/// UTF-16 offset of a 0-based line and column, or `None` if out of range.
+fn line_col_to_utf16(s: &str, line: usize, col: usize) -> Option<usize> {
+    let mut offset = 0;
+    for (i, text) in s.split('\n').enumerate() {
+        let len: usize = text.chars().map(char::len_utf16).sum();
+        if i == line {
+            return (col <= len).then_some(offset + col);
+        }
+        offset += len + 1;
+    }
+    None
+}
+
+/// 0-based line and UTF-16 column of a UTF-16 offset.
+fn utf16_to_line_col(s: &str, offset: usize) -> (usize, usize) {
+    let (mut line, mut col, mut pos) = (0, 0, 0);
+    for ch in s.chars() {
+        if pos >= offset {
+            break;
+        }
+        pos += ch.len_utf16();
+        if ch == '\n' {
+            line += 1;
+            col = 0;
+        } else {
+            col += ch.len_utf16();
+        }
+    }
+    (line, col)
+}
+
 /// Build a mapping from byte offset → UTF-16 code unit offset for a string.
 /// The returned Vec has len = s.len() + 1 (to handle end-of-string positions).
 fn build_byte_to_utf16_map(s: &str) -> Vec<usize> {
@@ -96,6 +234,17 @@ fn build_byte_to_utf16_map(s: &str) -> Vec<usize> {
     map
 }
 
+/// Inverse of `build_byte_to_utf16_map`. The low half of a surrogate pair
+/// maps to the byte where its character starts.
+fn build_utf16_to_byte_map(s: &str) -> Vec<usize> {
+    let mut map = Vec::with_capacity(s.len() + 1);
+    for (byte_pos, ch) in s.char_indices() {
+        map.extend(std::iter::repeat_n(byte_pos, ch.len_utf16()));
+    }
+    map.push(s.len());
+    map
+}
+
 /// Extract substring by UTF-16 positions
 fn substring_utf16(s: &str, start: usize, end: usize) -> String {
     if start >= end {
@@ -109,6 +258,28 @@ fn substring_utf16(s: &str, start: usize, end: usize) -> String {
     String::from_utf16_lossy(&utf16_units[start..end])
 }
 
+/// Merge each segment into the one before it when they're the same kind and
+/// pick up exactly where it left off in both source and compiled code, so
+/// editors get one injection per contiguous run instead of many.
+fn coalesce_segments(segments: Vec<Segment>) -> Vec<Segment> {
+    let mut merged: Vec<Segment> = Vec::with_capacity(segments.len());
+    for seg in segments {
+        if let Some(last) = merged.last_mut()
+            && last.language == seg.language
+            && last.needs_injection == seg.needs_injection
+            && last.html_prefix == seg.html_prefix
+            && last.source_end == seg.source_start
+            && last.compiled_end == seg.compiled_start
+        {
+            last.source_end = seg.source_end;
+            last.compiled_end = seg.compiled_end;
+            continue;
+        }
+        merged.push(seg);
+    }
+    merged
+}
+
 /// Output buffer that accumulates generated code with segments.
 ///
 /// Supports formatting-aware position tracking via `skip_next()` and
@@ -127,6 +298,7 @@ pub struct Output {
     skip_remaining: usize, // characters left to skip (for leading whitespace)
     dedent_amount: usize,  // spaces to strip at each line start (0 = inactive)
     dedent_skip_remaining: usize, // spaces left to strip on current content line
+    indent_unit: String,   // one level of Python indentation
 }
 
 impl Output {
@@ -140,9 +312,29 @@ impl Output {
             skip_remaining: 0,
             dedent_amount: 0,
             dedent_skip_remaining: 0,
+            indent_unit: super::DEFAULT_INDENT.to_string(),
         }
     }
 
+    /// Output that indents generated Python with `unit` per level.
+    pub fn with_indent(unit: &str) -> Self {
+        Self {
+            indent_unit: unit.to_string(),
+            ..Self::new()
+        }
+    }
+
+    /// `level` repetitions of the indentation unit.
+    pub fn indentation(&self, level: usize) -> String {
+        self.indent_unit.repeat(level)
+    }
+
+    /// Push indentation for a line at `level`.
+    pub fn indent(&mut self, level: usize) {
+        let indentation = self.indentation(level);
+        self.push(&indentation);
+    }
+
     /// Record a runtime helper as used (e.g. `escape`, `render_class`, `safe`).
     pub fn use_helper(&mut self, name: &str) {
         self.helpers.insert(name.to_string());
@@ -216,7 +408,7 @@ impl Output {
         }
 
         let code = self.lines.join("");
-        (code, self.segments)
+        (code, coalesce_segments(self.segments))
     }
 
     /// Transfer segments from another Output, adjusting compiled positions by an offset.
@@ -248,6 +440,19 @@ impl Output {
         self.dedent_skip_remaining = 0; // first line is not dedented
     }
 
+    /// Suspend dedent mode, returning the amount for `resume_dedent`.
+    pub fn pause_dedent(&mut self) -> usize {
+        let amount = self.dedent_amount;
+        self.end_dedent();
+        amount
+    }
+
+    /// Resume dedent mode after `pause_dedent`. Like `begin_dedent`, the
+    /// current line is left alone.
+    pub fn resume_dedent(&mut self, n: usize) {
+        self.begin_dedent(n);
+    }
+
     /// End dedent mode.
     pub fn end_dedent(&mut self) {
         self.dedent_amount = 0;
@@ -31,6 +31,7 @@ pub fn print_code(output: &mut Output, code: &Code) {
             compiled_end: end,
             needs_injection: true,
             html_prefix: None,
+            bytes: None,
         });
     }
 }
@@ -61,6 +62,18 @@ pub fn print_expr(output: &mut Output, expr: &Expr) {
             }
             output.push(")");
         }
+        Expr::Dict(dict) => {
+            output.push("{");
+            for (i, item) in dict.items.iter().enumerate() {
+                if i > 0 {
+                    output.push(", ");
+                }
+                print_expr(output, &item.key);
+                output.push(": ");
+                print_expr(output, &item.value);
+            }
+            output.push("}");
+        }
     }
 }
 
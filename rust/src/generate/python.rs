@@ -1,14 +1,19 @@
 use super::{
-    CompileOptions, CompileResult, Generator, Language, Output, Segment,
+    CompileOptions, CompileResult, DEFAULT_DECORATOR, Generator, Language, Output, Segment,
     collect_component_attr_expr_spans, collect_expression_braces, convert_braces_to_utf16,
-    html_segments_for_component, html_segments_for_element,
+    generate_stub, html_segments_for_component, html_segments_for_element,
 };
-use crate::ast::python::{Alias, Code, Identifier, StmtImportFrom};
+use crate::ast::python::{Alias, Code, Expr, Identifier, StmtImportFrom};
 use crate::ast::*;
 use crate::generate::print::{print_code, print_expr, print_import_from};
 use crate::html;
-use crate::lower::{code_span, helper_call, lower_interpolation, render_attr_call};
+use crate::lower::{
+    code_span, helper_call, lower_attr_value, lower_interpolation, prefixed_attr_call,
+    render_attr_call, render_optional_attr_call,
+};
 use crate::plugins::{DEFAULT_SLOT_PARAM, Helper, rename_reserved_keywords, slot_param_name};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashSet};
 
 /// Where a dynamic attribute's helper call lands in the f-string.
 enum Scaffold<'a> {
@@ -18,11 +23,32 @@ enum Scaffold<'a> {
     Whole,
 }
 
-pub struct PythonGenerator;
+pub struct PythonGenerator {
+    /// Whether the generator function being emitted is `async def`, where
+    /// `yield from` is not allowed
+    async_scope: Cell<bool>,
+    /// Whether text whitespace is being collapsed: `CompileOptions::minify`,
+    /// switched off inside elements whose whitespace is content
+    minify: Cell<bool>,
+    /// `CompileOptions::default_escape`
+    default_escape: Cell<bool>,
+    /// Slot functions already defined in the Python function being emitted,
+    /// so a repeated name gets a counter instead of shadowing the first
+    slot_functions: RefCell<HashSet<String>>,
+    /// Components this file defines without a decorator: plain generator
+    /// functions, called directly instead of through `.stream`
+    plain_components: RefCell<HashSet<String>>,
+}
 
 impl PythonGenerator {
     pub fn new() -> Self {
-        Self
+        Self {
+            async_scope: Cell::new(false),
+            minify: Cell::new(false),
+            default_escape: Cell::new(true),
+            slot_functions: RefCell::new(HashSet::new()),
+            plain_components: RefCell::new(HashSet::new()),
+        }
     }
 
     /// Check if a list of nodes contains only whitespace/newline text (no real content)
@@ -53,6 +79,22 @@ impl PythonGenerator {
         }
     }
 
+    /// A generator body that yields nothing. Async generators can't
+    /// `yield from`, so they return before a bare `yield` that only makes
+    /// them generators.
+    fn emit_empty_generator(&self, output: &mut Output, indent: usize) {
+        self.indent(output, indent);
+        if self.async_scope.get() {
+            output.push("return");
+            output.newline();
+            self.indent(output, indent);
+            output.push("yield");
+        } else {
+            output.push("yield from ()");
+        }
+        output.newline();
+    }
+
     /// Check if a node can be combined into a string literal (not control flow)
     fn is_combinable(&self, node: &Node) -> bool {
         match node {
@@ -61,12 +103,28 @@ impl PythonGenerator {
                 // Element is combinable only if all its children are combinable
                 el.children.iter().all(|child| self.is_combinable(child))
             }
+            Node::Fragment(fragment) => {
+                !fragment.children.is_empty()
+                    && fragment
+                        .children
+                        .iter()
+                        .all(|child| self.is_combinable(child))
+            }
             _ => false, // Components, Slots, control flow, etc. are not combinable
         }
     }
 
     /// Emit a group of nodes, combining consecutive text/expression nodes into f-strings
     fn emit_nodes(&self, nodes: &[&Node], output: &mut Output, indent: usize) {
+        // Fragments render their children in place, so those join the runs
+        // around them. Empty ones stand in for hoisted definitions.
+        if nodes
+            .iter()
+            .any(|node| matches!(node, Node::Fragment(f) if !f.children.is_empty()))
+        {
+            let flat = flatten_fragments(nodes);
+            return self.emit_nodes(&flat, output, indent);
+        }
         let mut i = 0;
         while i < nodes.len() {
             // Check if this node and following nodes can be combined into a string
@@ -90,8 +148,21 @@ impl PythonGenerator {
                     None
                 };
 
+                // Spaces between this run and a component or an inline
+                // assignment on the same line are content: `Hi <{Badge} />
+                // there` and `Hi { n = len(xs) } there` keep both.
+                let flows = |node: &Node| match node {
+                    Node::Component(_) => true,
+                    Node::Statement(statement) => statement.inline,
+                    _ => false,
+                };
+                let inline = InlineNeighbors {
+                    before: i > 0 && flows(nodes[i - 1]),
+                    after: j < nodes.len() && flows(nodes[j]),
+                };
+
                 // Emit combined nodes as a single string/f-string
-                self.emit_combined_nodes(&nodes[i..j], output, indent, trailing_comment);
+                self.emit_combined_nodes(&nodes[i..j], output, indent, trailing_comment, inline);
                 i = j;
                 if trailing_comment.is_some() {
                     i += 1; // skip the comment we already emitted inline
@@ -122,12 +193,17 @@ impl PythonGenerator {
                     .iter()
                     .any(|child| self.node_has_expressions(child))
             }
+            Node::Fragment(fragment) => fragment
+                .children
+                .iter()
+                .any(|child| self.node_has_expressions(child)),
             _ => false,
         }
     }
 
     /// Emit consecutive text/expression/element nodes as a single yield statement.
     /// If trailing_comment is Some, the comment is appended inline after the closing `"""`.
+    /// Whitespace next to an `inline` neighbor is kept unless it spans a line break.
     ///
     /// Uses a two-phase approach:
     ///   Phase 1: Emit to a temp buffer for content analysis (segments discarded).
@@ -138,6 +214,7 @@ impl PythonGenerator {
         output: &mut Output,
         indent: usize,
         trailing_comment: Option<&CommentNode>,
+        inline: InlineNeighbors,
     ) {
         let has_expressions = nodes.iter().any(|node| self.node_has_expressions(node));
 
@@ -145,11 +222,16 @@ impl PythonGenerator {
         // Emit to a temp buffer to get the raw content string.
         // Segments from this pass are discarded.
         let mut temp = Output::new();
-        for node in nodes {
-            self.emit_node_content(node, &mut temp, has_expressions);
-        }
+        self.emit_run_content(nodes, &mut temp, has_expressions, inline);
         let (content, _) = temp.finish();
-        let info = analyze_combined_content(&content);
+        let mut info = analyze_combined_content(&content);
+        let leading_ws = &content[..content.len() - content.trim_start().len()];
+        let trailing_ws = &content[content.trim_end().len()..];
+        let keep_leading = inline.before && !leading_ws.is_empty() && !leading_ws.contains('\n');
+        let keep_trailing = inline.after && !trailing_ws.is_empty() && !trailing_ws.contains('\n');
+        if keep_leading {
+            info.leading_skip = 0;
+        }
 
         // If content is empty after trimming, just emit blank lines.
         // The first newline is structural (line break between parent and child),
@@ -199,9 +281,7 @@ impl PythonGenerator {
             output.begin_dedent(info.anchor_indent);
         }
 
-        for node in nodes {
-            self.emit_node_content(node, output, has_expressions);
-        }
+        self.emit_run_content(nodes, output, has_expressions, inline);
 
         if info.anchor_indent > 0 {
             output.end_dedent();
@@ -213,7 +293,7 @@ impl PythonGenerator {
         // Otherwise trim everything so """ stays on the content line.
         if info.is_multiline && info.has_trailing_newline {
             output.trim_trailing_spaces();
-        } else {
+        } else if !keep_trailing {
             output.trim_trailing();
         }
 
@@ -231,22 +311,73 @@ impl PythonGenerator {
         }
     }
 
-    /// Emit the content of a node as part of a string literal
-    fn emit_node_content(&self, node: &Node, output: &mut Output, in_fstring: bool) {
-        match node {
-            Node::Text(text) => {
+    /// Emit sibling nodes as part of a string literal. When minifying, each
+    /// stretch of text is collapsed as a whole, losing its whitespace against
+    /// a block-level element or either end of the run unless that end borders
+    /// an `inline` neighbor. Whitespace against an inline element like `<b>`
+    /// renders as a space, so one is kept.
+    fn emit_run_content(
+        &self,
+        nodes: &[&Node],
+        output: &mut Output,
+        in_fstring: bool,
+        inline: InlineNeighbors,
+    ) {
+        let mut i = 0;
+        while i < nodes.len() {
+            if !matches!(nodes[i], Node::Text(_)) {
+                self.emit_node_content(nodes[i], output, in_fstring);
+                i += 1;
+                continue;
+            }
+            let mut j = i;
+            let mut content = String::new();
+            let mut texts = Vec::new();
+            while let Some(Node::Text(text)) = nodes.get(j) {
+                content.push_str(&text.content);
+                texts.push(text);
+                j += 1;
+            }
+            if self.minify.get() {
+                let is_block = |node: &Node| matches!(node, Node::Element(el) if html::is_block_element(&el.tag));
+                let trim_start = match i.checked_sub(1) {
+                    Some(prev) => is_block(nodes[prev]),
+                    None => !inline.before,
+                };
+                let trim_end = match nodes.get(j) {
+                    Some(next) => is_block(next),
+                    None => !inline.after,
+                };
+                content = collapse_whitespace(&content, trim_start, trim_end);
+            }
+            push_template_text(output, &content, &texts, |text| {
+                // A backslash is literal text, never an escape or a line
+                // continuation in the generated string
+                let text = text.replace('\\', "\\\\");
                 if in_fstring {
                     // Escape braces so they're literal in the f-string
-                    output.push(&text.content.replace('{', "{{").replace('}', "}}"));
+                    escape_fstring_braces(&text)
                 } else {
-                    output.push(&text.content);
+                    text
                 }
+            });
+            i = j;
+        }
+    }
+
+    /// Emit the content of a node as part of a string literal
+    fn emit_node_content(&self, node: &Node, output: &mut Output, in_fstring: bool) {
+        match node {
+            Node::Text(_) => {
+                self.emit_run_content(&[node], output, in_fstring, InlineNeighbors::NONE)
             }
             Node::Expression(expr) if in_fstring => {
                 if expr.expr.contains("safe(") {
                     output.use_helper("safe");
                 }
-                if let Some(lowered) = lower_interpolation(expr) {
+                if self.escapes(expr.escape, expr.force_escape)
+                    && let Some(lowered) = lower_interpolation(expr)
+                {
                     output.push("{");
                     print_expr(output, &lowered);
                     output.push("}");
@@ -275,17 +406,17 @@ impl PythonGenerator {
                     output.push("}");
                     (start, end)
                 } else {
-                    let start = output.position();
                     output.push("{");
+                    let start = output.position();
                     output.push(&expr.expr);
-                    output.push("}");
                     let end = output.position();
+                    output.push("}");
                     (start, end)
                 };
 
                 // Source segment excludes braces, just the inner expression
-                let content_start = expr.range.start.byte + 1; // skip '{'
-                let content_end = expr.range.end.byte - 1; // skip '}'
+                // Source excludes braces and whitespace-control markers
+                let (content_start, content_end) = expr.code_span();
 
                 output.add_segment(Segment {
                     language: Language::Python,
@@ -295,11 +426,16 @@ impl PythonGenerator {
                     compiled_end: end,
                     needs_injection: true,
                     html_prefix: None,
+                    bytes: None,
                 });
             }
             Node::Element(el) => {
                 self.emit_element_content(el, output, in_fstring);
             }
+            Node::Fragment(fragment) => {
+                let children: Vec<&Node> = fragment.children.iter().collect();
+                self.emit_run_content(&children, output, in_fstring, InlineNeighbors::NONE);
+            }
             _ => {}
         }
     }
@@ -323,9 +459,24 @@ impl PythonGenerator {
         } else {
             output.push(">");
 
-            // Emit children content
-            for child in &el.children {
-                self.emit_node_content(child, output, in_fstring);
+            // Emit children content. <pre> keeps its lines exactly as written,
+            // so the anchor dedent of the surrounding block doesn't apply.
+            let is_pre = el.tag.eq_ignore_ascii_case("pre");
+            let dedent = if is_pre { output.pause_dedent() } else { 0 };
+            let outer_minify = self
+                .minify
+                .replace(self.minify.get() && !keeps_whitespace(&el.tag));
+            let children: Vec<&Node> = el.children.iter().collect();
+            // Inside an inline element, whitespace at either end renders too
+            let inline = !html::is_block_element(&el.tag);
+            let neighbors = InlineNeighbors {
+                before: inline,
+                after: inline,
+            };
+            self.emit_run_content(&children, output, in_fstring, neighbors);
+            self.minify.set(outer_minify);
+            if is_pre {
+                output.resume_dedent(dedent);
             }
 
             output.push("</");
@@ -352,7 +503,12 @@ impl PythonGenerator {
                 output.push(" ");
                 output.push(name);
                 output.push("=\"");
-                output.push(&escape_html_attr_quotes(value));
+                let value = escape_attr_text(value);
+                if in_fstring {
+                    output.push(&escape_fstring_braces(&value));
+                } else {
+                    output.push(&value);
+                }
                 output.push("\"");
                 return;
             }
@@ -367,52 +523,37 @@ impl PythonGenerator {
                     output.push(" ");
                     output.push(name);
                     output.push("=\"");
-                    // Emit template value with position tracking for each {expr}
                     // value_start_byte: skip past `name="` in the source
                     let value_start_byte = attr.range.start.byte + name.len() + 2;
-                    let mut byte_offset = 0;
-                    let mut chars = value.chars().peekable();
-                    #[allow(clippy::while_let_on_iterator)]
-                    while let Some(ch) = chars.next() {
-                        if ch == '{' {
-                            // Collect expression until closing }
-                            let expr_byte_start = byte_offset + 1; // past '{'
-                            byte_offset += ch.len_utf8();
-                            let mut expr = String::new();
-                            let mut depth = 1;
-                            while let Some(inner) = chars.next() {
-                                byte_offset += inner.len_utf8();
-                                if inner == '{' {
-                                    depth += 1;
-                                    expr.push(inner);
-                                } else if inner == '}' {
-                                    depth -= 1;
-                                    if depth == 0 {
-                                        break;
-                                    }
-                                    expr.push(inner);
-                                } else {
-                                    expr.push(inner);
-                                }
+                    for part in template_parts(value) {
+                        let (code, range) = match part {
+                            TemplatePart::Text(text) => {
+                                output.push(&escape_attr_text(&escape_fstring_braces(&text)));
+                                continue;
                             }
-                            let expr_byte_end = byte_offset - 1; // before '}'
-                            // Template value is parsed here, so rename the extracted expr.
-                            let safe_expr = rename_reserved_keywords(expr.trim());
-                            output.push("{");
-                            let code = code_span(
-                                safe_expr,
-                                value_start_byte + expr_byte_start,
-                                value_start_byte + expr_byte_end,
-                            );
+                            TemplatePart::Expression { code, range } => (code, range),
+                        };
+                        // `{!expr}` opts out of escaping and `{&expr}` in, as
+                        // in content
+                        let (expr, start) = match code.strip_prefix(['!', '&']) {
+                            Some(rest) => (rest, range.start + 1),
+                            None => (code, range.start),
+                        };
+                        let escape = self.escapes(!code.starts_with('!'), code.starts_with('&'));
+                        // Template value is parsed here, so rename the extracted expr.
+                        let safe_expr = rename_reserved_keywords(expr.trim());
+                        output.push("{");
+                        let code = code_span(
+                            safe_expr,
+                            value_start_byte + start,
+                            value_start_byte + range.end,
+                        );
+                        if escape {
                             print_expr(output, &helper_call("escape", code));
-                            output.push("}");
-                        } else if ch == '"' {
-                            output.push("&quot;");
-                            byte_offset += ch.len_utf8();
                         } else {
-                            output.push(&ch.to_string());
-                            byte_offset += ch.len_utf8();
+                            print_expr(output, &Expr::Code(code));
                         }
+                        output.push("}");
                     }
                     output.push("\"");
                 }
@@ -423,23 +564,27 @@ impl PythonGenerator {
                 name,
                 expr,
                 expr_range,
+                escape,
+                force_escape,
+                optional,
+                ..
             } => {
                 if !in_fstring {
                     return;
                 }
                 // Already renamed in the AST by ReservedKeywordPlugin.
-                let safe_expr = expr.trim().to_string();
-                // expr_range includes {expr}, skip braces for injection.
-                let content_start = expr_range.start.byte + 1;
-                let content_end = expr_range.end.byte - 1;
-                let code = code_span(safe_expr, content_start, content_end);
+                let code = trimmed_code(expr, expr_range.start.byte, expr_range.end.byte);
                 match name.as_str() {
+                    _ if *optional => (Scaffold::Whole, render_optional_attr_call(name, code)),
                     "class" => (Scaffold::Value(name), helper_call("render_class", code)),
                     "style" => (Scaffold::Value(name), helper_call("render_style", code)),
                     n if self.is_boolean_attribute(n) => {
                         (Scaffold::Whole, render_attr_call(name, code))
                     }
-                    _ => (Scaffold::Value(name), helper_call("escape", code)),
+                    _ => (
+                        Scaffold::Value(name),
+                        lower_attr_value(code, self.escapes(*escape, *force_escape)),
+                    ),
                 }
             }
 
@@ -448,18 +593,33 @@ impl PythonGenerator {
                     return;
                 }
                 // Shorthand maps one AST field to two outputs: HTML attr name
-                // stays, Python value variable renames. Rename here.
-                let var_name = rename_reserved_keywords(name);
+                // stays, Python value variable renames. Rename here; `{data-id}`
+                // reads the variable `data_id`.
+                let var_name = rename_reserved_keywords(&name.replace('-', "_"));
                 // Shorthand expr_range.end points TO closing brace (not past it),
                 // so content_end = end.byte gives exclusive end of name content.
-                let content_start = expr_range.start.byte + 1;
-                let content_end = expr_range.end.byte;
-                let code = code_span(var_name, content_start, content_end);
+                // A renamed variable isn't the source text, so it's not injected.
+                let code = if var_name == *name {
+                    code_span(var_name, expr_range.start.byte + 1, expr_range.end.byte)
+                } else {
+                    Code {
+                        source: var_name,
+                        range: TextRange::synthetic(),
+                    }
+                };
                 match name.as_str() {
                     "class" => (Scaffold::Value(name), helper_call("render_class", code)),
                     "style" => (Scaffold::Value(name), helper_call("render_style", code)),
                     "data" => (Scaffold::Whole, helper_call("render_data", code)),
                     "aria" => (Scaffold::Whole, helper_call("render_aria", code)),
+                    n if n.starts_with("data-") => (
+                        Scaffold::Whole,
+                        prefixed_attr_call("render_data", &n["data-".len()..], code),
+                    ),
+                    n if n.starts_with("aria-") => (
+                        Scaffold::Whole,
+                        prefixed_attr_call("render_aria", &n["aria-".len()..], code),
+                    ),
                     _ => (Scaffold::Whole, render_attr_call(name, code)),
                 }
             }
@@ -469,11 +629,8 @@ impl PythonGenerator {
                     return;
                 }
                 // Already renamed in the AST by ReservedKeywordPlugin.
-                let safe_expr = expr.trim().to_string();
                 // Spread expr_range is {**expr}; skip 3 chars for "{**".
-                let content_start = expr_range.start.byte + 3;
-                let content_end = expr_range.end.byte;
-                let code = code_span(safe_expr, content_start, content_end);
+                let code = trimmed_code(expr, expr_range.start.byte + 3, expr_range.end.byte);
                 (Scaffold::Whole, helper_call("spread_attrs", code))
             }
         };
@@ -499,42 +656,36 @@ impl PythonGenerator {
     /// Also escapes double quotes in static parts as &quot; for valid HTML attributes.
     fn convert_template_expressions(&self, template: &str) -> String {
         let mut result = String::new();
-        let mut chars = template.chars().peekable();
-
-        #[allow(clippy::while_let_on_iterator)]
-        while let Some(ch) = chars.next() {
-            if ch == '{' {
-                // Found start of expression, collect until closing }
-                let mut expr = String::new();
-                let mut depth = 1;
-                while let Some(inner) = chars.next() {
-                    if inner == '{' {
-                        depth += 1;
-                        expr.push(inner);
-                    } else if inner == '}' {
-                        depth -= 1;
-                        if depth == 0 {
-                            break;
-                        }
-                        expr.push(inner);
+        for part in template_parts(template) {
+            match part {
+                TemplatePart::Text(text) => {
+                    result.push_str(&escape_attr_text(&escape_fstring_braces(&text)));
+                }
+                // Emit as direct escape() call, unless marked `{!expr}` or
+                // escaping is off by default
+                TemplatePart::Expression { code, .. } => {
+                    let expr = code.strip_prefix(['!', '&']).unwrap_or(code);
+                    if self.escapes(!code.starts_with('!'), code.starts_with('&')) {
+                        result.push_str("{escape(");
+                        result.push_str(expr);
+                        result.push_str(")}");
                     } else {
-                        expr.push(inner);
+                        result.push('{');
+                        result.push_str(expr);
+                        result.push('}');
                     }
                 }
-                // Emit as direct escape() call
-                result.push_str("{escape(");
-                result.push_str(&expr);
-                result.push_str(")}");
-            } else if ch == '"' {
-                result.push_str("&quot;");
-            } else {
-                result.push(ch);
             }
         }
-
         result
     }
 
+    /// Whether a value is HTML-escaped: `{!expr}` never is, `{&expr}` always
+    /// is, and a plain `{expr}` follows `CompileOptions::default_escape`
+    fn escapes(&self, escape: bool, force_escape: bool) -> bool {
+        force_escape || (escape && self.default_escape.get())
+    }
+
     fn emit_node(&self, node: &Node, output: &mut Output, indent: usize) {
         match node {
             Node::Text(text) => self.emit_text(text, output, indent),
@@ -561,7 +712,7 @@ impl PythonGenerator {
     fn emit_text(&self, text: &TextNode, output: &mut Output, indent: usize) {
         self.indent(output, indent);
         output.push("yield \"");
-        output.push(&escape_string(&text.content));
+        push_template_text(output, &text.content, &[text], escape_string);
         output.push("\"");
         output.newline();
     }
@@ -577,7 +728,9 @@ impl PythonGenerator {
         if expr.expr.contains("safe(") {
             output.use_helper("safe");
         }
-        if let Some(lowered) = lower_interpolation(expr) {
+        if self.escapes(expr.escape, expr.force_escape)
+            && let Some(lowered) = lower_interpolation(expr)
+        {
             output.push("yield ");
             print_expr(output, &lowered);
             output.newline();
@@ -604,15 +757,17 @@ impl PythonGenerator {
                 output.push(spec);
             }
             output.push("}\"");
-            // Source range excludes braces: range.start + 1 to range.end - 1
+            // Source range excludes braces
+            let (source_start, source_end) = expr.code_span();
             output.add_segment(Segment {
                 language: Language::Python,
-                source_start: expr.range.start.byte + 1,
-                source_end: expr.range.end.byte - 1,
+                source_start,
+                source_end,
                 compiled_start: start,
                 compiled_end: end,
                 needs_injection: true,
                 html_prefix: None,
+                bytes: None,
             });
         } else {
             output.push("yield str(");
@@ -620,14 +775,16 @@ impl PythonGenerator {
             output.push(&expr.expr);
             let end = output.position();
             output.push(")");
+            let (source_start, source_end) = expr.code_span();
             output.add_segment(Segment {
                 language: Language::Python,
-                source_start: expr.range.start.byte + 1,
-                source_end: expr.range.end.byte - 1,
+                source_start,
+                source_end,
                 compiled_start: start,
                 compiled_end: end,
                 needs_injection: true,
                 html_prefix: None,
+                bytes: None,
             });
         }
         output.newline();
@@ -671,7 +828,11 @@ impl PythonGenerator {
 
             // Emit children using emit_nodes for proper grouping
             let refs: Vec<&Node> = el.children.iter().collect();
+            let outer_minify = self
+                .minify
+                .replace(self.minify.get() && !keeps_whitespace(&el.tag));
             self.emit_nodes(&refs, output, indent);
+            self.minify.set(outer_minify);
 
             // Closing tag
             self.indent(output, indent);
@@ -713,10 +874,38 @@ impl PythonGenerator {
         result
     }
 
+    /// `component_to_func_name`, with `_2`, `_3`, ... appended when the name
+    /// is already taken in the current Python function
+    fn unique_slot_function(&self, component: &str, slot: Option<&str>) -> String {
+        let base = self.component_to_func_name(component, slot);
+        let mut taken = self.slot_functions.borrow_mut();
+        let name = (1..)
+            .map(|n| match n {
+                1 => base.clone(),
+                n => format!("{base}_{n}"),
+            })
+            .find(|name| !taken.contains(name))
+            .unwrap();
+        taken.insert(name.clone());
+        name
+    }
+
+    /// Emit `body` as the body of a new Python function, whose slot function
+    /// names start afresh
+    fn emit_function_body(&self, body: &[Node], output: &mut Output, indent: usize) {
+        let outer = self.slot_functions.take();
+        self.emit_body_or_pass(body, output, indent);
+        self.slot_functions.replace(outer);
+    }
+
     fn emit_component(&self, c: &ComponentNode, output: &mut Output, indent: usize) {
-        let has_content = !c.children.is_empty();
         let mut named_slots: Vec<_> = c.slots.iter().collect();
         named_slots.sort_by_key(|(name, _)| *name);
+        // `<{X}></{X}>` passes empty content, unlike `<{X} />` which passes none
+        let empty_content = c.close_range.is_some()
+            && named_slots.is_empty()
+            && self.is_effectively_empty(&c.children.iter().collect::<Vec<_>>());
+        let has_content = !c.children.is_empty() || empty_content;
         let has_body = has_content || !named_slots.is_empty();
 
         if has_body {
@@ -727,48 +916,70 @@ impl PythonGenerator {
             output.newline();
         }
 
-        if has_content {
-            let func_name = self.component_to_func_name(&c.name, None);
+        let slot_def = if c.async_bodies { "async def " } else { "def " };
+        let outer_scope = self.async_scope.replace(c.async_bodies);
+        let content_function = has_content.then(|| self.unique_slot_function(&c.name, None));
+        if let Some(func_name) = &content_function {
             self.indent(output, indent);
-            output.push("def ");
-            output.push(&func_name);
+            output.push(slot_def);
+            output.push(func_name);
             output.push("():");
             output.newline();
-            self.emit_body_or_pass(&c.children, output, indent + 1);
+            if empty_content {
+                self.emit_empty_generator(output, indent + 1);
+            } else {
+                self.emit_function_body(&c.children, output, indent + 1);
+            }
         }
 
+        let mut slot_functions = Vec::with_capacity(named_slots.len());
         for (name, body) in &named_slots {
-            let func_name = self.component_to_func_name(&c.name, Some(name));
+            let func_name = self.unique_slot_function(&c.name, Some(name));
             self.indent(output, indent);
-            output.push("def ");
+            output.push(slot_def);
             output.push(&func_name);
             output.push("():");
             output.newline();
-            self.emit_body_or_pass(body, output, indent + 1);
+            self.emit_function_body(body, output, indent + 1);
+            slot_functions.push((name, func_name));
         }
 
+        self.async_scope.set(outer_scope);
+
+        // Async generators can't `yield from`, so they loop over the stream
         self.indent(output, indent);
-        output.push("yield from ");
+        let loops = c.is_async || outer_scope;
+        if c.is_async {
+            output.push("async for _chunk in ");
+        } else if outer_scope {
+            output.push("for _chunk in ");
+        } else {
+            output.push("yield from ");
+        }
         let name_compiled_start = output.position();
         output.push(&c.name);
         let name_compiled_end = output.position();
-        output.push(".stream(");
+        if self.plain_components.borrow().contains(&c.name) {
+            output.push("(");
+        } else {
+            output.push(".stream(");
+        }
 
         let mut first = true;
-        if has_content {
+        if let Some(func_name) = &content_function {
             output.push(DEFAULT_SLOT_PARAM);
             output.push("=");
-            output.push(&self.component_to_func_name(&c.name, None));
+            output.push(func_name);
             output.push("()");
             first = false;
         }
-        for (name, _) in &named_slots {
+        for (name, func_name) in &slot_functions {
             if !first {
                 output.push(", ");
             }
             output.push(name);
             output.push("=");
-            output.push(&self.component_to_func_name(&c.name, Some(name)));
+            output.push(func_name);
             output.push("()");
             first = false;
         }
@@ -783,6 +994,12 @@ impl PythonGenerator {
             first = false;
         }
         output.push(")");
+        if loops {
+            output.push(":");
+            output.newline();
+            self.indent(output, indent + 1);
+            output.push("yield _chunk");
+        }
         output.newline();
 
         if has_body {
@@ -803,6 +1020,7 @@ impl PythonGenerator {
             compiled_end: name_compiled_end,
             needs_injection: true,
             html_prefix: None,
+            bytes: None,
         });
 
         // Add Python segment for the component name in the closing tag.
@@ -821,6 +1039,7 @@ impl PythonGenerator {
                     compiled_end: 0,
                     needs_injection: false,
                     html_prefix: None,
+                    bytes: None,
                 });
             }
         }
@@ -850,13 +1069,13 @@ impl PythonGenerator {
                 output.push(&escape_string(value));
                 output.push("\"");
             }
+            // Props are passed as values, so `{!expr}` is the same as `{expr}`
             AttributeKind::Expression {
                 name,
                 expr,
                 expr_range,
+                ..
             } => {
-                let content_start = expr_range.start.byte + 1;
-                let content_end = expr_range.end.byte - 1;
                 output.push(name);
                 output.push("=");
                 let s = output.position();
@@ -864,12 +1083,13 @@ impl PythonGenerator {
                 let e = output.position();
                 output.add_segment(Segment {
                     language: Language::Python,
-                    source_start: content_start,
-                    source_end: content_end,
+                    source_start: expr_range.start.byte,
+                    source_end: expr_range.end.byte,
                     compiled_start: s,
                     compiled_end: e,
                     needs_injection: true,
                     html_prefix: None,
+                    bytes: None,
                 });
             }
             AttributeKind::Boolean { name } => {
@@ -893,6 +1113,7 @@ impl PythonGenerator {
                     compiled_end: e,
                     needs_injection: true,
                     html_prefix: None,
+                    bytes: None,
                 });
             }
             AttributeKind::Spread { expr, expr_range } => {
@@ -910,6 +1131,7 @@ impl PythonGenerator {
                     compiled_end: e,
                     needs_injection: true,
                     html_prefix: None,
+                    bytes: None,
                 });
             }
             AttributeKind::Template { name, value } => {
@@ -977,8 +1199,17 @@ impl PythonGenerator {
         output.newline();
 
         self.indent(output, indent + 1);
-        output.push("yield from ");
-        output.push(&slot_var);
+        if self.async_scope.get() {
+            output.push("async for _chunk in ");
+            output.push(&slot_var);
+            output.push(":");
+            output.newline();
+            self.indent(output, indent + 2);
+            output.push("yield _chunk");
+        } else {
+            output.push("yield from ");
+            output.push(&slot_var);
+        }
         output.newline();
 
         if !s.fallback.is_empty() {
@@ -1020,26 +1251,24 @@ impl PythonGenerator {
         // Strip trailing `:` so it does not land in the injection segment
         let condition = if_node.condition.trim_end_matches(':').trim();
         print_code(output, &code_from(condition, if_node.condition_range));
-        output.push(":");
-        output.newline();
+        end_block_header(output, if_node.comment.as_deref());
 
         self.emit_body_or_pass(&if_node.then_branch, output, indent + 1);
 
-        for (condition, condition_range, body) in &if_node.elif_branches {
+        for (condition, condition_range, body, comment) in &if_node.elif_branches {
             self.indent(output, indent);
             output.push("elif ");
             let condition = condition.trim_end_matches(':').trim();
             print_code(output, &code_from(condition, *condition_range));
-            output.push(":");
-            output.newline();
+            end_block_header(output, comment.as_deref());
 
             self.emit_body_or_pass(body, output, indent + 1);
         }
 
         if let Some(else_branch) = &if_node.else_branch {
             self.indent(output, indent);
-            output.push("else:");
-            output.newline();
+            output.push("else");
+            end_block_header(output, if_node.else_comment.as_deref());
 
             self.emit_body_or_pass(else_branch, output, indent + 1);
         }
@@ -1069,10 +1298,15 @@ impl PythonGenerator {
                 },
             },
         );
-        output.push(":");
-        output.newline();
+        end_block_header(output, for_node.comment.as_deref());
 
         self.emit_body_or_pass(&for_node.body, output, indent + 1);
+        self.emit_loop_else(
+            for_node.else_branch.as_deref(),
+            for_node.else_comment.as_deref(),
+            output,
+            indent,
+        );
     }
 
     fn emit_match(&self, match_node: &MatchNode, output: &mut Output, indent: usize) {
@@ -1080,16 +1314,14 @@ impl PythonGenerator {
         output.push("match ");
         let expr = match_node.expr.trim_end_matches(':').trim();
         print_code(output, &code_from(expr, match_node.expr_range));
-        output.push(":");
-        output.newline();
+        end_block_header(output, match_node.comment.as_deref());
 
         for case in match_node.cases.iter() {
             self.indent(output, indent + 1);
             output.push("case ");
             let pattern = case.pattern.trim_end_matches(':').trim();
             print_code(output, &code_from(pattern, case.pattern_range));
-            output.push(":");
-            output.newline();
+            end_block_header(output, case.comment.as_deref());
 
             self.emit_body_or_pass(&case.body, output, indent + 2);
         }
@@ -1100,10 +1332,31 @@ impl PythonGenerator {
         output.push("while ");
         let condition = while_node.condition.trim_end_matches(':').trim();
         print_code(output, &code_from(condition, while_node.condition_range));
-        output.push(":");
-        output.newline();
+        end_block_header(output, while_node.comment.as_deref());
 
         self.emit_body_or_pass(&while_node.body, output, indent + 1);
+        self.emit_loop_else(
+            while_node.else_branch.as_deref(),
+            while_node.else_comment.as_deref(),
+            output,
+            indent,
+        );
+    }
+
+    fn emit_loop_else(
+        &self,
+        else_branch: Option<&[Node]>,
+        comment: Option<&str>,
+        output: &mut Output,
+        indent: usize,
+    ) {
+        if let Some(else_branch) = else_branch {
+            self.indent(output, indent);
+            output.push("else");
+            end_block_header(output, comment);
+
+            self.emit_body_or_pass(else_branch, output, indent + 1);
+        }
     }
 
     fn emit_with(&self, with_node: &WithNode, output: &mut Output, indent: usize) {
@@ -1114,17 +1367,52 @@ impl PythonGenerator {
             output.push("with ");
         }
         let items = with_node.items.trim_end_matches(':').trim();
-        print_code(output, &code_from(items, with_node.items_range));
-        output.push(":");
-        output.newline();
+        if items.contains('\n') {
+            // A parenthesized header wrapped across lines: re-indent its
+            // continuation lines relative to `with`, and map the whole span
+            // up to the `:`
+            let continuation_indent = output.indentation(indent);
+            let header_col = with_node.range.start.col;
+            let source = items
+                .split('\n')
+                .enumerate()
+                .map(|(i, line)| {
+                    if i == 0 {
+                        return line.to_string();
+                    }
+                    let ws = line.len() - line.trim_start().len();
+                    format!("{continuation_indent}{}", &line[ws.min(header_col)..])
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let range = &with_node.items_range;
+            let trimmed_end = with_node.items.len() - items.len();
+            print_code(
+                output,
+                &Code {
+                    source,
+                    range: TextRange {
+                        start: range.start,
+                        end: Position {
+                            byte: range.end.byte - trimmed_end,
+                            line: range.end.line,
+                            col: range.end.col - trimmed_end,
+                        },
+                    },
+                },
+            );
+        } else {
+            print_code(output, &code_from(items, with_node.items_range));
+        }
+        end_block_header(output, with_node.comment.as_deref());
 
         self.emit_body_or_pass(&with_node.body, output, indent + 1);
     }
 
     fn emit_try(&self, try_node: &TryNode, output: &mut Output, indent: usize) {
         self.indent(output, indent);
-        output.push("try:");
-        output.newline();
+        output.push("try");
+        end_block_header(output, try_node.comment.as_deref());
 
         self.emit_body_or_pass(&try_node.body, output, indent + 1);
 
@@ -1133,28 +1421,31 @@ impl PythonGenerator {
             output.push("except");
             if let Some(exception) = &except.exception {
                 output.push(" ");
-                let exception = exception.trim_end_matches(':').trim();
-                let range = except.exception_range.unwrap_or(TextRange::synthetic());
-                print_code(output, &code_from(exception, range));
+                print_code(
+                    output,
+                    &Code {
+                        source: exception.clone(),
+                        range: except.exception_range.unwrap_or(TextRange::synthetic()),
+                    },
+                );
             }
-            output.push(":");
-            output.newline();
+            end_block_header(output, except.comment.as_deref());
 
             self.emit_body_or_pass(&except.body, output, indent + 1);
         }
 
         if let Some(else_clause) = &try_node.else_clause {
             self.indent(output, indent);
-            output.push("else:");
-            output.newline();
+            output.push("else");
+            end_block_header(output, try_node.else_comment.as_deref());
 
             self.emit_body_or_pass(else_clause, output, indent + 1);
         }
 
         if let Some(finally_clause) = &try_node.finally_clause {
             self.indent(output, indent);
-            output.push("finally:");
-            output.newline();
+            output.push("finally");
+            end_block_header(output, try_node.finally_comment.as_deref());
 
             self.emit_body_or_pass(finally_clause, output, indent + 1);
         }
@@ -1162,17 +1453,10 @@ impl PythonGenerator {
 
     fn emit_statement(&self, stmt: &StatementNode, output: &mut Output, indent: usize) {
         self.indent(output, indent);
-        // Re-indent continuation lines for multiline statements
-        let source = if stmt.stmt.contains('\n') {
-            let continuation_indent = "    ".repeat(indent);
-            stmt.stmt.replace('\n', &format!("\n{continuation_indent}"))
-        } else {
-            stmt.stmt.clone()
-        };
         print_code(
             output,
             &Code {
-                source,
+                source: reindent_continuation(&stmt.stmt, output, indent),
                 range: stmt.range,
             },
         );
@@ -1190,7 +1474,7 @@ impl PythonGenerator {
         );
         output.newline();
 
-        self.emit_body_or_pass(&def.body, output, indent + 1);
+        self.emit_function_body(&def.body, output, indent + 1);
     }
 
     fn emit_import(&self, import: &ImportNode, output: &mut Output, _indent: usize) {
@@ -1209,7 +1493,7 @@ impl PythonGenerator {
         print_code(
             output,
             &Code {
-                source: dec.decorator.clone(),
+                source: reindent_continuation(&dec.decorator, output, indent),
                 range: dec.range,
             },
         );
@@ -1217,9 +1501,7 @@ impl PythonGenerator {
     }
 
     fn indent(&self, output: &mut Output, level: usize) {
-        for _ in 0..level {
-            output.push("    ");
-        }
+        output.indent(level);
     }
 
     /// Emit one signature parameter (`name: type = default,`). User params map
@@ -1247,6 +1529,7 @@ impl PythonGenerator {
                 compiled_end: end,
                 needs_injection: true,
                 html_prefix: None,
+                bytes: None,
             });
         }
         output.push(",");
@@ -1264,17 +1547,22 @@ impl PythonGenerator {
             .collect()
     }
 
+    /// Emit `function` as `def name(...)`, or as `def name(self, ...)` one
+    /// level in when `method` is set.
     fn emit_render_function(
         &self,
         name: &str,
         name_range: Option<TextRange>,
         function: &Function,
         output: &mut Output,
+        method: bool,
     ) {
+        let base = usize::from(method);
         for decorator in &function.decorators {
-            self.emit_decorator(decorator, output, 0);
+            self.emit_decorator(decorator, output, base);
         }
 
+        self.indent(output, base);
         if function.is_async {
             output.push("async def ");
         } else {
@@ -1291,6 +1579,7 @@ impl PythonGenerator {
                 compiled_end: output.position(),
                 needs_injection: true,
                 html_prefix: None,
+                bytes: None,
             });
         }
 
@@ -1311,12 +1600,19 @@ impl PythonGenerator {
             .find(|param| param.kind == ParamKind::VarKeyword);
 
         if positional.is_empty() && keyword_only.is_empty() && var_keyword.is_none() {
-            output.push("():");
+            output.push(if method { "(self):" } else { "():" });
             output.newline();
         } else {
             output.push("(");
             output.newline();
-            let indent = "        ";
+            // Parameters hang two levels deep, apart from the body
+            let indent = &output.indentation(base + 2);
+
+            if method {
+                output.push(indent);
+                output.push("self,");
+                output.newline();
+            }
 
             for param in positional {
                 self.emit_signature_param(param, output, indent);
@@ -1340,17 +1636,18 @@ impl PythonGenerator {
                 output.newline();
             }
 
+            self.indent(output, base);
             output.push("):");
             output.newline();
         }
 
+        self.async_scope.set(function.is_async);
+        self.slot_functions.take();
         let body: Vec<&Node> = function.body.iter().collect();
         if body.is_empty() || self.is_effectively_empty(&body) {
-            self.indent(output, 1);
-            output.push("yield from ()");
-            output.newline();
+            self.emit_empty_generator(output, base + 1);
         } else {
-            self.emit_nodes(&body, output, 1);
+            self.emit_nodes(&body, output, base + 1);
         }
     }
 }
@@ -1363,7 +1660,16 @@ impl Default for PythonGenerator {
 
 impl Generator for PythonGenerator {
     fn generate(&self, ast: &Ast, options: &CompileOptions) -> CompileResult {
-        let mut output = Output::new();
+        let mut output = Output::with_indent(&options.indent);
+        self.minify.set(options.minify);
+        self.default_escape.set(options.default_escape);
+        if options.decorator_name().is_none() {
+            *self.plain_components.borrow_mut() = ast
+                .definitions
+                .iter()
+                .map(|definition| definition.name.clone())
+                .collect();
+        }
 
         // Frontmatter and body are already split by the `lower` pass.
         let function = &ast.function;
@@ -1390,6 +1696,7 @@ impl Generator for PythonGenerator {
                 compiled_end: import_end,
                 needs_injection: true,
                 html_prefix: None,
+                bytes: None,
             });
         }
         let runtime_import_offset = output.position();
@@ -1415,19 +1722,30 @@ impl Generator for PythonGenerator {
                 Some(definition.name_range),
                 &definition.function,
                 &mut output,
+                false,
             );
             output.newline();
             output.newline();
         }
 
         if ast.mode == FileMode::ImplicitComponent {
-            self.emit_render_function(&function_name, None, function, &mut output);
+            match &options.wrap_in_class {
+                Some(class) => {
+                    output.push(&format!("class {}:", class));
+                    output.newline();
+                    self.emit_render_function("render", None, function, &mut output, true);
+                }
+                None => {
+                    self.emit_render_function(&function_name, None, function, &mut output, false)
+                }
+            }
         }
 
         // Hyper runtime imports, in Helper::ALL order, for helpers actually emitted.
         let mut hyper_imports = Vec::new();
-        if ast.mode == FileMode::ImplicitComponent || !ast.definitions.is_empty() {
-            hyper_imports.push("component");
+        let decorates = options.decorator_name() == Some(DEFAULT_DECORATOR);
+        if decorates && (ast.mode == FileMode::ImplicitComponent || !ast.definitions.is_empty()) {
+            hyper_imports.push(DEFAULT_DECORATOR);
         }
         for helper in Helper::ALL {
             if output.helper_used(helper.import_name()) {
@@ -1436,38 +1754,32 @@ impl Generator for PythonGenerator {
         }
 
         let (mut code, tracked_segments) = output.finish();
+        code.truncate(code.trim_end_matches('\n').len());
+        if options.trailing_newline {
+            code.push('\n');
+        }
 
-        // Iterable import is needed when a param is typed with it (slot params).
-        let needs_iterable = all_parameters.iter().any(|p| {
-            p.type_hint
-                .as_deref()
-                .is_some_and(|t| t.contains("Iterable"))
-        });
-
-        // Detect typing constructs needed from parameter type hints
-        let mut typing_imports: Vec<&str> = Vec::new();
-        let all_type_hints: String = all_parameters
+        // Names the type hints use that typing provides, unless the user's
+        // own imports already bind them. Matched as whole names, so
+        // `Company` doesn't pull in `Any`.
+        let hint_names: HashSet<&str> = all_parameters
             .iter()
-            .filter_map(|p| p.type_hint.as_ref())
-            .cloned()
-            .collect::<Vec<_>>()
-            .join(" ");
+            .filter_map(|p| p.type_hint.as_deref())
+            .flat_map(|hint| hint.split(|c: char| !(c.is_alphanumeric() || c == '_')))
+            .collect();
+        let user_imported: HashSet<String> = imports
+            .iter()
+            .flat_map(|import| imported_names(&import.stmt))
+            .collect();
+        let needed = |name: &str| hint_names.contains(name) && !user_imported.contains(name);
 
-        if all_type_hints.contains("Any") {
-            typing_imports.push("Any");
-        }
-        if all_type_hints.contains("Callable") {
-            typing_imports.push("Callable");
-        }
-        if all_type_hints.contains("Optional") {
-            typing_imports.push("Optional");
-        }
-        if all_type_hints.contains("Union") {
-            typing_imports.push("Union");
-        }
-        if all_type_hints.contains("TypeVar") {
-            typing_imports.push("TypeVar");
-        }
+        // Iterable import is needed when a param is typed with it (slot params).
+        let needs_iterable = needed("Iterable");
+
+        let typing_imports: Vec<&str> = ["Any", "Callable", "Optional", "Union", "TypeVar"]
+            .into_iter()
+            .filter(|name| needed(name))
+            .collect();
 
         // Build import block
         let mut import_lines = String::new();
@@ -1503,9 +1815,22 @@ impl Generator for PythonGenerator {
             import_lines.push('\n');
         }
 
-        let import_offset = import_lines.len();
+        let import_offset = import_lines.encode_utf16().count();
         code.insert_str(runtime_import_offset, &import_lines);
 
+        // The banner goes above everything, user imports included
+        let banner = options.banner.as_deref().map(|banner| {
+            let mut banner = banner.to_string();
+            if !banner.ends_with('\n') {
+                banner.push('\n');
+            }
+            banner
+        });
+        if let Some(banner) = &banner {
+            code.insert_str(0, banner);
+        }
+        let banner_offset = banner.map_or(0, |banner| banner.encode_utf16().count());
+
         // Adjust segments and collect IDE metadata when ranges are requested.
         let (segments, expression_braces) = if options.include_ranges {
             // Adjust tracked segments by the import line offset, but only for segments
@@ -1517,6 +1842,8 @@ impl Generator for PythonGenerator {
                         s.compiled_start += import_offset;
                         s.compiled_end += import_offset;
                     }
+                    s.compiled_start += banner_offset;
+                    s.compiled_end += banner_offset;
                     s
                 })
                 .collect();
@@ -1534,8 +1861,13 @@ impl Generator for PythonGenerator {
             code,
             file_mode: ast.mode,
             component_name: (ast.mode == FileMode::ImplicitComponent).then_some(function_name),
+            defined_components: ast.definitions.iter().map(|d| d.name.clone()).collect(),
+            front_matter: ast.front_matter.clone(),
             segments,
             expression_braces,
+            warnings: Vec::new(),
+            referenced_components: BTreeSet::new(),
+            stub: options.stub.then(|| generate_stub(ast, options)),
         }
     }
 }
@@ -1560,6 +1892,99 @@ struct CombinedContentInfo {
     is_empty: bool,
 }
 
+/// Close a block's opening line with `:` and its trailing comment, if any
+fn end_block_header(output: &mut Output, comment: Option<&str>) {
+    output.push(":");
+    if let Some(comment) = comment {
+        output.push("  ");
+        output.push(comment);
+    }
+    output.newline();
+}
+
+/// Whether the nodes on either side of a combined run are inline: components
+/// or statements on the same line, or the tags of an inline parent element
+#[derive(Clone, Copy)]
+struct InlineNeighbors {
+    before: bool,
+    after: bool,
+}
+
+impl InlineNeighbors {
+    const NONE: Self = Self {
+        before: false,
+        after: false,
+    };
+}
+
+/// Elements whose text whitespace is content, left alone by `minify`
+fn keeps_whitespace(tag: &str) -> bool {
+    ["pre", "textarea", "script", "style"]
+        .iter()
+        .any(|t| tag.eq_ignore_ascii_case(t))
+}
+
+/// Push literal text from `texts`, already joined (and maybe collapsed) into
+/// `content`, with a `Template` segment over everything but its surrounding
+/// whitespace, which the literal's cleanup may trim.
+fn push_template_text(
+    output: &mut Output,
+    content: &str,
+    texts: &[&TextNode],
+    escape: impl Fn(&str) -> String,
+) {
+    let first = texts.iter().find(|text| !text.content.trim().is_empty());
+    let last = texts.iter().rfind(|text| !text.content.trim().is_empty());
+    let (Some(first), Some(last)) = (first, last) else {
+        output.push(&escape(content));
+        return;
+    };
+
+    let core = content.trim();
+    let leading = content.len() - content.trim_start().len();
+    output.push(&escape(&content[..leading]));
+    let compiled_start = output.position();
+    output.push(&escape(core));
+    let compiled_end = output.position();
+    output.push(&escape(&content[leading + core.len()..]));
+
+    let source_start =
+        first.range.start.byte + (first.content.len() - first.content.trim_start().len());
+    let source_end = last.range.end.byte - (last.content.len() - last.content.trim_end().len());
+    output.add_segment(Segment {
+        language: Language::Template,
+        source_start: source_start.min(first.range.end.byte),
+        source_end: source_end.max(last.range.start.byte),
+        compiled_start,
+        compiled_end,
+        needs_injection: false,
+        html_prefix: None,
+        bytes: None,
+    });
+}
+
+/// Collapse each run of whitespace in `text` to one space, dropping it
+/// entirely at a trimmed end
+fn collapse_whitespace(text: &str, trim_start: bool, trim_end: bool) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut pending_space = !trim_start && text.starts_with(|c: char| c.is_ascii_whitespace());
+    for ch in text.chars() {
+        if ch.is_ascii_whitespace() {
+            pending_space |= !result.is_empty();
+            continue;
+        }
+        if pending_space {
+            result.push(' ');
+            pending_space = false;
+        }
+        result.push(ch);
+    }
+    if pending_space && !trim_end {
+        result.push(' ');
+    }
+    result
+}
+
 /// Analyze raw combined content to determine formatting parameters.
 ///
 /// The content string is the concatenation of all node outputs (text, expressions,
@@ -1656,13 +2081,29 @@ fn escape_string(s: &str) -> String {
         .replace('\t', "\\t")
 }
 
-/// Escape double quotes as &quot; for HTML attribute values.
-/// This is needed when single-quoted source values contain double quotes.
-fn escape_html_attr_quotes(s: &str) -> String {
-    s.replace('"', "&quot;")
+/// Escape attribute text for a double-quoted string literal: backslashes are
+/// doubled so they stay literal, and double quotes become &quot; (single-quoted
+/// source values may contain them).
+fn escape_attr_text(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "&quot;")
+}
+
+/// Indent the continuation lines of multiline code emitted at `indent`
+fn reindent_continuation(code: &str, output: &Output, indent: usize) -> String {
+    if code.contains('\n') {
+        let continuation_indent = output.indentation(indent);
+        code.replace('\n', &format!("\n{continuation_indent}"))
+    } else {
+        code.to_string()
+    }
+}
+
+/// Double braces so an f-string prints them literally
+fn escape_fstring_braces(s: &str) -> String {
+    s.replace('{', "{{").replace('}', "}}")
 }
 
-fn to_pascal_case(s: &str) -> String {
+pub(super) fn to_pascal_case(s: &str) -> String {
     s.split('_')
         .map(|word| {
             let mut chars = word.chars();
@@ -1674,7 +2115,7 @@ fn to_pascal_case(s: &str) -> String {
         .collect()
 }
 
-fn import_from(module: &str, names: &[&str]) -> StmtImportFrom {
+pub(super) fn import_from(module: &str, names: &[&str]) -> StmtImportFrom {
     StmtImportFrom {
         module: Some(Identifier::new(module)),
         names: names
@@ -1688,6 +2129,28 @@ fn import_from(module: &str, names: &[&str]) -> StmtImportFrom {
     }
 }
 
+/// Names an import statement binds: `from m import a, b as c` binds `a`
+/// and `c`, `import x.y` binds `x`.
+pub(super) fn imported_names(stmt: &str) -> Vec<String> {
+    let stmt = stmt.trim();
+    let names = match stmt.strip_prefix("from ") {
+        Some(rest) => rest.split_once(" import ").map_or("", |(_, names)| names),
+        None => stmt.strip_prefix("import ").unwrap_or(""),
+    };
+    names
+        .trim_matches(|c: char| c == '(' || c == ')' || c.is_whitespace())
+        .split(',')
+        .filter_map(|name| {
+            let name = name.trim().trim_end_matches(')').trim();
+            let bound = match name.split_once(" as ") {
+                Some((_, alias)) => alias.trim(),
+                None => name.split('.').next().unwrap_or(name),
+            };
+            (!bound.is_empty()).then(|| bound.to_string())
+        })
+        .collect()
+}
+
 /// Build a `Code` whose source spans `[start, start + source.len())` in `.hyper`.
 /// Used for control-flow conditions where trimming `:` only shrinks the tail.
 /// Synthetic input range stays synthetic so the printer skips injection.
@@ -1709,3 +2172,25 @@ fn code_from(source: &str, range: TextRange) -> Code {
         range: adjusted,
     }
 }
+
+/// `Code` for `expr` without its surrounding whitespace, which may span lines
+/// (`class={\n  x\n}`). The range shrinks by what was trimmed from each end.
+fn trimmed_code(expr: &str, start_byte: usize, end_byte: usize) -> Code {
+    let trimmed = expr.trim();
+    let leading = expr.len() - expr.trim_start().len();
+    let trailing = expr.len() - expr.trim_end().len();
+    code_span(trimmed, start_byte + leading, end_byte - trailing)
+}
+
+/// `nodes` with each non-empty fragment replaced by its children, recursively
+fn flatten_fragments<'a>(nodes: &[&'a Node]) -> Vec<&'a Node> {
+    nodes
+        .iter()
+        .flat_map(|node| match node {
+            Node::Fragment(fragment) if !fragment.children.is_empty() => {
+                flatten_fragments(&fragment.children.iter().collect::<Vec<_>>())
+            }
+            node => vec![*node],
+        })
+        .collect()
+}
@@ -0,0 +1,174 @@
+//! `.pyi` stubs: each component's signature without its body, so type
+//! checkers can check call sites without importing the generated module.
+
+use std::collections::HashSet;
+
+use super::CompileOptions;
+use super::print::print_import_from;
+use super::python::{import_from, imported_names, to_pascal_case};
+use crate::ast::{Ast, FileMode, Function, Node, ParamKind, ParameterNode};
+
+/// Render the stub for a transformed `ast`. Each component is declared with a
+/// `Protocol` typing both ways to render it: calling it returns an
+/// `HtmlResult` (awaitable when async) and `stream` returns `Iterable[str]`
+/// (`AsyncIterable[str]` when async). Defaults are written as `...`, as stubs
+/// do. Module-level statements other than imports are left out.
+pub fn generate_stub(ast: &Ast, options: &CompileOptions) -> String {
+    let mut functions: Vec<(String, &Function)> = ast
+        .definitions
+        .iter()
+        .map(|definition| (definition.name.clone(), &definition.function))
+        .collect();
+    // The file's own component, unless `wrap_in_class` makes it a method
+    let mut method = None;
+    if ast.mode == FileMode::ImplicitComponent {
+        match &options.wrap_in_class {
+            Some(class) => method = Some((class, &ast.function)),
+            None => {
+                let name = options
+                    .function_name
+                    .as_deref()
+                    .map(to_pascal_case)
+                    .unwrap_or_else(|| "Render".to_string());
+                functions.push((name, &ast.function));
+            }
+        }
+    }
+    let all_functions = || {
+        functions
+            .iter()
+            .map(|(_, function)| *function)
+            .chain(method.map(|(_, function)| function))
+    };
+
+    let user_imported: HashSet<String> = ast
+        .function
+        .imports
+        .iter()
+        .flat_map(|import| imported_names(&import.stmt))
+        .collect();
+    let hint_names: HashSet<&str> = all_functions()
+        .flat_map(parameters)
+        .filter_map(|param| param.type_hint.as_deref())
+        .flat_map(|hint| hint.split(|c: char| !(c.is_alphanumeric() || c == '_')))
+        .collect();
+    let needed = |name: &str| !user_imported.contains(name);
+
+    let mut abc_names = Vec::new();
+    let any_sync = all_functions().any(|function| !function.is_async);
+    let any_async = all_functions().any(|function| function.is_async);
+    if needed("AsyncIterable") && any_async {
+        abc_names.push("AsyncIterable");
+    }
+    if needed("Awaitable") && any_async {
+        abc_names.push("Awaitable");
+    }
+    if needed("Iterable") && (any_sync || hint_names.contains("Iterable")) {
+        abc_names.push("Iterable");
+    }
+    let mut typing_names: Vec<&str> = ["Any", "Callable", "Optional", "Union", "TypeVar"]
+        .into_iter()
+        .filter(|name| hint_names.contains(name) && needed(name))
+        .collect();
+    let any_component = all_functions().next().is_some();
+    if any_component && needed("Protocol") {
+        typing_names.push("Protocol");
+    }
+
+    let mut stub = String::new();
+    for import in &ast.function.imports {
+        stub.push_str(&import.stmt);
+        stub.push('\n');
+    }
+    if !typing_names.is_empty() {
+        stub.push_str(&print_import_from(&import_from("typing", &typing_names)));
+        stub.push('\n');
+    }
+    if !abc_names.is_empty() {
+        stub.push_str(&print_import_from(&import_from(
+            "collections.abc",
+            &abc_names,
+        )));
+        stub.push('\n');
+    }
+    if any_component && needed("HtmlResult") {
+        stub.push_str(&print_import_from(&import_from(
+            "hyperhtml.decorators",
+            &["HtmlResult"],
+        )));
+        stub.push('\n');
+    }
+
+    for (name, function) in &functions {
+        let protocol_name = format!("_{name}");
+        stub.push_str(&protocol(&protocol_name, function, &options.indent));
+        stub.push_str(&format!("\n\n{name}: {protocol_name}\n"));
+    }
+    if let Some((class, function)) = method {
+        let protocol_name = format!("_{class}Render");
+        stub.push_str(&protocol(&protocol_name, function, &options.indent));
+        stub.push_str(&format!(
+            "\n\nclass {class}:\n{}render: {protocol_name}\n",
+            options.indent
+        ));
+    }
+    stub
+}
+
+/// `class name(Protocol):` with the component's `__call__` and `stream`
+fn protocol(name: &str, function: &Function, indent: &str) -> String {
+    let params = signature_params(function);
+    let (result, stream) = if function.is_async {
+        ("Awaitable[HtmlResult]", "AsyncIterable[str]")
+    } else {
+        ("HtmlResult", "Iterable[str]")
+    };
+    format!(
+        "\n\nclass {name}(Protocol):\n\
+         {indent}def __call__({params}) -> {result}: ...\n\
+         {indent}def stream({params}) -> {stream}: ...\n"
+    )
+}
+
+fn parameters(function: &Function) -> impl Iterator<Item = &ParameterNode> {
+    function.params.iter().filter_map(|node| match node {
+        Node::Parameter(param) => Some(param),
+        _ => None,
+    })
+}
+
+/// The parameter list of a component's protocol methods, `self` first
+fn signature_params(function: &Function) -> String {
+    let param = |param: &ParameterNode| {
+        let mut text = param.name.clone();
+        if let Some(type_hint) = &param.type_hint {
+            text.push_str(": ");
+            text.push_str(type_hint);
+        }
+        if param.default.is_some() {
+            text.push_str(" = ...");
+        }
+        text
+    };
+
+    let mut params = vec!["self".to_string()];
+    params.extend(
+        parameters(function)
+            .filter(|p| p.kind == ParamKind::Positional)
+            .map(param),
+    );
+    let keyword_only: Vec<String> = parameters(function)
+        .filter(|p| p.kind == ParamKind::KeywordOnly)
+        .map(param)
+        .collect();
+    if !keyword_only.is_empty() {
+        params.push("*".to_string());
+        params.extend(keyword_only);
+    }
+    params.extend(
+        parameters(function)
+            .filter(|p| p.kind == ParamKind::VarKeyword)
+            .map(|p| format!("**{}", param(p).trim_start_matches('*'))),
+    );
+    params.join(", ")
+}
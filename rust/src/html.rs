@@ -50,6 +50,14 @@ const BLOCK_ELEMENTS: &[&str] = &[
 /// Interactive elements that cannot be nested inside themselves.
 const INTERACTIVE_ELEMENTS: &[&str] = &["a", "button"];
 
+/// Elements that are only valid directly inside one of the listed parents.
+/// Browsers move or drop them anywhere else.
+const REQUIRED_PARENTS: &[(&str, &[&str])] = &[
+    ("li", &["ul", "ol", "menu"]),
+    ("td", &["tr"]),
+    ("th", &["tr"]),
+];
+
 pub fn is_void_element(tag: &str) -> bool {
     VOID_ELEMENTS.contains(&tag.to_ascii_lowercase().as_str())
 }
@@ -66,6 +74,15 @@ pub fn is_interactive_element(tag: &str) -> bool {
     INTERACTIVE_ELEMENTS.contains(&tag.to_ascii_lowercase().as_str())
 }
 
+/// The parents `tag` must appear directly inside, if it is restricted.
+pub fn required_parents(tag: &str) -> Option<&'static [&'static str]> {
+    let tag = tag.to_ascii_lowercase();
+    REQUIRED_PARENTS
+        .iter()
+        .find(|(child, _)| *child == tag)
+        .map(|(_, parents)| *parents)
+}
+
 /// Boolean HTML attributes: rendered as present/absent, not as key="value".
 /// https://html.spec.whatwg.org/multipage/common-microsyntaxes.html#boolean-attributes
 const BOOLEAN_ATTRIBUTES: &[&str] = &[
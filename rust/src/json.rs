@@ -0,0 +1,175 @@
+//! JSON encoding of compile results
+//!
+//! This is the wire format of `hyper generate --json` and the daemon protocol.
+//! It lives in the library so other hosts, such as a browser playground built
+//! for `wasm32`, can return exactly what the CLI returns.
+
+use crate::generate::{ExpressionBrace, Segment};
+use crate::{CompileError, CompileOptions, CompileResult, Warning, compile};
+
+#[derive(serde::Serialize)]
+struct Response {
+    compiled: String,
+    warnings: Vec<Warning>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    segments: Option<Vec<Segment>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expression_braces: Option<Vec<ExpressionBrace>>,
+}
+
+#[derive(serde::Serialize)]
+struct ErrorResponse {
+    error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_col: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_end_line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_end_col: Option<usize>,
+}
+
+/// Compile `source` and encode the outcome as JSON. Never panics: compile
+/// errors become an `{"error": ...}` object.
+pub fn compile_json(source: &str, options: &CompileOptions) -> String {
+    match compile(source, options) {
+        Ok(result) => result_to_json(result, options.include_ranges, false),
+        Err(e) => error_to_json(&e, false),
+    }
+}
+
+/// The browser playground's entry point: compile `source` with default
+/// options, including segments and expression braces when
+/// `include_injections` is set. Where panics unwind, a panic inside the
+/// compiler, such as a slice that splits a character, becomes an
+/// `{"error": ...}` object too. On `wasm32` panics abort instead, so the
+/// host sees a trap; see [`panic_to_json`].
+pub fn transpile_json(source: &str, include_injections: bool) -> String {
+    let options = CompileOptions {
+        include_ranges: include_injections,
+        ..Default::default()
+    };
+    std::panic::catch_unwind(|| compile_json(source, &options)).unwrap_or_else(|panic| {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        panic_to_json(&message)
+    })
+}
+
+/// Encode a compiler panic's message as an `{"error": ...}` object
+pub fn panic_to_json(message: &str) -> String {
+    let response = ErrorResponse {
+        error: format!("Internal compiler error: {}", message),
+        error_line: None,
+        error_col: None,
+        error_end_line: None,
+        error_end_col: None,
+    };
+    encode(&response, false)
+        .unwrap_or_else(|_| r#"{"error":"Internal compiler error"}"#.to_string())
+}
+
+/// Encode a successful compile and its warnings. Segments and expression
+/// braces are only included when `include_ranges` is set. `pretty` indents
+/// the output.
+pub fn result_to_json(result: CompileResult, include_ranges: bool, pretty: bool) -> String {
+    let response = Response {
+        compiled: result.code,
+        warnings: result.warnings,
+        segments: include_ranges.then_some(result.segments),
+        expression_braces: include_ranges.then_some(result.expression_braces),
+    };
+    encode(&response, pretty).unwrap_or_else(|e| format!(r#"{{"error":"{}"}}"#, e))
+}
+
+/// Encode a compile error, with its location when it has one.
+pub fn error_to_json(e: &CompileError, pretty: bool) -> String {
+    let (line, col, end_line, end_col) = match e {
+        CompileError::Parse(parse_err) => (
+            Some(parse_err.range.start.line),
+            Some(parse_err.range.start.col),
+            Some(parse_err.range.end.line),
+            Some(parse_err.range.end.col),
+        ),
+        CompileError::Generate(_) => (None, None, None, None),
+    };
+
+    let response = ErrorResponse {
+        error: e.to_string(),
+        error_line: line,
+        error_col: col,
+        error_end_line: end_line,
+        error_end_col: end_col,
+    };
+
+    encode(&response, pretty)
+        .unwrap_or_else(|_| format!(r#"{{"error":"{}"}}"#, e.to_string().replace('"', "\\\"")))
+}
+
+fn encode(value: &impl serde::Serialize, pretty: bool) -> serde_json::Result<String> {
+    if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+}
+
+#[derive(serde::Serialize)]
+struct Diagnostic<'a> {
+    file: &'a str,
+    kind: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    help: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    span: Option<Span>,
+}
+
+#[derive(serde::Serialize)]
+struct Span {
+    start: Location,
+    end: Location,
+}
+
+#[derive(serde::Serialize)]
+struct Location {
+    line: usize,
+    col: usize,
+}
+
+/// Encode a compile error as one line of `hyper generate --diagnostics`
+/// output. Lines and columns are 0-based; generation errors have no span.
+pub fn diagnostic_to_json(e: &CompileError, file: &str) -> String {
+    let diagnostic = match e {
+        CompileError::Parse(parse_err) => Diagnostic {
+            file,
+            kind: parse_err.kind.as_str(),
+            message: parse_err.message.clone(),
+            help: parse_err.help.as_deref(),
+            span: Some(Span {
+                start: Location {
+                    line: parse_err.range.start.line,
+                    col: parse_err.range.start.col,
+                },
+                end: Location {
+                    line: parse_err.range.end.line,
+                    col: parse_err.range.end.col,
+                },
+            }),
+        },
+        CompileError::Generate(msg) => Diagnostic {
+            file,
+            kind: "Generation error",
+            message: msg.clone(),
+            help: None,
+            span: None,
+        },
+    };
+
+    serde_json::to_string(&diagnostic)
+        .unwrap_or_else(|_| format!(r#"{{"error":"{}"}}"#, e.to_string().replace('"', "\\\"")))
+}
@@ -12,10 +12,12 @@
 //! println!("{}", result.code);
 //! ```
 
+mod analyze;
 pub mod ast;
 pub mod error;
 pub mod generate;
 pub mod html;
+pub mod json;
 pub mod lower;
 pub mod parse;
 pub mod plugins;
@@ -23,22 +25,47 @@ pub mod plugins;
 #[cfg(feature = "python-extension")]
 mod python_module;
 
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+mod wasm;
+
 use generate::Generator;
+use std::collections::BTreeSet;
 use std::path::Path;
 
 /// Compile a `.hyper` source string to Python.
 pub fn compile(source: &str, options: &CompileOptions) -> Result<CompileResult, CompileError> {
-    let parsed = parse::HyperParser::new().parse_file(source)?;
-    let mut ast = lower::lower(parsed.nodes, source, parsed.has_separator);
+    compile_with_plugins(source, options, &mut [])
+}
 
-    plugins::run(&mut ast)?;
+/// Like [`compile`], with extra plugins that run after the standard ones in
+/// each function scope. The same instances see every scope, so anything they
+/// record can be read back once this returns.
+pub fn compile_with_plugins(
+    source: &str,
+    options: &CompileOptions,
+    extra_plugins: &mut [&mut dyn Plugin],
+) -> Result<CompileResult, CompileError> {
+    if options.indent.is_empty() || !options.indent.chars().all(|c| c == ' ' || c == '\t') {
+        return Err(CompileError::Generate(format!(
+            "indent must be one or more spaces or tabs, got {:?}",
+            options.indent
+        )));
+    }
+
+    let Transformed {
+        ast,
+        warnings,
+        referenced_components,
+    } = transform(source, options, extra_plugins)?;
 
     let mut result = generate::PythonGenerator::new().generate(&ast, options);
+    result.warnings = warnings;
+    result.referenced_components = referenced_components;
 
     if options.include_ranges {
         generate::validate_python_segments(source, &result.code, &mut result.segments);
         // Convert source offsets from byte to UTF-16 last; validation expects byte offsets.
-        generate::segments_source_to_utf16(source, &mut result.segments);
+        generate::segments_source_to_utf16(source, &result.code, &mut result.segments);
     }
 
     Ok(result)
@@ -50,6 +77,75 @@ pub fn compile_to_python(source: &str, filename: Option<&str>) -> Result<String,
     compile_python_file(source, filename).map(|result| result.code)
 }
 
+/// A file taken through parsing, lowering and plugins, ready to generate
+pub(crate) struct Transformed {
+    pub ast: Ast,
+    pub warnings: Vec<Warning>,
+    pub referenced_components: BTreeSet<String>,
+}
+
+/// Every stage of [`compile_with_plugins`] before generation.
+pub(crate) fn transform(
+    source: &str,
+    options: &CompileOptions,
+    extra_plugins: &mut [&mut dyn Plugin],
+) -> Result<Transformed, CompileError> {
+    let parsed = parse::HyperParser::new().parse_file(source)?;
+    let mut ast = lower::lower(parsed.nodes, source, parsed.has_separator);
+    ast.front_matter = parsed.front_matter;
+
+    let mut warnings = parsed.warnings;
+    if options.strict_indent {
+        warnings.extend(
+            parse::mixed_indentation(source)
+                .into_iter()
+                .map(|range| Warning {
+                    message: "This line's indentation mixes tabs and spaces.".to_string(),
+                    range,
+                }),
+        );
+    }
+    warnings.extend(plugins::run(
+        &mut ast,
+        options.decorator_name(),
+        extra_plugins,
+    )?);
+    warnings.extend(plugins::validate_props(&mut ast, &options.component_props));
+
+    let mut referenced = plugins::ReferencedComponents::default();
+    for definition in &mut ast.definitions {
+        referenced.run(&mut definition.function)?;
+    }
+    referenced.run(&mut ast.function)?;
+
+    Ok(Transformed {
+        ast,
+        warnings,
+        referenced_components: referenced.into_names(),
+    })
+}
+
+/// Compile several named `.hyper` sources without touching the filesystem.
+///
+/// Each name is a path like `components/Card.hyper`; its file stem becomes the
+/// component name, as it does for `hyper generate`. Results are returned in input
+/// order, one per file, so a failing file doesn't stop the rest.
+pub fn compile_many(
+    files: &[(String, &str)],
+    options: &CompileOptions,
+) -> Vec<(String, Result<CompileResult, CompileError>)> {
+    files
+        .iter()
+        .map(|(name, source)| {
+            let options = CompileOptions {
+                function_name: function_name_from_filename(name),
+                ..options.clone()
+            };
+            (name.clone(), compile(source, &options))
+        })
+        .collect()
+}
+
 // Import hooks also need the mode from the same parse and compile pass.
 fn compile_python_file(
     source: &str,
@@ -58,6 +154,7 @@ fn compile_python_file(
     let options = CompileOptions {
         function_name: filename.and_then(function_name_from_filename),
         include_ranges: false,
+        ..Default::default()
     };
     compile(source, &options)
 }
@@ -69,8 +166,9 @@ fn function_name_from_filename(filename: &str) -> Option<String> {
         .map(str::to_string)
 }
 
+pub use analyze::{ParameterInfo, TemplateInfo, analyze};
 pub use ast::{Ast, FileMode, Node, Position, TextRange};
-pub use error::{CompileError, ParseError, ParseResult};
+pub use error::{CompileError, ParseError, ParseResult, Warning};
 pub use generate::{CompileOptions, CompileResult};
 pub use parse::Parser;
 pub use plugins::{Flow, Plugin, walk};
@@ -5,8 +5,12 @@
 
 use std::sync::Arc;
 
-use crate::ast::python::{Arguments, Code, Expr, ExprCall, ExprName, Identifier, StringLiteral};
-use crate::ast::{Ast, ExpressionNode, FileMode, Function, Node, Position, TextRange};
+use crate::ast::python::{
+    Arguments, Code, DictItem, Expr, ExprCall, ExprDict, ExprName, Identifier, StringLiteral,
+};
+use crate::ast::{
+    Ast, ExpressionNode, FileMode, Function, Node, Position, TextRange, parse_expression_parts,
+};
 
 pub fn lower(nodes: Vec<Node>, source: &str, has_separator: bool) -> Ast {
     let n = nodes.len();
@@ -128,14 +132,111 @@ fn selects_implicit_component(node: &Node) -> bool {
 }
 
 /// Lower a template interpolation to an output expression for the cases already
-/// migrated to the output AST. Returns None for cases the old generator path
-/// still emits (raw `{x}`, `str(...)`, format-spec/conversion/debug).
+/// migrated to the output AST. Conversions and format specs apply before
+/// escaping: `{x!r:>8}` becomes `escape(format(repr(x), ">8"))`. Returns None
+/// for cases the old generator path still emits (raw `{x}`, `str(...)`, debug
+/// `{x=}`, and nested specs like `{x:{width}}`).
 pub fn lower_interpolation(expr: &ExpressionNode) -> Option<Expr> {
-    let has_format_extras = expr.format_spec.is_some() || expr.conversion.is_some() || expr.debug;
-    if has_format_extras || !expr.escape {
+    let nested_spec = expr
+        .format_spec
+        .as_ref()
+        .is_some_and(|spec| spec.contains('{'));
+    if expr.debug || nested_spec || !expr.escape {
         return None;
     }
-    Some(helper_call("escape", interp_code(&expr.expr, expr.range)))
+    let mut code = interp_code(expr);
+    if !code.range.is_synthetic() && (expr.conversion.is_some() || expr.format_spec.is_some()) {
+        // The span covers `x!r:>8`; map only the `x` the printer emits.
+        code.range.end.byte = code.range.start.byte + expr.expr.len();
+    }
+    let value = format_value(
+        Expr::Code(code),
+        expr.conversion,
+        expr.format_spec.as_deref(),
+    );
+    Some(call("escape", vec![value]))
+}
+
+/// `escape(...)` for an attribute value like `title={price:.2f}`, applying any
+/// conversion or format spec the same way as text interpolations. Without
+/// `escape` (`title={!markup}`) the value is inserted as-is.
+pub fn lower_attr_value(code: Code, escape: bool) -> Expr {
+    let wrap = |value| {
+        if escape {
+            call("escape", vec![value])
+        } else {
+            value
+        }
+    };
+    let (value, format_spec, conversion, debug) = parse_expression_parts(&code.source);
+    let nested_spec = format_spec.as_ref().is_some_and(|spec| spec.contains('{'));
+    if debug || nested_spec || (format_spec.is_none() && conversion.is_none()) {
+        return wrap(Expr::Code(code));
+    }
+    let start = code.range.start.byte;
+    let value = code_span(value.trim_end(), start, start + value.trim_end().len());
+    wrap(format_value(
+        Expr::Code(value),
+        conversion,
+        format_spec.as_deref(),
+    ))
+}
+
+/// Apply an f-string conversion (`!r`, `!s`, `!a`) and format spec to a value:
+/// `repr(x)`, then `format(..., "spec")`. Both are Python builtins.
+pub fn format_value(value: Expr, conversion: Option<char>, format_spec: Option<&str>) -> Expr {
+    let mut value = value;
+    if let Some(conversion) = conversion {
+        let builtin = match conversion {
+            'r' => "repr",
+            'a' => "ascii",
+            _ => "str",
+        };
+        value = call(builtin, vec![value]);
+    }
+    if let Some(spec) = format_spec {
+        value = call("format", vec![value, spec_literal(spec)]);
+    }
+    value
+}
+
+/// A string expression for `spec` without backslashes, which an f-string
+/// field can't contain before Python 3.12: quotes and backslashes become
+/// `chr(..)` calls joined with `+`.
+fn spec_literal(spec: &str) -> Expr {
+    if !spec.contains(['"', '\\']) {
+        return Expr::StringLiteral(StringLiteral {
+            value: spec.to_string(),
+        });
+    }
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    for c in spec.chars() {
+        if c == '"' || c == '\\' {
+            if !literal.is_empty() {
+                parts.push(format!("\"{}\"", std::mem::take(&mut literal)));
+            }
+            parts.push(format!("chr({})", c as u32));
+        } else {
+            literal.push(c);
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(format!("\"{}\"", literal));
+    }
+    Expr::Code(Code {
+        source: parts.join(" + "),
+        range: TextRange::synthetic(),
+    })
+}
+
+fn call(name: &str, args: Vec<Expr>) -> Expr {
+    Expr::Call(ExprCall {
+        func: Box::new(Expr::Name(ExprName {
+            id: Identifier::new(name),
+        })),
+        arguments: Arguments { args },
+    })
 }
 
 /// `Code` spanning explicit source bytes. Source text is independent of the span
@@ -160,35 +261,51 @@ pub fn code_span(source: impl Into<String>, start_byte: usize, end_byte: usize)
 
 /// `Code` for an interpolation: source is the printed expr text; range is the
 /// `{expr}` span minus its braces. Synthetic stays synthetic.
-fn interp_code(source: &str, brace_range: TextRange) -> Code {
-    if brace_range.is_synthetic() {
+fn interp_code(expr: &ExpressionNode) -> Code {
+    if expr.range.is_synthetic() {
         return Code {
-            source: source.to_string(),
-            range: brace_range,
+            source: expr.expr.clone(),
+            range: expr.range,
         };
     }
-    code_span(source, brace_range.start.byte + 1, brace_range.end.byte - 1)
+    let (start, end) = expr.code_span();
+    code_span(expr.expr.as_str(), start, end)
 }
 
 /// `helper(arg)` where `arg` is verbatim user `Code`. Used for single-argument
 /// helpers (`escape`, `render_class`, `render_style`, `render_data`,
 /// `render_aria`, `spread_attrs`).
 pub fn helper_call(name: &str, arg: Code) -> Expr {
-    Expr::Call(ExprCall {
-        func: Box::new(Expr::Name(ExprName {
-            id: Identifier::new(name),
-        })),
-        arguments: Arguments {
-            args: vec![Expr::Code(arg)],
-        },
-    })
+    call(name, vec![Expr::Code(arg)])
 }
 
 /// `render_attr("name", arg)`: the static attribute name plus the user `Code`.
 pub fn render_attr_call(attr_name: &str, arg: Code) -> Expr {
+    named_attr_call("render_attr", attr_name, arg)
+}
+
+/// `render_optional_attr("name", arg)`, for `name?={arg}`.
+pub fn render_optional_attr_call(attr_name: &str, arg: Code) -> Expr {
+    named_attr_call("render_optional_attr", attr_name, arg)
+}
+
+/// `render_data({"key": arg})` or `render_aria({"key": arg})` for a single
+/// `data-key`/`aria-key` attribute, so it renders exactly as the `{data}` and
+/// `{aria}` dicts would.
+pub fn prefixed_attr_call(helper: &str, key: &str, arg: Code) -> Expr {
+    let item = DictItem {
+        key: Expr::StringLiteral(StringLiteral {
+            value: key.to_string(),
+        }),
+        value: Expr::Code(arg),
+    };
+    call(helper, vec![Expr::Dict(ExprDict { items: vec![item] })])
+}
+
+fn named_attr_call(func: &str, attr_name: &str, arg: Code) -> Expr {
     Expr::Call(ExprCall {
         func: Box::new(Expr::Name(ExprName {
-            id: Identifier::new("render_attr"),
+            id: Identifier::new(func),
         })),
         arguments: Arguments {
             args: vec![
@@ -1,12 +1,23 @@
 use clap::{Parser, Subcommand};
-use hyper::generate::{ExpressionBrace, Segment};
-use hyper::{CompileOptions, compile};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use hyper::json::{compile_json, diagnostic_to_json, error_to_json, result_to_json};
+use hyper::{CompileOptions, CompileResult, compile};
+#[cfg(not(target_arch = "wasm32"))]
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::{self, IsTerminal, Read};
-use std::path::Path;
-use std::time::Instant;
+use std::path::{Path, PathBuf};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
 use walkdir::WalkDir;
 
+/// How long a file must stay unchanged before watch mode regenerates it.
+/// Editors often save in two writes; waiting lets both land first.
+#[cfg(not(target_arch = "wasm32"))]
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(150);
+
 #[derive(Parser)]
 #[command(name = "hyper")]
 #[command(about = "Hyper - Python templates with HTML and control flow")]
@@ -26,10 +37,19 @@ enum Commands {
         #[arg(long)]
         stdin: bool,
 
+        /// Path the stdin source came from; its stem names the component
+        /// (unless --name is given) and it labels errors
+        #[arg(long, value_name = "PATH", requires = "stdin")]
+        stdin_filename: Option<String>,
+
         /// Output as JSON with source mappings
         #[arg(long)]
         json: bool,
 
+        /// Indent --json output for reading by hand (ignored without --json)
+        #[arg(long)]
+        pretty: bool,
+
         /// Include injection pieces for IDE integration
         #[arg(long)]
         injection: bool,
@@ -41,6 +61,45 @@ enum Commands {
         /// Run as daemon: read length-prefixed messages from stdin
         #[arg(long)]
         daemon: bool,
+
+        /// Keep running and regenerate .hyper files when they change
+        #[arg(long)]
+        watch: bool,
+
+        /// Write .py files under this directory, mirroring the input layout,
+        /// instead of next to each .hyper file
+        #[arg(long, value_name = "PATH")]
+        out_dir: Option<PathBuf>,
+
+        /// Start each generated file with a "do not edit" comment naming its source
+        #[arg(long)]
+        banner: bool,
+
+        /// Report errors on stdout as JSON Lines, one object per error
+        #[arg(long)]
+        diagnostics: bool,
+
+        /// Compile every file and report errors without writing any output
+        #[arg(long, conflicts_with_all = ["out_dir", "watch", "stdin", "daemon"])]
+        check: bool,
+
+        /// Also write an __init__.py at each input directory (or --out-dir)
+        /// that re-exports every component beneath it
+        #[arg(long, conflicts_with_all = ["check", "watch", "stdin", "daemon"])]
+        flat_exports: bool,
+
+        /// Also write a .pyi stub next to each generated .py, declaring each
+        /// component's signature for type checkers
+        #[arg(long, conflicts_with_all = ["check", "stdin", "daemon"])]
+        stubs: bool,
+
+        /// Don't list each generated file, only the summary
+        #[arg(long, conflicts_with = "verbose")]
+        quiet: bool,
+
+        /// Also show how long each file took and how many source mappings it has
+        #[arg(long)]
+        verbose: bool,
     },
 }
 
@@ -51,147 +110,756 @@ fn main() {
         Commands::Generate {
             files,
             stdin,
+            stdin_filename,
             json,
+            pretty,
             injection,
             name,
             daemon,
+            watch,
+            out_dir,
+            banner,
+            diagnostics,
+            check,
+            flat_exports,
+            stubs,
+            quiet,
+            verbose,
         } => {
+            if let Some(dir) = &out_dir
+                && dir.exists()
+                && !dir.is_dir()
+            {
+                eprintln!(
+                    "error: --out-dir {} exists and is not a directory",
+                    dir.display()
+                );
+                std::process::exit(1);
+            }
+            let out_dir = out_dir.as_deref();
+            let write = WriteOptions { banner, stubs };
+            let verbosity = if quiet {
+                Verbosity::Quiet
+            } else if verbose {
+                Verbosity::Verbose
+            } else {
+                Verbosity::Normal
+            };
+
             if daemon {
                 run_daemon();
             } else if stdin {
-                generate_stdin(json, injection, name);
-            } else {
-                generate_files(files, json, injection, name);
+                generate_stdin(
+                    json,
+                    pretty,
+                    injection,
+                    name,
+                    stdin_filename,
+                    banner,
+                    diagnostics,
+                );
+            } else if watch {
+                generate_files(&files, out_dir, write, diagnostics, false, false, verbosity);
+                watch_files(&files, out_dir, write, diagnostics, verbosity);
+            } else if !generate_files(
+                &files,
+                out_dir,
+                write,
+                diagnostics,
+                check,
+                flat_exports,
+                verbosity,
+            ) {
+                std::process::exit(1);
             }
         }
     }
 }
 
-fn generate_stdin(json_output: bool, include_injections: bool, name: Option<String>) {
+/// What goes into each generated file besides the code
+#[derive(Clone, Copy)]
+struct WriteOptions {
+    /// Start it with a "do not edit" comment naming its source
+    banner: bool,
+    /// Write a .pyi stub next to it
+    stubs: bool,
+}
+
+/// The `--banner` comment for a file generated from `filename`
+fn banner_for(filename: &str) -> String {
+    format!("# Generated by hyper from {}. Do not edit.", filename)
+}
+
+fn generate_stdin(
+    json_output: bool,
+    pretty: bool,
+    include_injections: bool,
+    name: Option<String>,
+    filename: Option<String>,
+    banner: bool,
+    diagnostics: bool,
+) {
     let mut source = String::new();
     if let Err(e) = io::stdin().read_to_string(&mut source) {
         eprintln!("error: failed to read stdin: {}", e);
         std::process::exit(1);
     }
 
+    let function_name = name.or_else(|| {
+        let stem = Path::new(filename.as_deref()?).file_stem()?;
+        stem.to_str().map(str::to_string)
+    });
+    let filename = filename.as_deref().unwrap_or("stdin");
+
     let options = CompileOptions {
-        function_name: name,
+        function_name,
         include_ranges: include_injections,
+        banner: banner.then(|| banner_for(filename)),
+        ..Default::default()
     };
 
     let result = match compile(&source, &options) {
         Ok(r) => r,
         Err(e) => {
-            if json_output {
-                println!("{}", error_to_json(&e));
+            if diagnostics {
+                println!("{}", diagnostic_to_json(&e, filename));
+            } else if json_output {
+                println!("{}", error_to_json(&e, pretty));
             } else {
-                render_error(&e, &source, "stdin");
+                render_error(&e, &source, filename);
             }
             std::process::exit(1);
         }
     };
 
+    render_warnings(&result.warnings, filename);
     if json_output {
-        let output = result_to_response(result, include_injections);
-        println!("{}", serde_json::to_string(&output).unwrap());
+        println!("{}", result_to_json(result, include_injections, pretty));
     } else {
         print!("{}", result.code);
     }
 }
 
+/// Generate every file matched by `files`, or with `check` only compile
+/// them. With `flat_exports`, also write the re-export files once all
+/// succeed. Returns `false` if any failed.
 fn generate_files(
-    files: Vec<String>,
-    _json_output: bool,
-    _include_injections: bool,
-    _name: Option<String>,
-) {
+    files: &[String],
+    out_dir: Option<&Path>,
+    write: WriteOptions,
+    diagnostics: bool,
+    check: bool,
+    flat_exports: bool,
+    verbosity: Verbosity,
+) -> bool {
     let start = Instant::now();
 
-    let files_to_process: Vec<String> = if files.is_empty() {
-        // Recursively discover all .hyper files starting from current directory
-        discover_hyper_files(".")
-    } else {
-        let mut result = Vec::new();
-        for arg in &files {
-            let path = Path::new(arg);
-            if path.is_dir() {
-                result.extend(discover_hyper_files(arg));
+    let files_to_process = collect_hyper_files(files);
+    if files_to_process.is_empty() {
+        eprintln!("No .hyper files found");
+        return false;
+    }
+    if !check && !report_output_collisions(&files_to_process, out_dir) {
+        return false;
+    }
+
+    let mut failed = Vec::new();
+    let mut success_count = 0;
+    let mut components = Vec::new();
+
+    for file in &files_to_process {
+        if let Some(result) = generate_file(file, out_dir, write, diagnostics, check, verbosity) {
+            success_count += 1;
+            for name in result
+                .component_name
+                .into_iter()
+                .chain(result.defined_components)
+            {
+                components.push((file, name));
+            }
+        } else {
+            failed.push(file.path.as_str());
+        }
+    }
+
+    let exports_ok =
+        !flat_exports || !failed.is_empty() || write_flat_exports(&components, out_dir, verbosity);
+
+    if check {
+        print_check_summary(success_count, &failed);
+    } else if success_count > 0 {
+        let elapsed = start.elapsed();
+        print_summary(success_count, elapsed);
+    }
+
+    failed.is_empty() && exports_ok
+}
+
+/// A .hyper file to generate, and the input root its output path mirrors
+#[derive(Clone)]
+struct SourceFile {
+    path: String,
+    root: PathBuf,
+}
+
+impl SourceFile {
+    /// The path relative to its input root, for messages in generated files
+    fn display_name(&self) -> String {
+        let source = Path::new(&self.path);
+        source
+            .strip_prefix(&self.root)
+            .unwrap_or(source)
+            .display()
+            .to_string()
+    }
+
+    /// The directory `output_path` mirrors the input root into
+    fn output_root(&self, out_dir: Option<&Path>) -> PathBuf {
+        out_dir.map_or_else(|| self.root.clone(), Path::to_path_buf)
+    }
+
+    /// Where the generated .py goes: next to the source, or at the same
+    /// relative location under `out_dir`
+    fn output_path(&self, out_dir: Option<&Path>) -> PathBuf {
+        let source = Path::new(&self.path);
+        match out_dir {
+            None => source.with_extension("py"),
+            Some(out_dir) => {
+                let relative = source
+                    .strip_prefix(&self.root)
+                    .unwrap_or_else(|_| Path::new(source.file_name().unwrap_or_default()));
+                out_dir.join(relative).with_extension("py")
+            }
+        }
+    }
+}
+
+/// Resolve CLI arguments to .hyper files. Directories are searched
+/// recursively; no arguments means the current directory. A file reached
+/// through more than one argument is kept once.
+fn collect_hyper_files(files: &[String]) -> Vec<SourceFile> {
+    if files.is_empty() {
+        return discover_hyper_files(".");
+    }
+    let mut result = Vec::new();
+    for arg in files {
+        let path = Path::new(arg);
+        if path.is_dir() {
+            result.extend(discover_hyper_files(arg));
+        } else {
+            result.push(SourceFile {
+                path: arg.clone(),
+                root: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            });
+        }
+    }
+    let mut seen = HashSet::new();
+    result.retain(|file| {
+        let path = Path::new(&file.path);
+        seen.insert(canonical(path))
+    });
+    result
+}
+
+/// Report each source that would write the same .py as an earlier one (e.g.
+/// `a/card.hyper` and `b/card.hyper` with `--out-dir`), naming the first
+/// source to claim it. Paths are compared case-insensitively, since `Card.py`
+/// and `card.py` are the same file on macOS and Windows. Returns `false` if
+/// there were any.
+fn report_output_collisions(files: &[SourceFile], out_dir: Option<&Path>) -> bool {
+    let mut outputs: HashMap<String, &str> = HashMap::new();
+    let mut ok = true;
+    for file in files {
+        let output = file.output_path(out_dir);
+        let key = output.to_string_lossy().to_lowercase();
+        let first = *outputs.entry(key).or_insert(&file.path);
+        if first != file.path {
+            eprintln!(
+                "error: {} and {} would both generate {}",
+                first,
+                file.path,
+                output.display()
+            );
+            ok = false;
+        }
+    }
+    ok
+}
+
+/// Compile a single .hyper file and write its .py, and its .pyi if asked
+/// for, unless `check`.
+/// Errors are reported to stderr, or to stdout as JSON Lines with
+/// `diagnostics`; returns `None` on failure.
+fn generate_file(
+    file: &SourceFile,
+    out_dir: Option<&Path>,
+    write: WriteOptions,
+    diagnostics: bool,
+    check: bool,
+    verbosity: Verbosity,
+) -> Option<CompileResult> {
+    let file_path = file.path.as_str();
+    let source = match fs::read_to_string(file_path) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", file_path, e);
+            return None;
+        }
+    };
+
+    // Extract function name from filename
+    let function_name = Path::new(file_path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_string());
+
+    let options = CompileOptions {
+        function_name,
+        // Only --verbose reports how many mappings each file has
+        include_ranges: verbosity == Verbosity::Verbose,
+        banner: write.banner.then(|| banner_for(&file.display_name())),
+        stub: write.stubs,
+        ..Default::default()
+    };
+
+    let start = Instant::now();
+    let result = match compile(&source, &options) {
+        Ok(r) => r,
+        Err(e) => {
+            if diagnostics {
+                println!("{}", diagnostic_to_json(&e, file_path));
             } else {
-                result.push(arg.clone());
+                render_error(&e, &source, file_path);
             }
+            return None;
         }
-        result
     };
+    let elapsed = start.elapsed();
+    render_warnings(&result.warnings, file_path);
+    if check {
+        return Some(result);
+    }
 
-    if files_to_process.is_empty() {
-        eprintln!("No .hyper files found");
-        std::process::exit(1);
+    // Write to .py file
+    let output_path = file.output_path(out_dir);
+    if let Some(parent) = output_path.parent()
+        && let Err(e) = fs::create_dir_all(parent)
+    {
+        eprintln!("Error creating {}: {}", parent.display(), e);
+        return None;
+    }
+    if let Err(e) = fs::write(&output_path, &result.code) {
+        eprintln!("Error writing {}: {}", output_path.display(), e);
+        return None;
+    }
+    if let Some(stub) = &result.stub {
+        let stub_path = output_path.with_extension("pyi");
+        if let Err(e) = fs::write(&stub_path, stub) {
+            eprintln!("Error writing {}: {}", stub_path.display(), e);
+            return None;
+        }
     }
 
-    let mut has_errors = false;
-    let mut success_count = 0;
+    match verbosity {
+        Verbosity::Quiet => {}
+        Verbosity::Normal => print_generated(&output_path.to_string_lossy()),
+        Verbosity::Verbose => print_generated(&format!(
+            "{} ({}, {} mappings)",
+            output_path.display(),
+            format_duration(elapsed),
+            result.segments.len()
+        )),
+    }
+    Some(result)
+}
 
-    for file_path in files_to_process {
-        let source = match fs::read_to_string(&file_path) {
-            Ok(s) => s,
-            Err(e) => {
-                eprintln!("Error reading {}: {}", file_path, e);
-                has_errors = true;
-                continue;
+/// First line of every `--flat-exports` file, so regenerating can tell its
+/// own output from a hand-written `__init__.py`
+const FLAT_EXPORTS_HEADER: &str = "# Generated by hyper --flat-exports. Do not edit.";
+
+/// Whether `name` is a Python identifier, so it can be imported by name
+fn is_python_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_alphabetic() || first == '_')
+        && chars.all(|ch| ch.is_alphanumeric() || ch == '_')
+}
+
+/// Write an `__init__.py` at each output root re-exporting every component
+/// generated beneath it. A component whose name or module path isn't a
+/// Python identifier can't be imported, so it's skipped with a warning. Two
+/// components with the same name under one root are an error, as is an
+/// existing `__init__.py` that hyper didn't write. Returns `false` if any
+/// root failed.
+fn write_flat_exports(
+    components: &[(&SourceFile, String)],
+    out_dir: Option<&Path>,
+    verbosity: Verbosity,
+) -> bool {
+    let mut roots: HashMap<PathBuf, Vec<(String, &String, &str)>> = HashMap::new();
+    for (file, name) in components {
+        let root = file.output_root(out_dir);
+        let output = file.output_path(out_dir);
+        let parts: Vec<_> = output
+            .strip_prefix(&root)
+            .unwrap_or(&output)
+            .with_extension("")
+            .iter()
+            .map(|part| part.to_string_lossy().into_owned())
+            .collect();
+        if !is_python_identifier(name) || !parts.iter().all(|part| is_python_identifier(part)) {
+            eprintln!(
+                "warning: {}: skipping {} in --flat-exports: not importable as a Python name",
+                file.path, name
+            );
+            continue;
+        }
+        let module = parts.join(".");
+        roots
+            .entry(root)
+            .or_default()
+            .push((module, name, file.path.as_str()));
+    }
+
+    let mut ok = true;
+    for (root, mut exports) in roots {
+        exports.sort();
+        let mut names: HashMap<&str, &str> = HashMap::new();
+        let mut collides = false;
+        for (_, name, path) in &exports {
+            let first = *names.entry(name).or_insert(path);
+            if first != *path {
+                eprintln!("error: {} and {} would both export {}", first, path, name);
+                collides = true;
             }
-        };
+        }
+        let init = root.join("__init__.py");
+        if collides {
+            ok = false;
+            continue;
+        }
+        if let Ok(existing) = fs::read_to_string(&init)
+            && existing.lines().next() != Some(FLAT_EXPORTS_HEADER)
+        {
+            eprintln!(
+                "error: {} already exists and wasn't generated by --flat-exports",
+                init.display()
+            );
+            ok = false;
+            continue;
+        }
 
-        // Extract function name from filename
-        let function_name = Path::new(&file_path)
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .map(|s| s.to_string());
+        let mut code = format!("{}\n", FLAT_EXPORTS_HEADER);
+        for (module, name, _) in &exports {
+            code.push_str(&format!("from .{} import {}\n", module, name));
+        }
+        let mut all: Vec<_> = exports
+            .iter()
+            .map(|(_, name, _)| format!("\"{}\"", name))
+            .collect();
+        all.sort();
+        code.push_str(&format!("\n__all__ = [{}]\n", all.join(", ")));
+        if let Err(e) = fs::write(&init, code) {
+            eprintln!("Error writing {}: {}", init.display(), e);
+            ok = false;
+            continue;
+        }
+        if verbosity != Verbosity::Quiet {
+            print_generated(&init.to_string_lossy());
+        }
+    }
+    ok
+}
 
-        let options = CompileOptions {
-            function_name,
-            include_ranges: false,
+/// Watch the given files and directories, regenerating each .hyper file
+/// once it has been modified and then left alone for `WATCH_DEBOUNCE`.
+/// Errors are reported and watching continues. The inputs are walked once;
+/// after that, only new files and directories are looked at.
+#[cfg(not(target_arch = "wasm32"))]
+fn watch_files(
+    files: &[String],
+    out_dir: Option<&Path>,
+    write: WriteOptions,
+    diagnostics: bool,
+    verbosity: Verbosity,
+) -> ! {
+    let (events, changes) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(events).unwrap_or_else(|e| {
+        eprintln!("Error: Cannot watch for changes: {}", e);
+        std::process::exit(1);
+    });
+    let roots = if files.is_empty() {
+        vec![".".to_string()]
+    } else {
+        files.to_vec()
+    };
+    for root in &roots {
+        let path = Path::new(root);
+        // Editors often save by renaming a new file over the old one, which
+        // ends a watch on the file itself, so watch its directory instead
+        let watched = if path.is_dir() {
+            watcher.watch(path, RecursiveMode::Recursive)
+        } else {
+            let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+            watcher.watch(dir.unwrap_or(Path::new(".")), RecursiveMode::NonRecursive)
         };
+        if let Err(e) = watched {
+            eprintln!("Error: Cannot watch {}: {}", root, e);
+        }
+    }
+    eprintln!("\nWatching for changes...");
 
-        let result = match compile(&source, &options) {
-            Ok(r) => r,
-            Err(e) => {
-                render_error(&e, &source, &file_path);
-                has_errors = true;
-                continue;
-            }
+    // Sources by canonical path, kept up to date from the events
+    let mut sources: HashMap<PathBuf, SourceFile> = collect_hyper_files(files)
+        .into_iter()
+        .map(|file| (canonical(Path::new(&file.path)), file))
+        .collect();
+    let mut dirs: Vec<WatchedDir> = roots
+        .iter()
+        .filter(|root| Path::new(root).is_dir())
+        .map(|root| WatchedDir::new(root))
+        .collect();
+    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+    loop {
+        // Sleep until the next event, or until the oldest change has settled
+        let settled = pending
+            .values()
+            .map(|changed| WATCH_DEBOUNCE.saturating_sub(changed.elapsed()))
+            .min();
+        let event = match settled {
+            Some(timeout) => changes.recv_timeout(timeout).ok(),
+            None => changes.recv().ok(),
         };
+        if let Some(Ok(event)) = event
+            && matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+        {
+            let created = matches!(event.kind, EventKind::Create(_));
+            for path in event.paths {
+                let key = canonical(&path);
+                if sources.contains_key(&key) {
+                    // A later write restarts the debounce window
+                    pending.insert(key, Instant::now());
+                    continue;
+                }
+                // New to the inputs: a .hyper file, or a directory that may
+                // hold some
+                let is_hyper = path.extension().is_some_and(|ext| ext == "hyper");
+                if !(is_hyper || (created && path.is_dir())) {
+                    continue;
+                }
+                let Some(dir) = dirs.iter_mut().find(|dir| key.starts_with(&dir.canonical)) else {
+                    continue;
+                };
+                for file in dir.discover(&key) {
+                    let key = canonical(Path::new(&file.path));
+                    pending.insert(key.clone(), Instant::now());
+                    sources.insert(key, file);
+                }
+            }
+        }
 
-        // Write to .py file
-        let output_path = Path::new(&file_path).with_extension("py");
-        if let Err(e) = fs::write(&output_path, &result.code) {
-            eprintln!("Error writing {}: {}", output_path.display(), e);
-            has_errors = true;
+        let ready: HashSet<PathBuf> = pending
+            .iter()
+            .filter(|(_, changed)| changed.elapsed() >= WATCH_DEBOUNCE)
+            .map(|(path, _)| path.clone())
+            .collect();
+        if ready.is_empty() {
             continue;
         }
-
-        print_generated(&output_path.to_string_lossy());
-        success_count += 1;
+        pending.retain(|path, _| !ready.contains(path));
+        for path in ready {
+            // Skip files deleted before the debounce elapsed
+            if let Some(file) = sources.get(&path)
+                && Path::new(&file.path).is_file()
+            {
+                generate_file(file, out_dir, write, diagnostics, false, verbosity);
+            }
+        }
     }
+}
 
-    if success_count > 0 {
-        let elapsed = start.elapsed();
-        print_summary(success_count, elapsed);
+#[cfg(target_arch = "wasm32")]
+fn watch_files(
+    _files: &[String],
+    _out_dir: Option<&Path>,
+    _write: WriteOptions,
+    _diagnostics: bool,
+    _verbosity: Verbosity,
+) -> ! {
+    eprintln!("Error: --watch isn't available on this platform");
+    std::process::exit(1);
+}
+
+/// A directory input in watch mode, for finding files created under it
+#[cfg(not(target_arch = "wasm32"))]
+struct WatchedDir {
+    /// The directory as given on the command line
+    root: String,
+    canonical: PathBuf,
+    ignores: HyperIgnores,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl WatchedDir {
+    fn new(root: &str) -> Self {
+        Self {
+            root: root.to_string(),
+            canonical: canonical(Path::new(root)),
+            ignores: HyperIgnores::new(root),
+        }
     }
 
-    if has_errors {
-        std::process::exit(1);
+    /// The .hyper files at or under `path`, a canonical path inside this
+    /// directory, unless a `.hyperignore` excludes them
+    fn discover(&mut self, path: &Path) -> Vec<SourceFile> {
+        let Ok(relative) = path.strip_prefix(&self.canonical) else {
+            return Vec::new();
+        };
+        let root = Path::new(&self.root);
+        let start = root.join(relative);
+        let ignores = &mut self.ignores;
+        let ignored = start
+            .ancestors()
+            .take_while(|ancestor| *ancestor != root)
+            .any(|ancestor| ignores.is_ignored(ancestor, ancestor != start || start.is_dir()));
+        if ignored {
+            return Vec::new();
+        }
+        WalkDir::new(&start)
+            .into_iter()
+            .filter_entry(|e| {
+                e.depth() == 0 || !ignores.is_ignored(e.path(), e.file_type().is_dir())
+            })
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "hyper"))
+            .map(|e| SourceFile {
+                path: e.path().to_string_lossy().to_string(),
+                root: root.to_path_buf(),
+            })
+            .collect()
     }
 }
 
-fn discover_hyper_files(dir: &str) -> Vec<String> {
+/// `path` with symlinks and `..` resolved, or as given if it doesn't exist
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Every .hyper file under `dir`, except those a `.hyperignore` excludes
+fn discover_hyper_files(dir: &str) -> Vec<SourceFile> {
+    let mut ignores = HyperIgnores::new(dir);
     WalkDir::new(dir)
         .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !ignores.is_ignored(e.path(), e.file_type().is_dir()))
         .filter_map(|e| e.ok())
         .filter(|e| e.path().extension().is_some_and(|ext| ext == "hyper"))
-        .map(|e| e.path().to_string_lossy().to_string())
+        .map(|e| SourceFile {
+            path: e.path().to_string_lossy().to_string(),
+            root: PathBuf::from(dir),
+        })
         .collect()
 }
 
+/// File holding gitignore-style globs of templates `generate` should skip
+/// when searching a directory. Each one applies to its own directory and
+/// everything beneath it.
+const HYPERIGNORE: &str = ".hyperignore";
+
+/// The `.hyperignore` files from a search root down, loaded once per
+/// directory
+struct HyperIgnores {
+    root: PathBuf,
+    by_dir: HashMap<PathBuf, Option<IgnorePatterns>>,
+}
+
+/// Globs from one `.hyperignore`, matched against paths relative to its
+/// directory
+struct IgnorePatterns {
+    any: GlobSet,
+    /// Patterns written with a trailing `/`, which only match directories
+    dirs: GlobSet,
+}
+
+impl HyperIgnores {
+    fn new(root: &str) -> Self {
+        Self {
+            root: PathBuf::from(root),
+            by_dir: HashMap::new(),
+        }
+    }
+
+    /// Whether a `.hyperignore` between the root and `path` matches it
+    fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let root = &self.root;
+        let mut dirs = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(root));
+        dirs.any(|dir| {
+            let patterns = self
+                .by_dir
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| IgnorePatterns::load(dir));
+            patterns.as_ref().is_some_and(|patterns| {
+                let relative = path.strip_prefix(dir).unwrap_or(path);
+                patterns.any.is_match(relative) || (is_dir && patterns.dirs.is_match(relative))
+            })
+        })
+    }
+}
+
+impl IgnorePatterns {
+    fn load(dir: &Path) -> Option<Self> {
+        let path = dir.join(HYPERIGNORE);
+        let text = fs::read_to_string(&path).ok()?;
+        let mut any = GlobSetBuilder::new();
+        let mut dirs = GlobSetBuilder::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (pattern, dir_only) = match line.strip_suffix('/') {
+                Some(pattern) => (pattern, true),
+                None => (line, false),
+            };
+            // Like .gitignore, a pattern with no inner `/` matches at any depth
+            let pattern = match pattern.strip_prefix('/') {
+                Some(anchored) => anchored.to_string(),
+                None if pattern.contains('/') => pattern.to_string(),
+                None => format!("**/{}", pattern),
+            };
+            let glob = match GlobBuilder::new(&pattern).literal_separator(true).build() {
+                Ok(glob) => glob,
+                Err(e) => {
+                    eprintln!("warning: {}: skipping {:?}: {}", path.display(), line, e);
+                    continue;
+                }
+            };
+            if dir_only {
+                dirs.add(glob);
+            } else {
+                any.add(glob);
+            }
+        }
+        Some(Self {
+            any: any.build().ok()?,
+            dirs: dirs.build().ok()?,
+        })
+    }
+}
+
+/// How much `generate` reports about each file it writes
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Verbosity {
+    /// Only the summary
+    Quiet,
+    /// A line per file
+    Normal,
+    /// A line per file, with its compile time and mapping count
+    Verbose,
+}
+
 fn print_generated(path: &str) {
     let is_tty = io::stderr().is_terminal();
     if is_tty {
@@ -216,6 +884,37 @@ fn print_summary(count: usize, elapsed: std::time::Duration) {
     }
 }
 
+/// `--check` result: a count when everything compiled, otherwise the
+/// failing files (their errors were already reported)
+fn print_check_summary(ok_count: usize, failed: &[&str]) {
+    let is_tty = io::stderr().is_terminal();
+    if failed.is_empty() {
+        let files_word = if ok_count == 1 { "file" } else { "files" };
+        if is_tty {
+            eprintln!("\n\x1b[32m✓\x1b[0m {} {} OK", ok_count, files_word);
+        } else {
+            eprintln!("\n✓ {} {} OK", ok_count, files_word);
+        }
+        return;
+    }
+
+    let total = ok_count + failed.len();
+    let files_word = if total == 1 { "file" } else { "files" };
+    if is_tty {
+        eprintln!(
+            "\n\x1b[31m✗\x1b[0m {} of {} {} failed:",
+            failed.len(),
+            total,
+            files_word
+        );
+    } else {
+        eprintln!("\n✗ {} of {} {} failed:", failed.len(), total, files_word);
+    }
+    for path in failed {
+        eprintln!("  {}", path);
+    }
+}
+
 fn format_duration(d: std::time::Duration) -> String {
     let micros = d.as_micros();
     if micros < 1000 {
@@ -323,28 +1022,23 @@ fn process_request(json: &str) -> String {
     let options = CompileOptions {
         function_name: req.name,
         include_ranges: req.injection,
+        ..Default::default()
     };
 
-    let result = match compile(&req.content, &options) {
-        Ok(r) => r,
-        Err(e) => return error_to_json(&e),
-    };
-
-    let response = result_to_response(result, req.injection);
-    serde_json::to_string(&response).unwrap_or_else(|e| format!(r#"{{"error":"{}"}}"#, e))
+    compile_json(&req.content, &options)
 }
 
-#[derive(serde::Serialize)]
-struct DaemonErrorResponse {
-    error: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error_line: Option<usize>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error_col: Option<usize>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error_end_line: Option<usize>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error_end_col: Option<usize>,
+fn render_warnings(warnings: &[hyper::Warning], filename: &str) {
+    for warning in warnings {
+        let start = warning.range.start;
+        eprintln!(
+            "warning: {}:{}:{}: {}",
+            filename,
+            start.line + 1,
+            start.col + 1,
+            warning
+        );
+    }
 }
 
 fn render_error(e: &hyper::CompileError, source: &str, filename: &str) {
@@ -354,45 +1048,3 @@ fn render_error(e: &hyper::CompileError, source: &str, filename: &str) {
         eprint!("{}", e.render(source, filename));
     }
 }
-
-fn result_to_response(result: hyper::CompileResult, include_injections: bool) -> DaemonResponse {
-    DaemonResponse {
-        compiled: result.code,
-        segments: include_injections.then_some(result.segments),
-        expression_braces: include_injections.then_some(result.expression_braces),
-    }
-}
-
-fn error_to_json(e: &hyper::CompileError) -> String {
-    use hyper::CompileError;
-
-    let (line, col, end_line, end_col) = match e {
-        CompileError::Parse(parse_err) => (
-            Some(parse_err.range.start.line),
-            Some(parse_err.range.start.col),
-            Some(parse_err.range.end.line),
-            Some(parse_err.range.end.col),
-        ),
-        CompileError::Generate(_) => (None, None, None, None),
-    };
-
-    let response = DaemonErrorResponse {
-        error: e.to_string(),
-        error_line: line,
-        error_col: col,
-        error_end_line: end_line,
-        error_end_col: end_col,
-    };
-
-    serde_json::to_string(&response)
-        .unwrap_or_else(|_| format!(r#"{{"error":"{}"}}"#, e.to_string().replace('"', "\\\"")))
-}
-
-#[derive(serde::Serialize)]
-struct DaemonResponse {
-    compiled: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    segments: Option<Vec<Segment>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    expression_braces: Option<Vec<ExpressionBrace>>,
-}
@@ -0,0 +1,289 @@
+//! Indented dump of the parsed node tree, for snapshot tests and bug reports.
+//! One line per node: kind, its name or code, and the 1-based source span.
+
+use super::{HyperParser, Parser, TextRange};
+use crate::ast::*;
+use crate::error::ParseResult;
+
+/// Parse `source` and render its node tree, e.g. for `<div>{x}</div>`:
+///
+/// ```text
+/// Element div 1:1-1:15
+///   Expression x 1:6-1:9
+/// ```
+pub fn debug_ast(source: &str) -> ParseResult<String> {
+    let nodes = HyperParser::new().parse(source)?;
+    let mut out = String::new();
+    write_nodes(&mut out, &nodes, 0);
+    Ok(out)
+}
+
+fn write_nodes(out: &mut String, nodes: &[Node], depth: usize) {
+    for node in nodes {
+        write_node(out, node, depth);
+    }
+}
+
+fn write_line(out: &mut String, depth: usize, label: &str, range: &TextRange) {
+    out.push_str(&"  ".repeat(depth));
+    out.push_str(label);
+    if !range.is_synthetic() {
+        out.push_str(&format!(
+            " {}:{}-{}:{}",
+            range.start.line + 1,
+            range.start.col + 1,
+            range.end.line + 1,
+            range.end.col + 1
+        ));
+    }
+    out.push('\n');
+}
+
+/// Widen an opening-tag range to its closing tag, so the span covers the
+/// whole element.
+fn with_close(open: TextRange, close: Option<TextRange>) -> TextRange {
+    match close {
+        Some(close) => TextRange {
+            start: open.start,
+            end: close.end,
+        },
+        None => open,
+    }
+}
+
+/// A branch header (`else`, `except ValueError`) without a span of its own.
+fn write_branch(out: &mut String, depth: usize, label: &str, body: &[Node]) {
+    out.push_str(&"  ".repeat(depth));
+    out.push_str(label);
+    out.push('\n');
+    write_nodes(out, body, depth + 1);
+}
+
+fn write_node(out: &mut String, node: &Node, depth: usize) {
+    match node {
+        Node::Text(text) => {
+            write_line(out, depth, &format!("Text {:?}", text.content), &text.range)
+        }
+        Node::Expression(expr) => write_line(
+            out,
+            depth,
+            &format!("Expression {}", expr.expr),
+            &expr.range,
+        ),
+        Node::Comment(comment) => write_line(
+            out,
+            depth,
+            &format!("Comment {}", comment.text),
+            &comment.range,
+        ),
+        Node::Element(el) => {
+            let range = with_close(el.range, el.close_range);
+            write_line(out, depth, &format!("Element {}", el.tag), &range);
+            write_attributes(out, &el.attributes, depth + 1);
+            write_nodes(out, &el.children, depth + 1);
+        }
+        Node::Component(c) => {
+            let range = with_close(c.range, c.close_range);
+            write_line(out, depth, &format!("Component {}", c.name), &range);
+            write_attributes(out, &c.attributes, depth + 1);
+            write_nodes(out, &c.children, depth + 1);
+        }
+        Node::Fragment(fragment) => {
+            write_line(out, depth, "Fragment", &fragment.range);
+            write_nodes(out, &fragment.children, depth + 1);
+        }
+        Node::Slot(slot) => {
+            let label = match &slot.name {
+                Some(name) => format!("Slot {}", name),
+                None => "Slot".to_string(),
+            };
+            let range = with_close(slot.range, slot.close_range);
+            write_line(out, depth, &label, &range);
+            write_nodes(out, &slot.fallback, depth + 1);
+        }
+        Node::If(if_node) => {
+            write_line(
+                out,
+                depth,
+                &format!("If {}", if_node.condition),
+                &if_node.range,
+            );
+            write_nodes(out, &if_node.then_branch, depth + 1);
+            for (condition, _, body, _) in &if_node.elif_branches {
+                write_branch(out, depth, &format!("Elif {}", condition), body);
+            }
+            if let Some(body) = &if_node.else_branch {
+                write_branch(out, depth, "Else", body);
+            }
+        }
+        Node::For(for_node) => {
+            let label = format!("For {} in {}", for_node.binding, for_node.iterable);
+            write_line(out, depth, &label, &for_node.range);
+            write_nodes(out, &for_node.body, depth + 1);
+            if let Some(body) = &for_node.else_branch {
+                write_branch(out, depth, "Else", body);
+            }
+        }
+        Node::Match(match_node) => {
+            let label = format!("Match {}", match_node.expr);
+            write_line(out, depth, &label, &match_node.range);
+            for case in &match_node.cases {
+                write_line(
+                    out,
+                    depth + 1,
+                    &format!("Case {}", case.pattern),
+                    &case.range,
+                );
+                write_nodes(out, &case.body, depth + 2);
+            }
+        }
+        Node::While(while_node) => {
+            let label = format!("While {}", while_node.condition);
+            write_line(out, depth, &label, &while_node.range);
+            write_nodes(out, &while_node.body, depth + 1);
+            if let Some(body) = &while_node.else_branch {
+                write_branch(out, depth, "Else", body);
+            }
+        }
+        Node::With(with_node) => {
+            write_line(
+                out,
+                depth,
+                &format!("With {}", with_node.items),
+                &with_node.range,
+            );
+            write_nodes(out, &with_node.body, depth + 1);
+        }
+        Node::Try(try_node) => {
+            write_line(out, depth, "Try", &try_node.range);
+            write_nodes(out, &try_node.body, depth + 1);
+            for clause in &try_node.except_clauses {
+                let label = match &clause.exception {
+                    Some(exception) => format!("Except {}", exception),
+                    None => "Except".to_string(),
+                };
+                write_line(out, depth, &label, &clause.range);
+                write_nodes(out, &clause.body, depth + 1);
+            }
+            if let Some(body) = &try_node.else_clause {
+                write_branch(out, depth, "Else", body);
+            }
+            if let Some(body) = &try_node.finally_clause {
+                write_branch(out, depth, "Finally", body);
+            }
+        }
+        Node::Statement(stmt) => {
+            write_line(out, depth, &format!("Statement {}", stmt.stmt), &stmt.range)
+        }
+        Node::Definition(def) => {
+            write_line(
+                out,
+                depth,
+                &format!("Definition {}", def.signature),
+                &def.range,
+            );
+            write_nodes(out, &def.body, depth + 1);
+        }
+        Node::Import(import) => write_line(
+            out,
+            depth,
+            &format!("Import {}", import.stmt),
+            &import.range,
+        ),
+        Node::Parameter(param) => {
+            let mut label = format!("Parameter {}", param.name);
+            if let Some(hint) = &param.type_hint {
+                label.push_str(&format!(": {}", hint));
+            }
+            if let Some(default) = &param.default {
+                label.push_str(&format!(" = {}", default));
+            }
+            write_line(out, depth, &label, &param.range);
+        }
+        Node::Decorator(decorator) => write_line(
+            out,
+            depth,
+            &format!("Decorator {}", decorator.decorator),
+            &decorator.range,
+        ),
+    }
+}
+
+fn write_attributes(out: &mut String, attributes: &[Attribute], depth: usize) {
+    for attr in attributes {
+        let label = match &attr.kind {
+            AttributeKind::Static { name, value } => format!("{}={:?}", name, value),
+            AttributeKind::Expression { name, expr, .. } => format!("{}={{{}}}", name, expr),
+            AttributeKind::Template { name, value } => format!("{}={:?}", name, value),
+            AttributeKind::Boolean { name } => name.clone(),
+            AttributeKind::Shorthand { name, .. } => format!("{{{}}}", name),
+            AttributeKind::Spread { expr, .. } => format!("{{**{}}}", expr),
+            AttributeKind::SlotAssignment { name, .. } => format!("{{...{}}}", name),
+        };
+        write_line(out, depth, &format!("Attribute {}", label), &attr.range);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::debug_ast;
+
+    #[test]
+    fn test_element_with_expression() {
+        assert_eq!(
+            debug_ast("<div>{x}</div>").unwrap(),
+            "Element div 1:1-1:15\n  Expression x 1:6-1:9\n"
+        );
+    }
+
+    #[test]
+    fn test_inline_component_children() {
+        assert_eq!(
+            debug_ast("<{Card}>Hello {name}</{Card}>").unwrap(),
+            "Component Card 1:1-1:30\n  Text \"Hello \" 1:9-1:15\n  Expression name 1:15-1:21\n"
+        );
+        // Also after other content on the line
+        assert_eq!(
+            debug_ast("<p><{Card}>Hi</{Card}></p>").unwrap(),
+            "Element p 1:1-1:27\n  Component Card 1:4-1:23\n    Text \"Hi\" 1:12-1:14\n"
+        );
+    }
+
+    #[test]
+    fn test_anonymous_fragment() {
+        assert_eq!(
+            debug_ast("<>a<br />b</>").unwrap(),
+            "Fragment 1:1-1:14\n  Text \"a\" 1:3-1:4\n  Element br 1:4-1:10\n  Text \"b\" 1:10-1:11\n"
+        );
+    }
+
+    #[test]
+    fn test_wrapped_with_is_one_block() {
+        let source = "with (\n    a() as x,\n    b() as y,\n):\n    {x}\nend\n";
+        let dump = debug_ast(source).unwrap();
+        assert!(
+            dump.starts_with("With (\n    a() as x,\n    b() as y,\n): 1:1-4:3\n"),
+            "{dump}"
+        );
+        assert_eq!(dump.matches("With").count(), 1, "{dump}");
+    }
+
+    #[test]
+    fn test_control_flow_branches() {
+        let source = "if a:\n    <p class=\"x\">{b}</p>\nelse:\n    Text\nend\n";
+        assert_eq!(
+            debug_ast(source).unwrap(),
+            "\
+If a: 1:1-1:6
+  Element p 2:5-2:25
+    Attribute class=\"x\" 2:8-2:17
+    Expression b 2:18-2:21
+  Text \"\\n\" 2:25-3:1
+Else
+  Text \"    \" 4:1-4:5
+  Text \"Text\" 4:5-4:9
+  Text \"\\n\" 4:9-5:1
+"
+        );
+    }
+}
@@ -1,17 +1,24 @@
+mod debug;
+mod python_parser;
 pub mod tokenizer;
 mod tree_builder;
 
+pub use debug::debug_ast;
+pub(crate) use python_parser::with_python_parser;
 pub use tokenizer::{Position, TextRange, Token, tokenize};
-use tree_builder::TreeBuilder;
+pub(crate) use tree_builder::TreeBuilder;
 
 use crate::ast::Node;
-use crate::error::ParseResult;
+use crate::error::{ParseResult, Warning};
 use std::sync::Arc;
 
 /// Parsed syntax plus file markers that do not become render nodes.
 pub(crate) struct ParsedFile {
     pub nodes: Vec<Node>,
     pub has_separator: bool,
+    pub front_matter: Option<String>,
+    /// Escaped braces around what reads like a dict or set literal
+    pub warnings: Vec<Warning>,
 }
 
 /// Parser trait - converts source code to a flat node stream (lowered later).
@@ -31,12 +38,22 @@ impl HyperParser {
 
     pub(crate) fn parse_file(&self, source: &str) -> ParseResult<ParsedFile> {
         let tokens = tokenize(source)?;
+        let warnings = escaped_literals(&tokens)
+            .into_iter()
+            .map(|range| Warning {
+                message: "`{{...}}` prints literal braces. Write `{ {...} }`, with spaces, to render a dict or set."
+                    .to_string(),
+                range,
+            })
+            .collect();
         let source_arc: Arc<str> = Arc::from(source);
         let mut builder = TreeBuilder::new(tokens, source_arc);
         let nodes = builder.build()?;
         Ok(ParsedFile {
             nodes,
             has_separator: builder.has_separator(),
+            front_matter: builder.take_front_matter(),
+            warnings,
         })
     }
 }
@@ -52,3 +69,80 @@ impl Parser for HyperParser {
         self.parse_file(source).map(|file| file.nodes)
     }
 }
+
+/// Leading whitespace runs that mix tabs and spaces, one range per line
+pub fn mixed_indentation(source: &str) -> Vec<TextRange> {
+    let mut ranges = Vec::new();
+    let mut line_start = 0;
+    for (line, text) in source.split('\n').enumerate() {
+        let width = text.len() - text.trim_start_matches([' ', '\t']).len();
+        let indent = &text[..width];
+        if indent.contains(' ') && indent.contains('\t') {
+            ranges.push(TextRange {
+                start: Position {
+                    line,
+                    col: 0,
+                    byte: line_start,
+                },
+                end: Position {
+                    line,
+                    col: width,
+                    byte: line_start + width,
+                },
+            });
+        }
+        line_start += text.len() + 1;
+    }
+    ranges
+}
+
+/// Spans of `{{...}}` in content whose inside, between single braces, would
+/// be a dict, set or comprehension. `{{` always escapes, so these print as
+/// text, which is rarely what the author meant.
+pub fn escaped_literals(tokens: &[Token]) -> Vec<TextRange> {
+    tokens
+        .windows(3)
+        .filter_map(|window| match window {
+            [
+                Token::EscapedBrace {
+                    brace: '{',
+                    range: open,
+                },
+                Token::Text { text, range },
+                Token::EscapedBrace {
+                    brace: '}',
+                    range: close,
+                },
+            ] if open.end == range.start
+                && range.end == close.start
+                && is_collection_literal(&format!("{{{text}}}")) =>
+            {
+                Some(TextRange {
+                    start: open.start,
+                    end: close.end,
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether `code` is exactly one dict, comprehension, or set of two or more
+/// items. A one-item set such as `{ name }` reads as another template
+/// language's placeholder instead.
+fn is_collection_literal(code: &str) -> bool {
+    with_python_parser(|parser| parser.parse(code, None)).is_some_and(|tree| {
+        let root = tree.root_node();
+        let expression = root
+            .named_child(0)
+            .filter(|statement| statement.kind() == "expression_statement")
+            .and_then(|statement| statement.named_child(0));
+        !root.has_error()
+            && root.named_child_count() == 1
+            && expression.is_some_and(|expr| match expr.kind() {
+                "dictionary" | "dictionary_comprehension" | "set_comprehension" => true,
+                "set" => expr.named_child_count() > 1,
+                _ => false,
+            })
+    })
+}
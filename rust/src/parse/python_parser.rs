@@ -0,0 +1,75 @@
+//! A reusable tree-sitter Python parser.
+//!
+//! Loading the grammar into a fresh `tree_sitter::Parser` costs more than most
+//! of the parses we run, so each thread keeps one parser and hands it out.
+//! Long-running hosts (the daemon, editors) compile the same thread many times.
+
+use std::cell::RefCell;
+
+thread_local! {
+    static PARSER: RefCell<Option<tree_sitter::Parser>> = const { RefCell::new(None) };
+}
+
+#[cfg(test)]
+thread_local! {
+    static PARSERS_CREATED: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+fn new_parser() -> tree_sitter::Parser {
+    #[cfg(test)]
+    PARSERS_CREATED.with(|count| count.set(count.get() + 1));
+
+    let mut parser = tree_sitter::Parser::new();
+    parser
+        .set_language(&tree_sitter_python::LANGUAGE.into())
+        .expect("tree-sitter-python grammar is statically linked");
+    parser
+}
+
+/// Run `f` with this thread's Python parser, creating it on first use. A nested
+/// call gets a parser of its own rather than panicking.
+pub(crate) fn with_python_parser<R>(f: impl FnOnce(&mut tree_sitter::Parser) -> R) -> R {
+    PARSER.with(|cell| match cell.try_borrow_mut() {
+        Ok(mut cached) => f(cached.get_or_insert_with(new_parser)),
+        Err(_) => f(&mut new_parser()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompileOptions, compile};
+
+    #[test]
+    fn test_repeated_compiles_reuse_one_parser() {
+        let source = "component Card(*, title: str):\n    <h1>{title}</h1>\nend\n\nx = 1\n<{Card} title={x} />\n";
+        let before = PARSERS_CREATED.with(|count| count.get());
+        for _ in 0..1000 {
+            compile(source, &CompileOptions::default()).unwrap();
+        }
+        let created = PARSERS_CREATED.with(|count| count.get()) - before;
+        assert!(created <= 1, "created {created} parsers for 1000 compiles");
+    }
+
+    #[test]
+    fn test_nested_use_gets_its_own_parser() {
+        let kinds = with_python_parser(|outer| {
+            let inner = with_python_parser(|inner| {
+                inner
+                    .parse("x = 1", None)
+                    .unwrap()
+                    .root_node()
+                    .kind()
+                    .to_string()
+            });
+            let outer = outer
+                .parse("y = 2", None)
+                .unwrap()
+                .root_node()
+                .kind()
+                .to_string();
+            (outer, inner)
+        });
+        assert_eq!(kinds, ("module".to_string(), "module".to_string()));
+    }
+}
@@ -1,7 +1,8 @@
+use super::with_python_parser;
 use crate::error::{ErrorKind, ParseError, ParseResult};
 
 /// Position in source code (byte offset only; convert to UTF-16 at output time)
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
 pub struct Position {
     /// Byte offset in source
     pub byte: usize,
@@ -19,6 +20,27 @@ impl Position {
             col: 0,
         }
     }
+
+    /// The position just past `text`, which starts here
+    pub(crate) fn advanced_over(self, text: &str) -> Self {
+        let mut position = self;
+        for ch in text.chars() {
+            if ch == '\n' {
+                position.line += 1;
+                position.col = 0;
+            } else {
+                position.col += 1;
+            }
+        }
+        position.byte += text.len();
+        position
+    }
+
+    /// Move by a byte and line delta; the column is unchanged
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        self.byte = self.byte.saturating_add_signed(bytes);
+        self.line = self.line.saturating_add_signed(lines);
+    }
 }
 
 impl Default for Position {
@@ -28,7 +50,7 @@ impl Default for Position {
 }
 
 /// TextRange in source code (a range from start position to end position)
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
 pub struct TextRange {
     pub start: Position,
     pub end: Position,
@@ -60,13 +82,15 @@ pub struct Attribute {
     pub name: String,
     pub value: AttributeValue,
     pub range: TextRange,
+    /// `name?={expr}`: rendered only when the value is set
+    pub optional: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AttributeValue {
     /// String literal: attr="value" or attr='value'
     String(String),
-    /// Expression: attr={expr}
+    /// Expression: attr={expr}. The range covers the code between the braces.
     Expression(String, TextRange),
     /// Boolean (no value): disabled
     Bool,
@@ -96,6 +120,8 @@ pub enum Token {
         rest: String,
         range: TextRange,
         rest_range: TextRange,
+        /// Trailing `# comment` on the line, kept for the generated Python
+        comment: Option<String>,
     },
     /// Explicit component declaration header.
     ComponentDefinition { signature: String, range: TextRange },
@@ -105,6 +131,8 @@ pub enum Token {
         rest: Option<String>,
         range: TextRange,
         rest_range: Option<TextRange>,
+        /// Trailing `# comment` on the line, kept for the generated Python
+        comment: Option<String>,
     },
     /// Block terminator: end
     End { range: TextRange },
@@ -122,9 +150,19 @@ pub enum Token {
     // === Content Domain ===
     /// Raw text/HTML content (no expressions)
     Text { text: String, range: TextRange },
-    /// Expression placeholder: {expr}
-    Expression { code: String, range: TextRange },
-    /// Escaped brace: {{ or }}
+    /// Expression placeholder: {expr}. `{- expr -}` sets the trim flags,
+    /// which strip whitespace from the neighbouring text. `{!expr}` sets
+    /// `raw`: the value is trusted HTML and skips escaping. `{&expr}` sets
+    /// `force_escape`: the value is escaped even with escaping off by default.
+    Expression {
+        code: String,
+        range: TextRange,
+        raw: bool,
+        force_escape: bool,
+        trim_left: bool,
+        trim_right: bool,
+    },
+    /// Escaped literal: `{{`, `}}`, or `\<` in content
     EscapedBrace { brace: char, range: TextRange },
 
     // === Components ===
@@ -164,9 +202,17 @@ pub enum Token {
         range: TextRange,
     },
 
+    // === Fragments ===
+    /// Anonymous fragment opening: <>
+    FragmentOpen { range: TextRange },
+    /// Anonymous fragment closing: </>
+    FragmentClose { range: TextRange },
+
     // === File Structure ===
     /// Header/body separator: ---
     Separator { range: TextRange },
+    /// Leading `---` ... `---` metadata block; `raw` is the text between the fences
+    FrontMatter { raw: String, range: TextRange },
 }
 
 impl Token {
@@ -194,18 +240,124 @@ impl Token {
             Token::HtmlElementClose { range, .. } => *range,
             Token::SlotOpen { range, .. } => *range,
             Token::SlotClose { range, .. } => *range,
+            Token::FragmentOpen { range } => *range,
+            Token::FragmentClose { range } => *range,
             Token::Separator { range, .. } => *range,
+            Token::FrontMatter { range, .. } => *range,
+        }
+    }
+
+    /// Move every position in the token, for splicing incremental updates
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        let shift_range = |range: &mut TextRange| {
+            range.start.shift(bytes, lines);
+            range.end.shift(bytes, lines);
+        };
+        let shift_attributes = |attributes: &mut [Attribute]| {
+            for attr in attributes {
+                shift_range(&mut attr.range);
+                match &mut attr.value {
+                    AttributeValue::Expression(_, range)
+                    | AttributeValue::Shorthand(_, range)
+                    | AttributeValue::Spread(_, range)
+                    | AttributeValue::SlotAssignment(_, range) => shift_range(range),
+                    AttributeValue::String(_) | AttributeValue::Bool => {}
+                }
+            }
+        };
+        match self {
+            Token::Eof { position } => position.shift(bytes, lines),
+            Token::ControlStart {
+                range, rest_range, ..
+            } => {
+                shift_range(range);
+                shift_range(rest_range);
+            }
+            Token::ControlContinuation {
+                range, rest_range, ..
+            } => {
+                shift_range(range);
+                if let Some(rest_range) = rest_range {
+                    shift_range(rest_range);
+                }
+            }
+            Token::ComponentOpen {
+                name_range,
+                attributes,
+                range,
+                ..
+            } => {
+                shift_range(name_range);
+                shift_attributes(attributes);
+                shift_range(range);
+            }
+            Token::HtmlElementOpen {
+                tag_range,
+                attributes,
+                close_bracket_pos,
+                range,
+                ..
+            } => {
+                shift_range(tag_range);
+                shift_attributes(attributes);
+                close_bracket_pos.shift(bytes, lines);
+                shift_range(range);
+            }
+            Token::Indent { range, .. }
+            | Token::Newline { range }
+            | Token::ComponentDefinition { range, .. }
+            | Token::End { range }
+            | Token::PythonStatement { range, .. }
+            | Token::Comment { range, .. }
+            | Token::Decorator { range, .. }
+            | Token::Text { range, .. }
+            | Token::Expression { range, .. }
+            | Token::EscapedBrace { range, .. }
+            | Token::ComponentClose { range, .. }
+            | Token::HtmlElementClose { range, .. }
+            | Token::SlotOpen { range, .. }
+            | Token::SlotClose { range, .. }
+            | Token::FragmentOpen { range }
+            | Token::FragmentClose { range }
+            | Token::Separator { range }
+            | Token::FrontMatter { range, .. } => shift_range(range),
         }
     }
 }
 
+/// Attribute that opts a `<style>`/`<script>` body into `{expr}` interpolation
+const INTERPOLATE_ATTRIBUTE: &str = "hyper-interpolate";
+
 /// How to exit raw content mode
 #[derive(Debug, Clone, PartialEq)]
 enum RawContentExit {
-    /// Exit when the matching closing tag is found (e.g. `</style>`, `</script>`)
-    ClosingTag(String),
+    /// Exit when the matching closing tag is found (e.g. `</style>`, `</script>`).
+    /// With `interpolate`, `{expr}` and `{{`/`}}` are still recognized inside.
+    ClosingTag { tag: String, interpolate: bool },
     /// Exit when `end` is found at the given indentation level (for `raw:` blocks)
     EndKeyword { indent: usize },
+    /// Exit after the line closing a fenced code block opened with `fence`
+    /// (e.g. ```` ``` ````) on the line at `opened`. Both fence lines are
+    /// content too.
+    Fence { fence: String, opened: TextRange },
+}
+
+/// The fence (three or more backticks or tildes) opening a CommonMark code
+/// block, such as the ```` ``` ```` of ```` ```python ````. The language can't
+/// contain the fence character, so inline code like ```` ```x``` ```` and a
+/// banner like `~~~ Welcome ~~~` are not fences.
+fn opening_fence(line: &str) -> Option<&str> {
+    let marker = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = line.len() - line.trim_start_matches(marker).len();
+    let (fence, info) = line.split_at(len);
+    (len >= 3 && !info.contains(marker)).then_some(fence)
+}
+
+/// Whether `line` closes a code block opened with `fence`: the same
+/// character, at least as many times, and nothing else.
+fn closes_fence(line: &str, fence: &str) -> bool {
+    let marker = fence.as_bytes()[0] as char;
+    line.len() >= fence.len() && line.chars().all(|c| c == marker)
 }
 
 /// Tokenizer for Hyper source files
@@ -213,13 +365,14 @@ pub struct Tokenizer<'a> {
     source: &'a str,
     bytes: &'a [u8],
     position: Position,
-    /// Parser for Python classification
-    parser: tree_sitter::Parser,
     /// Track if we're inside a multi-line string (""" or ''')
     in_multiline_string: Option<&'static str>,
     /// Track if we're inside raw content (<style>, <script>, or `raw:` block).
     /// Content is emitted as plain text — no expression interpolation or control flow.
     in_raw_content: Option<RawContentExit>,
+    /// Number of open `<pre>` elements. Inside one, whitespace is content:
+    /// lines keep their indentation and are never read as control flow.
+    pre_depth: usize,
 }
 
 /// Context for tracking quote state in content
@@ -232,21 +385,22 @@ enum QuoteCtx {
 
 impl<'a> Tokenizer<'a> {
     pub fn new(source: &'a str) -> Self {
-        let mut parser = tree_sitter::Parser::new();
-        parser
-            .set_language(&tree_sitter_python::LANGUAGE.into())
-            .expect("tree-sitter-python grammar is statically linked");
-
         Self {
             source,
             bytes: source.as_bytes(),
             position: Position::new(),
-            parser,
             in_multiline_string: None,
             in_raw_content: None,
+            pre_depth: 0,
         }
     }
 
+    /// Whether tokenizing can restart here given only the multi-line string
+    /// state: not inside raw content or `<pre>`
+    fn at_resumable_state(&self) -> bool {
+        self.in_raw_content.is_none() && self.pre_depth == 0
+    }
+
     /// Tokenize the entire source
     pub fn tokenize(&mut self) -> ParseResult<Vec<Token>> {
         let mut tokens = Vec::new();
@@ -254,6 +408,15 @@ impl<'a> Tokenizer<'a> {
         while !self.at_eof() {
             self.tokenize_line(&mut tokens)?;
         }
+        if let Some(RawContentExit::Fence { fence, opened }) = &self.in_raw_content {
+            return Err(ParseError::new(
+                ErrorKind::UnclosedBlock,
+                format!("Code block opened with {} is never closed.", fence),
+                *opened,
+            )
+            .with_help(format!("Close it with a line of just {}.", fence))
+            .boxed());
+        }
 
         tokens.push(Token::Eof {
             position: self.position,
@@ -261,8 +424,64 @@ impl<'a> Tokenizer<'a> {
         Ok(tokens)
     }
 
+    /// Emit the rest of the line as text, and its newline
+    fn tokenize_verbatim_line(&mut self, tokens: &mut Vec<Token>) {
+        let text_start = self.position;
+        let text = self.consume_to_eol();
+        tokens.push(Token::Text {
+            text,
+            range: TextRange {
+                start: text_start,
+                end: self.position,
+            },
+        });
+        if self.at_newline() {
+            let nl_start = self.position;
+            self.consume_newline();
+            tokens.push(Token::Newline {
+                range: TextRange {
+                    start: nl_start,
+                    end: self.position,
+                },
+            });
+        }
+    }
+
     /// Tokenize a single line
     fn tokenize_line(&mut self, tokens: &mut Vec<Token>) -> ParseResult<()> {
+        // Front matter: a `---` fenced block opening the file is one token
+        if self.position.byte == 0
+            && let Some((raw, end)) = front_matter(self.source)
+        {
+            let start = self.position;
+            while self.position.byte < end {
+                self.advance();
+            }
+            tokens.push(Token::FrontMatter {
+                raw: raw.to_string(),
+                range: TextRange {
+                    start,
+                    end: self.position,
+                },
+            });
+            if self.at_newline() {
+                let nl_start = self.position;
+                self.consume_newline();
+                tokens.push(Token::Newline {
+                    range: TextRange {
+                        start: nl_start,
+                        end: self.position,
+                    },
+                });
+            }
+            return Ok(());
+        }
+
+        // 0. Inside <pre>, the whole line is content, indentation included
+        if self.pre_depth > 0 && self.in_raw_content.is_none() {
+            return self.tokenize_pre_line(tokens);
+        }
+
         // 1. Handle indentation
         let indent_start = self.position;
         let indent_level = self.consume_indent();
@@ -335,35 +554,32 @@ impl<'a> Tokenizer<'a> {
             let line_content = self.peek_line();
             let trimmed = line_content.trim();
 
-            let should_exit = match &exit_mode {
-                RawContentExit::ClosingTag(tag) => trimmed.starts_with(&format!("</{}", tag)),
-                RawContentExit::EndKeyword { indent } => {
-                    trimmed == "end" && indent_level == *indent
+            if let RawContentExit::ClosingTag { tag, interpolate } = &exit_mode {
+                // The closing tag may appear anywhere on the line; text before it
+                // stays raw and anything after it is tokenized normally.
+                self.tokenize_raw_text(tokens, tag, *interpolate)?;
+            } else if let RawContentExit::Fence { fence, .. } = &exit_mode {
+                if closes_fence(trimmed, fence) {
+                    self.in_raw_content = None;
                 }
-            };
-
-            if should_exit {
+                self.tokenize_verbatim_line(tokens);
+                return Ok(());
+            } else if trimmed == "end"
+                && matches!(exit_mode, RawContentExit::EndKeyword { indent } if indent == indent_level)
+            {
                 self.in_raw_content = None;
-                match exit_mode {
-                    RawContentExit::ClosingTag(_) => {
-                        // Tokenize </style> or </script> normally for tree-builder
-                        self.tokenize_content(tokens)?;
-                    }
-                    RawContentExit::EndKeyword { .. } => {
-                        // `end` closes `raw:` — consume entire line silently.
-                        // Pop the stray Indent token (emitted in step 1 before
-                        // we knew this line was the closing `end`).
-                        if let Some(Token::Indent { .. }) = tokens.last() {
-                            tokens.pop();
-                        }
-                        self.skip_to_eol();
-                        // Consume newline silently (it's the `end` line ending)
-                        if self.at_newline() {
-                            self.consume_newline();
-                        }
-                        return Ok(());
-                    }
+                // `end` closes `raw:` — consume entire line silently.
+                // Pop the stray Indent token (emitted in step 1 before
+                // we knew this line was the closing `end`).
+                if let Some(Token::Indent { .. }) = tokens.last() {
+                    tokens.pop();
+                }
+                self.skip_to_eol();
+                // Consume newline silently (it's the `end` line ending)
+                if self.at_newline() {
+                    self.consume_newline();
                 }
+                return Ok(());
             } else {
                 // For raw: blocks, strip the directive's own indentation from
                 // content lines so the output reflects nesting relative to the
@@ -431,8 +647,49 @@ impl<'a> Tokenizer<'a> {
             }
         }
 
+        // 4c. A fenced code block (```` ``` ```` or `~~~`, with an optional
+        //     language) is kept verbatim, fences included, up to its closing fence.
+        let line = self.peek_line();
+        if let Some(fence) = opening_fence(line.trim()) {
+            let fence = fence.to_string();
+            let start = self.position;
+            self.tokenize_verbatim_line(tokens);
+            let end = Position {
+                byte: start.byte + line.trim_end().len(),
+                col: start.col + line.trim_end().chars().count(),
+                ..start
+            };
+            self.in_raw_content = Some(RawContentExit::Fence {
+                fence,
+                opened: TextRange { start, end },
+            });
+            return Ok(());
+        }
+
+        // 4d. A line holding only a `{# ... #}` comment vanishes entirely.
+        //     Anything after the comment is tokenized as a line of its own.
+        if self.peek_char() == Some('{') && self.peek_next_char() == Some('#') {
+            self.skip_block_comment()?;
+            if self.peek_line().trim().is_empty() {
+                if let Some(Token::Indent { .. }) = tokens.last() {
+                    tokens.pop();
+                }
+                self.skip_to_eol();
+                if self.at_newline() {
+                    self.consume_newline();
+                }
+                return Ok(());
+            }
+            while matches!(self.peek_char(), Some(' ' | '\t')) {
+                self.advance();
+            }
+        }
+
         // 5. Determine line type and tokenize accordingly
         let line_content = self.peek_line();
+        // Python lines are classified as a whole when a trailing `\` continues
+        // them; in content, a trailing `\` is just a backslash
+        let logical_line = self.continued_line(&line_content);
 
         // Check for multi-line string start
         let trimmed = line_content.trim();
@@ -520,19 +777,20 @@ impl<'a> Tokenizer<'a> {
             });
         }
         // 5. Explicit component definition
-        else if self.is_component_definition(&line_content) {
+        else if self.is_component_definition(&logical_line) {
             self.tokenize_component_definition(tokens);
         }
         // 6. Control flow keywords
-        else if self.is_control_flow(&line_content) {
+        else if self.is_control_flow(&logical_line) {
             self.tokenize_control_start(tokens, &line_content);
         }
         // 7. Control continuation keywords (else, elif, except, finally)
         else if self.is_control_continuation(&line_content) {
             self.tokenize_control_continuation(tokens, &line_content);
         }
-        // 7. HTML content (starts with <)
-        else if line_content.starts_with('<') {
+        // 7. HTML content (starts with <), or a `for` line that isn't a loop:
+        // prose like "for your information:"
+        else if line_content.starts_with('<') || for_header(trimmed).is_some() {
             self.tokenize_content(tokens)?;
         }
         // 7.5. HTML assignment (identifier = <...>)
@@ -543,7 +801,7 @@ impl<'a> Tokenizer<'a> {
         // These aren't valid Python statements but are valid in header zone
         // 8. Check if it's a Python statement using tree-sitter
         else if self.is_parameter_declaration(&line_content)
-            || self.is_python_statement(&line_content)
+            || self.is_python_statement(&logical_line)
         {
             self.tokenize_python_statement(tokens);
         }
@@ -569,6 +827,30 @@ impl<'a> Tokenizer<'a> {
         Ok(())
     }
 
+    /// Tokenize a line inside `<pre>`: leading whitespace becomes text rather
+    /// than an Indent token, and the rest is content (tags and `{expr}` still
+    /// work, but `if`, `for` or `end` are just words).
+    fn tokenize_pre_line(&mut self, tokens: &mut Vec<Token>) -> ParseResult<()> {
+        let indent_start = self.position;
+        self.consume_while(|c| c == ' ' || c == '\t');
+        self.flush_raw_text(tokens, indent_start);
+
+        if !self.at_eof() && !self.at_newline() {
+            self.tokenize_content(tokens)?;
+        }
+        if self.at_newline() {
+            let nl_start = self.position;
+            self.consume_newline();
+            tokens.push(Token::Newline {
+                range: TextRange {
+                    start: nl_start,
+                    end: self.position,
+                },
+            });
+        }
+        Ok(())
+    }
+
     // === Classification helpers ===
 
     fn is_end_keyword(&self, line: &str) -> bool {
@@ -587,9 +869,22 @@ impl<'a> Tokenizer<'a> {
         // A trailing comment is `  # ...` (whitespace + hash) outside quotes.
         let effective = self.strip_trailing_comment(trimmed);
 
-        // for: requires trailing `:` (parser validates `in` keyword and reports errors)
-        if trimmed.starts_with("for ") || trimmed.starts_with("async for ") {
-            return effective.ends_with(':');
+        // for: requires trailing `:` and an `in` clause. Without `in`, only a line
+        // opening an indented block is a (malformed) loop, left for the parser to
+        // report; otherwise it is prose like "for your information:".
+        if let Some(header) = for_header(trimmed) {
+            return effective.ends_with(':')
+                && (find_for_in(header).is_some() || self.opens_indented_block());
+        }
+
+        // with: a parenthesized list of managers may wrap across lines, ending
+        // with `):` on the line that closes it
+        if (trimmed.starts_with("with ") || trimmed.starts_with("async with "))
+            && self.calculate_bracket_depth(effective) > 0
+        {
+            let header = self.bracketed_header(trimmed);
+            let last_line = header.lines().last().unwrap_or_default();
+            return self.strip_trailing_comment(last_line.trim()).ends_with(':');
         }
 
         // if, elif, while, match, with: require trailing `:`
@@ -603,7 +898,7 @@ impl<'a> Tokenizer<'a> {
         }
 
         // try: exact keyword (already includes colon)
-        if trimmed == "try:" || trimmed == "try :" {
+        if effective == "try:" || effective == "try :" {
             return true;
         }
 
@@ -624,9 +919,68 @@ impl<'a> Tokenizer<'a> {
         false
     }
 
+    /// Whether the next non-blank line is indented deeper than the current one
+    fn opens_indented_block(&self) -> bool {
+        fn indent_width(line: &str) -> usize {
+            line.chars()
+                .map_while(|c| match c {
+                    ' ' => Some(1),
+                    '\t' => Some(4),
+                    _ => None,
+                })
+                .sum()
+        }
+        let line_start = self.source[..self.position.byte]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+        let current = indent_width(&self.source[line_start..]);
+        self.source[self.position.byte..]
+            .lines()
+            .skip(1)
+            .find(|line| !line.trim().is_empty())
+            .is_some_and(|next| indent_width(next) > current)
+    }
+
+    /// `first_line` plus the following lines up to the one that closes its
+    /// brackets, without consuming anything. `first_line` may already span
+    /// several lines.
+    fn bracketed_header(&self, first_line: &str) -> String {
+        let mut header = first_line.to_string();
+        let mut following = self.source[self.position.byte..]
+            .lines()
+            .skip(1 + first_line.matches('\n').count());
+        while self.calculate_bracket_depth(&header) > 0
+            && let Some(line) = following.next()
+        {
+            header.push('\n');
+            header.push_str(line);
+        }
+        header
+    }
+
+    /// `first_line` plus the following lines it continues with a trailing
+    /// `\`, without consuming anything
+    fn continued_line(&self, first_line: &str) -> String {
+        let mut line = first_line.to_string();
+        let mut following = self.source[self.position.byte..].lines().skip(1);
+        while ends_with_continuation(&line)
+            && let Some(next) = following.next()
+        {
+            line.push('\n');
+            line.push_str(next);
+        }
+        line
+    }
+
     /// Strip a trailing `# comment` from a line (outside quotes) for syntax checks.
     /// Returns the effective code portion of the line.
     fn strip_trailing_comment<'b>(&self, line: &'b str) -> &'b str {
+        self.split_trailing_comment(line).0
+    }
+
+    /// Split a line into its code and its trailing `# comment` (outside quotes),
+    /// if any.
+    fn split_trailing_comment<'b>(&self, line: &'b str) -> (&'b str, Option<&'b str>) {
         let mut in_single = false;
         let mut in_double = false;
         let bytes = line.as_bytes();
@@ -642,12 +996,12 @@ impl<'a> Tokenizer<'a> {
                     // Found unquoted #; check if preceded by whitespace
                     && i > 0 && bytes[i - 1] == b' ' =>
                 {
-                    return line[..i].trim_end();
+                    return (line[..i].trim_end(), Some(&line[i..]));
                 }
                 _ => {}
             }
         }
-        line
+        (line, None)
     }
 
     /// Check if this looks like a parameter declaration (used in header zone)
@@ -672,7 +1026,11 @@ impl<'a> Tokenizer<'a> {
         {
             // Check colon comes before any = (for defaults)
             if let Some(colon_pos) = trimmed.find(':') {
-                if let Some(equals_pos) = trimmed.find('=') {
+                // `name := value` is a walrus, not an annotation
+                if trimmed[colon_pos + 1..].starts_with('=') {
+                    return false;
+                }
+                if let Some(equals_pos) = find_top_level_assignment(trimmed) {
                     return colon_pos < equals_pos;
                 }
                 return true;
@@ -738,6 +1096,12 @@ impl<'a> Tokenizer<'a> {
         // - Lowercase letters followed by content patterns (not Python keywords)
         // - HTML-like content
         // - Pure text without Python operators
+        // A parenthesized walrus, `(user := get_user())`, is the one Python
+        // statement that starts with punctuation
+        if first_char == '(' && trimmed.contains(":=") {
+            return false;
+        }
+
         match first_char {
             // HTML tags or content starting with punctuation (except @ which is decorator)
             '<' | '>' | '&' | '!' | '?' | '/' | '*' | '+' | '-' | '.' | ',' | ';' | ':' | '['
@@ -813,7 +1177,7 @@ impl<'a> Tokenizer<'a> {
         }
 
         // Try to parse with tree-sitter
-        if let Some(tree) = self.parser.parse(trimmed, None) {
+        if let Some(tree) = with_python_parser(|parser| parser.parse(trimmed, None)) {
             let root = tree.root_node();
 
             // Parse error = not Python (unless it's a multiline continuation)
@@ -843,7 +1207,18 @@ impl<'a> Tokenizer<'a> {
                     }
                     "expression_statement" => {
                         // Check if it's a meaningful expression (call, await, etc.)
-                        if let Some(expr) = child.child(0) {
+                        if let Some(mut expr) = child.child(0) {
+                            // `(x := a if b else c)`: tree-sitter binds `:=`
+                            // tighter than Python does, so the walrus may be
+                            // the first operand of the parenthesized expression
+                            if expr.kind() == "parenthesized_expression"
+                                && let Some(inner) = expr.named_child(0)
+                            {
+                                expr = match inner.named_child(0) {
+                                    Some(first) if first.kind() == "named_expression" => first,
+                                    _ => inner,
+                                };
+                            }
                             return matches!(
                                 expr.kind(),
                                 "call"
@@ -945,25 +1320,24 @@ impl<'a> Tokenizer<'a> {
     }
 
     fn tokenize_decorator(&mut self, tokens: &mut Vec<Token>) {
-        let start = self.position;
-        let code = self.consume_to_eol();
-        tokens.push(Token::Decorator {
-            code,
-            range: TextRange {
-                start,
-                end: self.position,
-            },
-        });
+        // Arguments may wrap: `@route(` continues until its brackets close
+        let (code, range) = self.consume_bracketed_statement();
+        tokens.push(Token::Decorator { code, range });
     }
 
     fn tokenize_control_start(&mut self, tokens: &mut Vec<Token>, _line: &str) {
         let start = self.position;
-        let code = self.consume_to_eol();
+        // A header such as `with (` continues until its brackets close
+        let (code, _) = self.consume_bracketed_statement();
         let trimmed = code.trim();
         let leading_ws = code.len() - code.trim_start().len();
 
-        // Strip trailing comment before parsing the control flow statement
-        let effective = self.strip_trailing_comment(trimmed);
+        // Strip trailing comment before parsing the control flow statement.
+        // Only the last line can have one; earlier `#`s sit inside brackets.
+        let last_line_start = trimmed.rfind('\n').map_or(0, |i| i + 1);
+        let (last_line, comment) = self.split_trailing_comment(&trimmed[last_line_start..]);
+        let effective = &trimmed[..last_line_start + last_line.len()];
+        let comment = comment.map(str::to_string);
 
         // Handle compound keywords (async for, async with, async def)
         let (keyword, rest, rest_offset_in_effective) =
@@ -1001,17 +1375,28 @@ impl<'a> Tokenizer<'a> {
         // Calculate rest_range in source coordinates
         let rest_start_byte = start.byte + leading_ws + rest_offset_in_effective;
         let rest_end_byte = rest_start_byte + rest.len();
+        let rest_end = if rest.contains('\n') {
+            // Count back from the end of the header, past any trailing comment
+            let trailing = &code[leading_ws + effective.len()..];
+            Position {
+                line: self.position.line,
+                col: self.position.col - trailing.chars().count(),
+                byte: self.position.byte - trailing.len(),
+            }
+        } else {
+            Position {
+                line: start.line,
+                col: start.col + leading_ws + rest_offset_in_effective + rest.len(),
+                byte: rest_end_byte,
+            }
+        };
         let rest_range = TextRange {
             start: Position {
                 line: start.line,
                 col: start.col + leading_ws + rest_offset_in_effective,
                 byte: rest_start_byte,
             },
-            end: Position {
-                line: start.line,
-                col: start.col + leading_ws + rest_offset_in_effective + rest.len(),
-                byte: rest_end_byte,
-            },
+            end: rest_end,
         };
 
         tokens.push(Token::ControlStart {
@@ -1022,18 +1407,20 @@ impl<'a> Tokenizer<'a> {
                 end: self.position,
             },
             rest_range,
+            comment,
         });
     }
 
     fn tokenize_control_continuation(&mut self, tokens: &mut Vec<Token>, _line: &str) {
         let start = self.position;
         let code = self.consume_to_eol();
-        let trimmed = code.trim();
         let leading_ws = code.len() - code.trim_start().len();
+        let (trimmed, comment) = self.split_trailing_comment(code.trim());
+        let comment = comment.map(str::to_string);
 
-        // Extract keyword and optional rest
+        // Extract keyword and optional rest; `except(A, B):` needs no space
         let (keyword, rest, rest_range) =
-            if let Some(idx) = trimmed.find(|c: char| c.is_whitespace() || c == ':') {
+            if let Some(idx) = trimmed.find(|c: char| c.is_whitespace() || c == ':' || c == '(') {
                 let kw = &trimmed[..idx];
                 let after_kw = &trimmed[idx..];
                 let r = after_kw.trim_start();
@@ -1069,6 +1456,7 @@ impl<'a> Tokenizer<'a> {
                 end: self.position,
             },
             rest_range,
+            comment,
         });
     }
 
@@ -1126,7 +1514,7 @@ impl<'a> Tokenizer<'a> {
         let mut code = self.consume_to_eol();
         let mut depth = self.calculate_bracket_depth(&code);
 
-        while depth > 0 && !self.at_eof() {
+        while (depth > 0 || ends_with_continuation(&code)) && !self.at_eof() {
             if self.at_newline() {
                 code.push('\n');
                 self.consume_newline();
@@ -1156,63 +1544,7 @@ impl<'a> Tokenizer<'a> {
 
     /// Calculate net bracket depth, accounting for strings and comments
     fn calculate_bracket_depth(&self, code: &str) -> i32 {
-        let mut depth = 0i32;
-        let mut in_string = false;
-        let mut string_char = ' ';
-        let mut in_triple_string = false;
-        let mut chars = code.chars().peekable();
-
-        while let Some(ch) = chars.next() {
-            if in_string {
-                if ch == '\\' && !in_triple_string {
-                    // Skip escaped character in regular strings
-                    chars.next();
-                    continue;
-                }
-                if in_triple_string {
-                    // Check for triple quote end
-                    if ch == string_char && chars.peek() == Some(&string_char) {
-                        chars.next();
-                        if chars.peek() == Some(&string_char) {
-                            chars.next();
-                            in_string = false;
-                            in_triple_string = false;
-                        }
-                    }
-                } else if ch == string_char {
-                    in_string = false;
-                }
-            } else {
-                match ch {
-                    '"' | '\'' => {
-                        // Check for triple quote
-                        if chars.peek() == Some(&ch) {
-                            chars.next();
-                            if chars.peek() == Some(&ch) {
-                                chars.next();
-                                in_string = true;
-                                string_char = ch;
-                                in_triple_string = true;
-                            }
-                            // else: empty string "" or '', not entering string mode
-                        } else {
-                            in_string = true;
-                            string_char = ch;
-                            in_triple_string = false;
-                        }
-                    }
-                    '#' => {
-                        // Rest of line is comment, skip to newline
-                        while chars.next().is_some_and(|c| c != '\n') {}
-                    }
-                    '(' | '[' | '{' => depth += 1,
-                    ')' | ']' | '}' => depth = (depth - 1).max(0),
-                    _ => {}
-                }
-            }
-        }
-
-        depth
+        scan_code(code, |_, _, _| true)
     }
 
     fn tokenize_content(&mut self, tokens: &mut Vec<Token>) -> ParseResult<()> {
@@ -1220,6 +1552,7 @@ impl<'a> Tokenizer<'a> {
         // - Text segments
         // - {expr} expressions
         // - {{/}} escaped braces
+        // - {# ... #} block comments (dropped)
         // - Trailing # comments
 
         let mut quote_ctx = QuoteCtx::None;
@@ -1354,6 +1687,51 @@ impl<'a> Tokenizer<'a> {
                     after_structural = true;
                 }
 
+                // Escaped angle bracket: `\<` is a literal `<`, never a tag
+                (QuoteCtx::None, '\\') if self.peek_next_char() == Some('<') => {
+                    // Flush text
+                    if !text_buf.is_empty() {
+                        tokens.push(Token::Text {
+                            text: text_buf.clone(),
+                            range: TextRange {
+                                start: text_start,
+                                end: self.position,
+                            },
+                        });
+                        text_buf.clear();
+                    }
+                    let escape_start = self.position;
+                    self.advance();
+                    self.advance();
+                    tokens.push(Token::EscapedBrace {
+                        brace: '<',
+                        range: TextRange {
+                            start: escape_start,
+                            end: self.position,
+                        },
+                    });
+                    text_start = self.position;
+                    after_structural = true;
+                }
+
+                // Block comment: `{# ... #}`, possibly spanning lines, produces
+                // no token and no output
+                (QuoteCtx::None, '{') if self.peek_next_char() == Some('#') => {
+                    // Flush text
+                    if !text_buf.is_empty() {
+                        tokens.push(Token::Text {
+                            text: text_buf.clone(),
+                            range: TextRange {
+                                start: text_start,
+                                end: self.position,
+                            },
+                        });
+                        text_buf.clear();
+                    }
+                    self.skip_block_comment()?;
+                    text_start = self.position;
+                }
+
                 // Expression
                 (QuoteCtx::None, '{') => {
                     // Flush text
@@ -1373,6 +1751,24 @@ impl<'a> Tokenizer<'a> {
                     after_structural = true;
                 }
 
+                // Doctype declaration: <!DOCTYPE ...>, passed through verbatim
+                (QuoteCtx::None, '<') if self.is_doctype_start() => {
+                    // Flush text
+                    if !text_buf.is_empty() {
+                        tokens.push(Token::Text {
+                            text: text_buf.clone(),
+                            range: TextRange {
+                                start: text_start,
+                                end: self.position,
+                            },
+                        });
+                        text_buf.clear();
+                    }
+                    self.tokenize_doctype(tokens);
+                    text_start = self.position;
+                    after_structural = true;
+                }
+
                 // HTML element opening tag: <tagname
                 (QuoteCtx::None, '<') if self.is_html_element_start() => {
                     // Flush text
@@ -1387,18 +1783,20 @@ impl<'a> Tokenizer<'a> {
                         text_buf.clear();
                     }
                     self.tokenize_html_element_open(tokens)?;
-                    // If the element entered raw content mode, stop inline processing.
-                    // The rest of this line (and subsequent lines) will be handled
-                    // by the raw content handler in tokenize_line().
-                    if self.in_raw_content.is_some() {
-                        return Ok(());
+                    // If the element entered raw content mode, the rest of this
+                    // line is raw text up to the closing tag. Subsequent lines are
+                    // handled by the raw content handler in tokenize_line().
+                    if let Some(RawContentExit::ClosingTag { tag, interpolate }) =
+                        self.in_raw_content.clone()
+                    {
+                        return self.tokenize_raw_text(tokens, &tag, interpolate);
                     }
                     text_start = self.position;
                     after_structural = true;
                 }
 
-                // Component closing tag: </{Name}>
-                (QuoteCtx::None, '<') if self.is_component_close() => {
+                // Component opening tag mid-line: <div><{Card}>...
+                (QuoteCtx::None, '<') if self.is_component_open() => {
                     // Flush text
                     if !text_buf.is_empty() {
                         tokens.push(Token::Text {
@@ -1410,13 +1808,13 @@ impl<'a> Tokenizer<'a> {
                         });
                         text_buf.clear();
                     }
-                    self.tokenize_component_close(tokens);
+                    self.tokenize_component_open(tokens)?;
                     text_start = self.position;
                     after_structural = true;
                 }
 
-                // HTML element closing tag: </tagname
-                (QuoteCtx::None, '<') if self.is_html_element_close() => {
+                // Component closing tag: </{Name}>
+                (QuoteCtx::None, '<') if self.is_component_close() => {
                     // Flush text
                     if !text_buf.is_empty() {
                         tokens.push(Token::Text {
@@ -1428,12 +1826,54 @@ impl<'a> Tokenizer<'a> {
                         });
                         text_buf.clear();
                     }
-                    self.tokenize_html_element_close(tokens);
+                    self.tokenize_component_close(tokens);
                     text_start = self.position;
                     after_structural = true;
                 }
 
-                // Regular character
+                // Anonymous fragment tags: <> and </>. A `<>` after text on
+                // the same line, as in `a <> b`, stays text.
+                (QuoteCtx::None, '<')
+                    if match self.fragment_tag_len() {
+                        Some(2) => after_structural && text_buf.trim().is_empty(),
+                        len => len.is_some(),
+                    } =>
+                {
+                    // Flush text
+                    if !text_buf.is_empty() {
+                        tokens.push(Token::Text {
+                            text: text_buf.clone(),
+                            range: TextRange {
+                                start: text_start,
+                                end: self.position,
+                            },
+                        });
+                        text_buf.clear();
+                    }
+                    self.tokenize_fragment_tag(tokens);
+                    text_start = self.position;
+                    after_structural = true;
+                }
+
+                // HTML element closing tag: </tagname
+                (QuoteCtx::None, '<') if self.is_html_element_close() => {
+                    // Flush text
+                    if !text_buf.is_empty() {
+                        tokens.push(Token::Text {
+                            text: text_buf.clone(),
+                            range: TextRange {
+                                start: text_start,
+                                end: self.position,
+                            },
+                        });
+                        text_buf.clear();
+                    }
+                    self.tokenize_html_element_close(tokens);
+                    text_start = self.position;
+                    after_structural = true;
+                }
+
+                // Regular character
                 _ => {
                     if !ch.is_whitespace() {
                         after_structural = false;
@@ -1461,18 +1901,20 @@ impl<'a> Tokenizer<'a> {
         let start = self.position;
         self.advance(); // consume {
 
-        let _expr_start = self.position;
         let mut depth = 1;
         let mut expr = String::new();
 
-        // Track string context to avoid counting braces inside strings
-        let mut in_string = false;
-        let mut string_char = ' ';
+        // Strings opened inside the expression, innermost last. An f-string's
+        // replacement field is code again, so its quotes open new strings
+        // (`{f"{d["k"]}"}`) and its braces don't close the expression.
+        let mut strings: Vec<OpenString> = Vec::new();
 
         while !self.at_eof() && depth > 0 {
             let Some(ch) = self.peek_char() else { break };
 
-            if in_string {
+            if let Some(string) = strings.last_mut()
+                && string.field_depth == 0
+            {
                 if ch == '\\' {
                     expr.push(ch);
                     self.advance();
@@ -1482,39 +1924,117 @@ impl<'a> Tokenizer<'a> {
                     }
                     continue;
                 }
-                if ch == string_char {
-                    in_string = false;
-                }
-                expr.push(ch);
-                self.advance();
-            } else {
-                match ch {
-                    '"' | '\'' => {
-                        in_string = true;
-                        string_char = ch;
+                let rest = &self.source[self.position.byte..];
+                let closing = if string.triple {
+                    rest.starts_with(&string.quote.to_string().repeat(3))
+                } else {
+                    ch == string.quote
+                };
+                if closing {
+                    let len = if string.triple { 3 } else { 1 };
+                    for _ in 0..len {
                         expr.push(ch);
                         self.advance();
                     }
-                    '{' => {
-                        depth += 1;
+                    strings.pop();
+                    continue;
+                }
+                if string.fstring && ch == '{' {
+                    if self.peek_next_char() == Some('{') {
                         expr.push(ch);
                         self.advance();
+                    } else {
+                        string.field_depth = 1;
                     }
-                    '}' => {
-                        depth -= 1;
-                        if depth > 0 {
-                            expr.push(ch);
-                        }
+                }
+                expr.push(ch);
+                self.advance();
+                continue;
+            }
+
+            // Code: the expression itself, or a replacement field of the
+            // innermost f-string
+            let depth = match strings.last_mut() {
+                Some(string) => &mut string.field_depth,
+                None => &mut depth,
+            };
+            match ch {
+                '"' | '\'' => {
+                    let rest = &self.source[self.position.byte..];
+                    let triple = rest.starts_with(&ch.to_string().repeat(3));
+                    strings.push(OpenString {
+                        quote: ch,
+                        triple,
+                        fstring: has_fstring_prefix(&expr),
+                        field_depth: 0,
+                    });
+                    let len = if triple { 3 } else { 1 };
+                    for _ in 0..len {
+                        expr.push(ch);
                         self.advance();
                     }
-                    _ => {
+                }
+                '{' => {
+                    *depth += 1;
+                    expr.push(ch);
+                    self.advance();
+                }
+                '}' => {
+                    *depth -= 1;
+                    if *depth > 0 || !strings.is_empty() {
                         expr.push(ch);
-                        self.advance();
                     }
+                    self.advance();
+                }
+                // CRLF inside a multi-line expression reads as `\n`
+                '\r' if self.peek_next_char() == Some('\n') => self.advance(),
+                _ => {
+                    expr.push(ch);
+                    self.advance();
                 }
             }
         }
 
+        // Raw marker: `{!html}` outputs trusted HTML unescaped. No Python
+        // expression starts with `!`, so this can't shadow real code.
+        let raw = expr.starts_with('!');
+        // Escape marker: `{&value}` escapes even in a template that doesn't
+        // by default. No Python expression starts with `&` either.
+        let force_escape = expr.starts_with('&');
+        if raw || force_escape {
+            expr.remove(0);
+        }
+
+        // Whitespace-control markers: a `-` against the brace, set off from the
+        // code by whitespace (`{-x}` stays unary minus). The whitespace that
+        // sets them off isn't part of the code either.
+        let trim_left = expr.starts_with('-') && expr[1..].starts_with(|c: char| c.is_whitespace());
+        if trim_left {
+            expr = expr[1..].trim_start().to_string();
+        }
+        let trim_right = expr.len() > 1
+            && expr.ends_with('-')
+            && expr[..expr.len() - 1].ends_with(|c: char| c.is_whitespace());
+        if trim_right {
+            expr = expr[..expr.len() - 1].trim_end().to_string();
+        }
+
+        // Inline binding: `{ total = price * qty }` assigns instead of printing
+        if !raw && !force_escape && is_inline_assignment(&expr) {
+            let inner = &self.source[start.byte + 1..self.position.byte - 1];
+            let code = inner.trim();
+            let leading = &inner[..inner.len() - inner.trim_start().len()];
+            let code_start = start.advanced_over("{").advanced_over(leading);
+            tokens.push(Token::PythonStatement {
+                code: code.to_string(),
+                range: TextRange {
+                    start: code_start,
+                    end: code_start.advanced_over(code),
+                },
+            });
+            return;
+        }
+
         // Convert children placeholder {...} to {children} or {...name} to {children_name}
         let trimmed = expr.trim();
         let final_expr = if let Some(after) = trimmed.strip_prefix("...") {
@@ -1534,13 +2054,47 @@ impl<'a> Tokenizer<'a> {
                 start,
                 end: self.position,
             },
+            raw,
+            force_escape,
+            trim_left,
+            trim_right,
         });
     }
 
+    /// Skip a `{# ... #}` comment, which may run across lines.
+    fn skip_block_comment(&mut self) -> ParseResult<()> {
+        let start = self.position;
+        self.advance(); // consume {
+        self.advance(); // consume #
+        let opener_end = self.position;
+
+        while !self.at_eof() {
+            if self.peek_char() == Some('#') && self.peek_next_char() == Some('}') {
+                self.advance();
+                self.advance();
+                return Ok(());
+            }
+            self.advance();
+        }
+
+        Err(ParseError::new(
+            ErrorKind::InvalidSyntax,
+            "Comment `{#` is never closed.",
+            TextRange {
+                start,
+                end: opener_end,
+            },
+        )
+        .with_help("Close the comment with `#}`.")
+        .boxed())
+    }
+
     /// Parse a single attribute (shared between components and HTML elements).
     /// Returns None if no attribute could be parsed.
-    fn parse_attribute(&mut self) -> Option<Attribute> {
-        let ch = self.peek_char()?;
+    fn parse_attribute(&mut self) -> ParseResult<Option<Attribute>> {
+        let Some(ch) = self.peek_char() else {
+            return Ok(None);
+        };
 
         if ch == '{' {
             // Shorthand {name}, spread {**expr}, or slot assignment {...name}
@@ -1553,8 +2107,8 @@ impl<'a> Tokenizer<'a> {
                 self.advance(); // *
                 let expr = self.consume_expression();
                 let attr_end = self.position;
-                self.advance(); // }
-                return Some(Attribute {
+                self.expect_attribute_brace(&format!("{{**{}", leading_name(&expr)), attr_start)?;
+                return Ok(Some(Attribute {
                     name: "**".to_string(),
                     value: AttributeValue::Spread(
                         expr,
@@ -1567,7 +2121,8 @@ impl<'a> Tokenizer<'a> {
                         start: attr_start,
                         end: self.position,
                     },
-                });
+                    optional: false,
+                }));
             } else if self.peek_char() == Some('.') {
                 // Slot assignment {...name}
                 self.advance(); // .
@@ -1576,7 +2131,7 @@ impl<'a> Tokenizer<'a> {
                 let slot_name = self.consume_until_char('}');
                 let attr_end = self.position;
                 self.advance(); // }
-                return Some(Attribute {
+                return Ok(Some(Attribute {
                     name: format!("...{}", slot_name),
                     value: AttributeValue::SlotAssignment(
                         slot_name.trim().to_string(),
@@ -1589,13 +2144,14 @@ impl<'a> Tokenizer<'a> {
                         start: attr_start,
                         end: self.position,
                     },
-                });
+                    optional: false,
+                }));
             } else {
                 // Shorthand {name}
                 let expr = self.consume_expression();
                 let attr_end = self.position;
-                self.advance(); // }
-                return Some(Attribute {
+                self.expect_attribute_brace(&format!("{{{}", leading_name(&expr)), attr_start)?;
+                return Ok(Some(Attribute {
                     name: expr.clone(),
                     value: AttributeValue::Shorthand(
                         expr,
@@ -1608,7 +2164,8 @@ impl<'a> Tokenizer<'a> {
                         start: attr_start,
                         end: self.position,
                     },
-                });
+                    optional: false,
+                }));
             }
         } else if ch.is_alphabetic() || ch == '_' || ch == '-' || ch == '@' || ch == ':' {
             // Named attribute
@@ -1617,21 +2174,44 @@ impl<'a> Tokenizer<'a> {
                 c.is_alphanumeric() || c == '_' || c == '-' || c == '@' || c == ':'
             });
 
+            // Conditional attribute: name?={expr}
+            let optional = self.peek_char() == Some('?') && self.peek_next_char() == Some('=');
+            if optional {
+                self.advance(); // ?
+            }
+
             if self.peek_char() == Some('=') {
                 self.advance(); // =
 
+                if optional && self.peek_char() != Some('{') {
+                    return Err(ParseError::new(
+                        ErrorKind::InvalidSyntax,
+                        format!("Attribute `{}?` needs an expression value.", attr_name),
+                        TextRange {
+                            start: attr_start,
+                            end: self.position,
+                        },
+                    )
+                    .with_help(format!(
+                        "Write `{}?={{value}}` to render it only when `value` is set.",
+                        attr_name
+                    ))
+                    .boxed());
+                }
+
                 let (value, value_end) = if self.peek_char() == Some('{') {
                     // Expression value: aria={expr}
-                    let val_start = self.position;
                     self.advance(); // {
+                    let expr_start = self.position;
                     let expr = self.consume_expression();
-                    self.advance(); // } - advance past closing brace
+                    let expr_end = self.position;
+                    self.expect_attribute_brace(&attr_name, attr_start)?;
                     (
                         AttributeValue::Expression(
                             expr,
                             TextRange {
-                                start: val_start,
-                                end: self.position,
+                                start: expr_start,
+                                end: expr_end,
                             },
                         ),
                         self.position,
@@ -1639,41 +2219,43 @@ impl<'a> Tokenizer<'a> {
                 } else if self.peek_char() == Some('"') {
                     // Double-quoted string
                     self.advance(); // "
-                    let val = self.consume_until_char('"');
+                    let val = self.consume_quoted_value('"');
                     self.advance(); // " - advance past closing quote
                     (AttributeValue::String(val), self.position)
                 } else if self.peek_char() == Some('\'') {
                     // Single-quoted string
                     self.advance(); // '
-                    let val = self.consume_until_char('\'');
+                    let val = self.consume_quoted_value('\'');
                     self.advance(); // ' - advance past closing quote
                     (AttributeValue::String(val), self.position)
                 } else {
                     (AttributeValue::Bool, self.position)
                 };
 
-                return Some(Attribute {
+                return Ok(Some(Attribute {
                     name: attr_name,
                     value,
                     range: TextRange {
                         start: attr_start,
                         end: value_end,
                     },
-                });
+                    optional,
+                }));
             } else {
                 // Boolean attribute
-                return Some(Attribute {
+                return Ok(Some(Attribute {
                     name: attr_name,
                     value: AttributeValue::Bool,
                     range: TextRange {
                         start: attr_start,
                         end: self.position,
                     },
-                });
+                    optional: false,
+                }));
             }
         }
 
-        None
+        Ok(None)
     }
 
     fn tokenize_component_open(&mut self, tokens: &mut Vec<Token>) -> ParseResult<()> {
@@ -1697,12 +2279,15 @@ impl<'a> Tokenizer<'a> {
 
         loop {
             // Formatting whitespace separates attributes; values retain their whitespace.
-            let crossed_line = self.skip_opening_tag_whitespace();
+            let attrs_end = self.position;
+            let newlines = self.skip_opening_tag_whitespace();
 
-            if self.at_eof() {
-                break;
+            // A blank line or another tag means the `>` was forgotten; stop
+            // here instead of swallowing the rest of the file.
+            if self.at_eof() || newlines > 1 || self.peek_char() == Some('<') {
+                return Err(Self::unclosed_opening_tag_error(start, attrs_end));
             }
-            if crossed_line
+            if newlines > 0
                 && let Some(error) = self.opening_tag_statement_error(&format!("<{{{name}}}"))
             {
                 return Err(error);
@@ -1746,7 +2331,7 @@ impl<'a> Tokenizer<'a> {
             }
 
             // Parse attribute using shared function
-            if let Some(attr) = self.parse_attribute() {
+            if let Some(attr) = self.parse_attribute()? {
                 attrs.push(attr);
             } else {
                 // Unknown character, skip
@@ -1754,16 +2339,7 @@ impl<'a> Tokenizer<'a> {
             }
         }
 
-        Err(ParseError::new(
-            ErrorKind::InvalidSyntax,
-            "Unclosed opening tag.",
-            TextRange {
-                start,
-                end: self.position,
-            },
-        )
-        .with_help("Close the opening tag with '>'.")
-        .boxed())
+        Err(Self::unclosed_opening_tag_error(start, self.position))
     }
 
     fn tokenize_component_close(&mut self, tokens: &mut Vec<Token>) {
@@ -1865,6 +2441,34 @@ impl<'a> Tokenizer<'a> {
         });
     }
 
+    /// Length of the `<>` or `</>` at the current position. Only those exact
+    /// tags count, so `a < b` and `<=` stay text.
+    fn fragment_tag_len(&self) -> Option<usize> {
+        let rest = &self.source[self.position.byte..];
+        ["<>", "</>"]
+            .into_iter()
+            .find(|tag| rest.starts_with(tag))
+            .map(str::len)
+    }
+
+    /// Tokenize a fragment tag: <> or </>
+    fn tokenize_fragment_tag(&mut self, tokens: &mut Vec<Token>) {
+        let start = self.position;
+        let len = self.fragment_tag_len().unwrap_or(0);
+        for _ in 0..len {
+            self.advance();
+        }
+        let range = TextRange {
+            start,
+            end: self.position,
+        };
+        tokens.push(if len == 2 {
+            Token::FragmentOpen { range }
+        } else {
+            Token::FragmentClose { range }
+        });
+    }
+
     /// Check if current position starts an HTML element (not component)
     fn is_html_element_start(&self) -> bool {
         if self.peek_char() != Some('<') {
@@ -1881,6 +2485,42 @@ impl<'a> Tokenizer<'a> {
         next_ch.is_ascii_alphabetic()
     }
 
+    /// Check if current position starts a doctype declaration: <!DOCTYPE
+    fn is_doctype_start(&self) -> bool {
+        self.source[self.position.byte..]
+            .get(..9)
+            .is_some_and(|s| s.eq_ignore_ascii_case("<!doctype"))
+    }
+
+    /// Consume a doctype declaration up to its closing `>` as a single text token.
+    /// Quoted public/system identifiers and `[...]` internal subsets may contain
+    /// `>`, so the scan tracks both.
+    fn tokenize_doctype(&mut self, tokens: &mut Vec<Token>) {
+        let start = self.position;
+        let mut quote: Option<char> = None;
+        let mut subset_depth = 0;
+        while !self.at_eof() && !self.at_newline() {
+            let Some(ch) = self.peek_char() else { break };
+            self.advance();
+            match (quote, ch) {
+                (Some(q), _) if ch == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(ch),
+                (None, '[') => subset_depth += 1,
+                (None, ']') => subset_depth -= 1,
+                (None, '>') if subset_depth <= 0 => break,
+                _ => {}
+            }
+        }
+        tokens.push(Token::Text {
+            text: self.captured_text(start.byte, self.position.byte),
+            range: TextRange {
+                start,
+                end: self.position,
+            },
+        });
+    }
+
     /// Check if current position starts an HTML closing tag
     fn is_html_element_close(&self) -> bool {
         if self.peek_char() != Some('<') {
@@ -1895,6 +2535,12 @@ impl<'a> Tokenizer<'a> {
         self.bytes[byte1] == b'/' && (self.bytes[byte2] as char).is_ascii_alphabetic()
     }
 
+    /// `<{Name}`, but not a slot's `<{...}`
+    fn is_component_open(&self) -> bool {
+        let rest = &self.source[self.position.byte..];
+        rest.starts_with("<{") && !rest.starts_with("<{...")
+    }
+
     /// Check if current position starts a component closing tag: </{Name}>
     fn is_component_close(&self) -> bool {
         if self.peek_char() != Some('<') {
@@ -1923,12 +2569,15 @@ impl<'a> Tokenizer<'a> {
 
         loop {
             // Formatting whitespace separates attributes; values retain their whitespace.
-            let crossed_line = self.skip_opening_tag_whitespace();
+            let attrs_end = self.position;
+            let newlines = self.skip_opening_tag_whitespace();
 
-            if self.at_eof() {
-                break;
+            // A blank line or another tag means the `>` was forgotten; stop
+            // here instead of swallowing the rest of the file.
+            if self.at_eof() || newlines > 1 || self.peek_char() == Some('<') {
+                return Err(Self::unclosed_opening_tag_error(start, attrs_end));
             }
-            if crossed_line
+            if newlines > 0
                 && let Some(error) = self.opening_tag_statement_error(&format!("<{tag}"))
             {
                 return Err(error);
@@ -1961,6 +2610,9 @@ impl<'a> Tokenizer<'a> {
                 let close_pos = self.position; // Position of ">"
                 self.advance();
                 let is_raw = Self::is_raw_text_element(&tag);
+                // `hyper-interpolate` is a compiler directive, not an HTML attribute
+                let interpolate = attrs.iter().any(|a| a.name == INTERPOLATE_ATTRIBUTE);
+                attrs.retain(|a| a.name != INTERPOLATE_ATTRIBUTE);
                 tokens.push(Token::HtmlElementOpen {
                     tag: tag.clone(),
                     tag_range: TextRange {
@@ -1976,13 +2628,15 @@ impl<'a> Tokenizer<'a> {
                     },
                 });
                 if is_raw {
-                    self.in_raw_content = Some(RawContentExit::ClosingTag(tag));
+                    self.in_raw_content = Some(RawContentExit::ClosingTag { tag, interpolate });
+                } else if tag.eq_ignore_ascii_case("pre") {
+                    self.pre_depth += 1;
                 }
                 return Ok(());
             }
 
             // Parse attribute using shared function
-            if let Some(attr) = self.parse_attribute() {
+            if let Some(attr) = self.parse_attribute()? {
                 attrs.push(attr);
             } else {
                 // Unknown character, skip
@@ -1990,16 +2644,71 @@ impl<'a> Tokenizer<'a> {
             }
         }
 
-        Err(ParseError::new(
-            ErrorKind::InvalidSyntax,
-            "Unclosed opening tag.",
-            TextRange {
-                start,
-                end: self.position,
-            },
-        )
-        .with_help("Close the opening tag with '>'.")
-        .boxed())
+        Err(Self::unclosed_opening_tag_error(start, self.position))
+    }
+
+    /// Consume raw text (the body of `<style>`/`<script>`) until the matching
+    /// closing tag or end of line. Finding the closing tag leaves raw mode and
+    /// tokenizes the rest of the line as regular content.
+    fn tokenize_raw_text(
+        &mut self,
+        tokens: &mut Vec<Token>,
+        tag: &str,
+        interpolate: bool,
+    ) -> ParseResult<()> {
+        let closing = format!("</{tag}");
+        let mut text_start = self.position;
+
+        while !self.at_eof() && !self.at_newline() {
+            let rest = &self.source[self.position.byte..];
+            let at_closing = rest.starts_with(&closing)
+                && !rest[closing.len()..].starts_with(|c: char| c.is_ascii_alphanumeric());
+            if at_closing {
+                self.flush_raw_text(tokens, text_start);
+                self.in_raw_content = None;
+                return self.tokenize_content(tokens);
+            }
+
+            let Some(ch) = self.peek_char() else { break };
+            let next = self.peek_next_char();
+            if interpolate && ((ch == '{' && next == Some('{')) || (ch == '}' && next == Some('}')))
+            {
+                self.flush_raw_text(tokens, text_start);
+                let brace_start = self.position;
+                self.advance();
+                self.advance();
+                tokens.push(Token::EscapedBrace {
+                    brace: ch,
+                    range: TextRange {
+                        start: brace_start,
+                        end: self.position,
+                    },
+                });
+                text_start = self.position;
+            } else if interpolate && ch == '{' {
+                self.flush_raw_text(tokens, text_start);
+                self.tokenize_expression(tokens);
+                text_start = self.position;
+            } else {
+                self.advance();
+            }
+        }
+
+        self.flush_raw_text(tokens, text_start);
+        Ok(())
+    }
+
+    /// Emit source text from `start` to the current position, if any.
+    fn flush_raw_text(&mut self, tokens: &mut Vec<Token>, start: Position) {
+        if start.byte < self.position.byte {
+            tokens.push(Token::Text {
+                text: self.source[start.byte..self.position.byte].to_string(),
+                range: TextRange {
+                    start,
+                    end: self.position,
+                },
+            });
+        }
     }
 
     /// Parse an HTML element closing tag: </tag>
@@ -2017,6 +2726,9 @@ impl<'a> Tokenizer<'a> {
         if self.peek_char() == Some('>') {
             self.advance();
         }
+        if tag.eq_ignore_ascii_case("pre") {
+            self.pre_depth = self.pre_depth.saturating_sub(1);
+        }
 
         tokens.push(Token::HtmlElementClose {
             tag,
@@ -2130,7 +2842,8 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
-    fn skip_opening_tag_whitespace(&mut self) -> bool {
+    /// Skip whitespace between attributes, returning how many line breaks it spanned
+    fn skip_opening_tag_whitespace(&mut self) -> usize {
         let start_line = self.position.line;
         while !self.at_eof() {
             match self.peek_char() {
@@ -2138,7 +2851,17 @@ impl<'a> Tokenizer<'a> {
                 _ => break,
             }
         }
-        self.position.line != start_line
+        self.position.line - start_line
+    }
+
+    fn unclosed_opening_tag_error(start: Position, end: Position) -> Box<ParseError> {
+        ParseError::new(
+            ErrorKind::InvalidSyntax,
+            "Unclosed opening tag.",
+            TextRange { start, end },
+        )
+        .with_help("Close the opening tag with '>'.")
+        .boxed()
     }
 
     fn opening_tag_statement_error(&mut self, opening: &str) -> Option<Box<ParseError>> {
@@ -2195,16 +2918,58 @@ impl<'a> Tokenizer<'a> {
             }
             self.advance();
         }
-        self.source[start..self.position.byte].to_string()
+        self.captured_text(start, self.position.byte)
+    }
+
+    /// Source text between two byte offsets, with CRLF line endings read as
+    /// `\n`, so multi-line token values match on CRLF and LF files. Ranges
+    /// keep pointing at the original bytes.
+    fn captured_text(&self, start: usize, end: usize) -> String {
+        self.source[start..end].replace("\r\n", "\n")
+    }
+
+    /// Consume a quoted attribute value up to its closing `quote`. Brackets in
+    /// the text are literal (`title=":)"`); only `{...}` interpolations nest,
+    /// and string literals inside them may hold the quote or a brace.
+    fn consume_quoted_value(&mut self, quote: char) -> String {
+        let start = self.position.byte;
+        let mut depth = 0;
+        let mut in_string = None;
+
+        while let Some(ch) = self.peek_char() {
+            if let Some(string_char) = in_string {
+                if ch == '\\' {
+                    self.advance();
+                } else if ch == string_char || ch == '\n' {
+                    in_string = None;
+                }
+                self.advance();
+                continue;
+            }
+
+            match ch {
+                _ if ch == quote && depth == 0 => break,
+                '{' => depth += 1,
+                '}' if depth > 0 => depth -= 1,
+                '"' | '\'' if depth > 0 => in_string = Some(ch),
+                _ => {}
+            }
+            self.advance();
+        }
+        self.captured_text(start, self.position.byte)
     }
 
     /// Consume a Python expression delimited by `}`. Tracks bracket depth
     /// across `()`, `[]`, `{}` and skips brackets inside string literals.
+    ///
+    /// The expression may span lines. When no `}` ever closes it, it ends at
+    /// its first unbracketed newline, where the missing brace gets reported.
     fn consume_expression(&mut self) -> String {
         let start = self.position.byte;
         let mut depth = 0;
         let mut in_string = false;
         let mut string_char = ' ';
+        let mut first_newline = None;
 
         while !self.at_eof() {
             let Some(ch) = self.peek_char() else { break };
@@ -2236,11 +3001,42 @@ impl<'a> Tokenizer<'a> {
                     }
                     depth -= 1;
                 }
+                '\n' if depth == 0 && first_newline.is_none() => {
+                    first_newline = Some(self.position);
+                }
                 _ => {}
             }
             self.advance();
         }
-        self.source[start..self.position.byte].to_string()
+        if let (None, Some(newline)) = (self.peek_char(), first_newline) {
+            self.position = newline;
+        }
+        self.captured_text(start, self.position.byte)
+    }
+
+    /// Consume the `}` closing an attribute expression, or report where it was
+    /// expected when the expression ran to end of line instead.
+    fn expect_attribute_brace(&mut self, name: &str, attr_start: Position) -> ParseResult<()> {
+        if self.peek_char() == Some('}') {
+            self.advance();
+            return Ok(());
+        }
+        let expected = TextRange {
+            start: self.position,
+            end: self.position,
+        };
+        Err(ParseError::new(
+            ErrorKind::InvalidSyntax,
+            format!("Attribute `{}` is missing its closing `}}`.", name),
+            TextRange {
+                start: attr_start,
+                end: self.position,
+            },
+        )
+        .with_related(expected)
+        .with_related_label("expected `}` here")
+        .with_help("Close the expression with `}`.")
+        .boxed())
     }
 
     fn consume_while<F: Fn(char) -> bool>(&mut self, pred: F) -> String {
@@ -2260,17 +3056,211 @@ impl<'a> Tokenizer<'a> {
     }
 }
 
-/// Tokenize source code
-pub fn tokenize(source: &str) -> ParseResult<Vec<Token>> {
-    Tokenizer::new(source).tokenize()
+/// A string literal open inside a content expression
+struct OpenString {
+    quote: char,
+    triple: bool,
+    fstring: bool,
+    /// Brace depth inside the current replacement field; 0 outside one
+    field_depth: usize,
 }
 
-// =============================================================================
-// Incremental Tokenizer
-// =============================================================================
+/// Whether the string literal about to start after `code` is an f-string:
+/// its prefix (`f`, `rf`, `Fr`, ...) contains an `f`.
+fn has_fstring_prefix(code: &str) -> bool {
+    let prefix_start = code.trim_end_matches(['f', 'F', 'r', 'R', 'b', 'B']).len();
+    let prefix = &code[prefix_start..];
+    let standalone = !code[..prefix_start].ends_with(|c: char| c.is_alphanumeric() || c == '_');
+    standalone && prefix.len() <= 2 && prefix.contains(['f', 'F'])
+}
 
-/// A text change for incremental parsing
-#[derive(Debug, Clone)]
+/// The identifier an attribute expression starts with, for naming it in errors.
+fn leading_name(expr: &str) -> &str {
+    let expr = expr.trim_start();
+    let end = expr
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+        .unwrap_or(expr.len());
+    &expr[..end]
+}
+
+/// Walk Python `code`, calling `visit(byte, ch, depth)` for each character
+/// outside strings and comments, where `depth` is the bracket depth before it.
+/// Stops early when `visit` returns false. Returns the final bracket depth.
+fn scan_code(code: &str, mut visit: impl FnMut(usize, char, i32) -> bool) -> i32 {
+    let mut depth = 0i32;
+    let mut in_string = false;
+    let mut string_char = ' ';
+    let mut in_triple_string = false;
+    let mut chars = code.char_indices().peekable();
+
+    while let Some((i, ch)) = chars.next() {
+        if in_string {
+            if ch == '\\' && !in_triple_string {
+                // Skip escaped character in regular strings
+                chars.next();
+                continue;
+            }
+            if in_triple_string {
+                // Check for triple quote end
+                if ch == string_char && chars.peek().map(|&(_, c)| c) == Some(string_char) {
+                    chars.next();
+                    if chars.peek().map(|&(_, c)| c) == Some(string_char) {
+                        chars.next();
+                        in_string = false;
+                        in_triple_string = false;
+                    }
+                }
+            } else if ch == string_char {
+                in_string = false;
+            }
+            continue;
+        }
+
+        if !visit(i, ch, depth) {
+            break;
+        }
+        match ch {
+            '"' | '\'' => {
+                // Check for triple quote
+                if chars.peek().map(|&(_, c)| c) == Some(ch) {
+                    chars.next();
+                    if chars.peek().map(|&(_, c)| c) == Some(ch) {
+                        chars.next();
+                        in_string = true;
+                        string_char = ch;
+                        in_triple_string = true;
+                    }
+                    // else: empty string "" or '', not entering string mode
+                } else {
+                    in_string = true;
+                    string_char = ch;
+                    in_triple_string = false;
+                }
+            }
+            '#' => {
+                // Rest of line is comment, skip to newline
+                while chars.next().is_some_and(|(_, c)| c != '\n') {}
+            }
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = (depth - 1).max(0),
+            _ => {}
+        }
+    }
+
+    depth
+}
+
+/// Whether Python `code` ends in a `\` that continues it on the next line,
+/// rather than one inside a string or comment
+fn ends_with_continuation(code: &str) -> bool {
+    let Some(last) = code.len().checked_sub(1) else {
+        return false;
+    };
+    let mut continued = false;
+    scan_code(code, |i, ch, _| {
+        continued = i == last && ch == '\\';
+        true
+    });
+    continued
+}
+
+/// Byte offset of the first `=` that assigns, outside brackets and strings,
+/// so `x: Annotated[int, Field(gt=0)] = 5` splits before the `5`. Comparison
+/// operators (`==`, `!=`, `<=`, `>=`) and walrus `:=` don't count.
+pub(crate) fn find_top_level_assignment(code: &str) -> Option<usize> {
+    let bytes = code.as_bytes();
+    let mut found = None;
+    scan_code(code, |i, ch, depth| {
+        let is_assignment = ch == '='
+            && depth == 0
+            && !matches!(
+                i.checked_sub(1).map(|p| bytes[p]),
+                Some(b'=' | b'!' | b'<' | b'>' | b':')
+            )
+            && bytes.get(i + 1) != Some(&b'=');
+        if is_assignment {
+            found = Some(i);
+        }
+        !is_assignment
+    });
+    found
+}
+
+/// Byte offset of the `in` keyword that splits a loop header's `binding in
+/// iterable`, outside brackets and strings. Any whitespace may surround it,
+/// or none before a bracket: `for x in[1, 2]:`.
+pub(crate) fn find_for_in(rest: &str) -> Option<usize> {
+    let bytes = rest.as_bytes();
+    let is_ident = |b: Option<&u8>| b.is_some_and(|b| b.is_ascii_alphanumeric() || *b == b'_');
+    let mut found = None;
+    scan_code(rest, |i, ch, depth| {
+        let is_in = ch == 'i'
+            && depth == 0
+            && i > 0
+            && bytes.get(i + 1) == Some(&b'n')
+            && !is_ident(bytes.get(i - 1))
+            && !is_ident(bytes.get(i + 2));
+        if is_in {
+            found = Some(i);
+        }
+        !is_in
+    });
+    found
+}
+
+/// Whether a content `{code}` binds a name, like `{ total = price * qty }`,
+/// rather than printing a value. The `=` must assign rather than compare,
+/// have a value after it (`{x=}` is f-string debug output) and follow a
+/// target without a top-level `:`, so `{a: b}` and `{x:0=8}` still print.
+fn is_inline_assignment(code: &str) -> bool {
+    let Some(eq) = find_top_level_assignment(code) else {
+        return false;
+    };
+    let mut target_has_colon = false;
+    scan_code(&code[..eq], |_, ch, depth| {
+        target_has_colon |= ch == ':' && depth == 0;
+        true
+    });
+    !target_has_colon && !code[eq + 1..].trim().is_empty()
+}
+
+/// The rest of a `for` or `async for` line after its keyword
+fn for_header(line: &str) -> Option<&str> {
+    line.strip_prefix("for ")
+        .or_else(|| line.strip_prefix("async for "))
+}
+
+/// Tokenize source code
+pub fn tokenize(source: &str) -> ParseResult<Vec<Token>> {
+    Tokenizer::new(source).tokenize()
+}
+
+/// A front-matter block: the file's first line is `---` and a later line is
+/// too. Returns the text between the fences and the byte where the closing
+/// fence ends. A lone leading `---` is the header/body separator instead.
+fn front_matter(source: &str) -> Option<(&str, usize)> {
+    let mut lines = source.split_inclusive('\n');
+    let opening = lines.next()?;
+    if opening.trim_end() != "---" {
+        return None;
+    }
+    let mut offset = opening.len();
+    for line in lines {
+        if line.trim_end() == "---" {
+            let raw = source[opening.len()..offset].trim_end_matches(['\r', '\n']);
+            return Some((raw, offset + line.trim_end_matches(['\r', '\n']).len()));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+// =============================================================================
+// Incremental Tokenizer
+// =============================================================================
+
+/// A text change for incremental parsing
+#[derive(Debug, Clone)]
 pub struct TextChange {
     /// Start line (0-indexed)
     pub start_line: usize,
@@ -2294,21 +3284,32 @@ pub struct IncrementalTokenizer {
     line_to_tokens: Vec<(usize, usize)>,
     /// Number of lines in source
     line_count: usize,
+    /// Per line: whether tokenizing can restart there. True when a line began a
+    /// fresh `tokenize_line` step outside raw content.
+    resumable: Vec<bool>,
+    /// Per line: the delimiter of the multi-line string the line began inside, if
+    /// any, to seed a tokenizer restarting there
+    multiline_strings: Vec<Option<&'static str>>,
 }
 
+/// How many lines past an edit re-tokenizing may run before it has to line up
+/// with the old tokens again. Beyond that the edit changed state that carries
+/// across lines (e.g. opened a `"""` string), so the whole file is re-tokenized.
+const RESYNC_LOOKAHEAD_LINES: usize = 16;
+
 impl IncrementalTokenizer {
     /// Create a new incremental tokenizer from source
     pub fn new(source: &str) -> ParseResult<Self> {
-        let tokens = tokenize(source)?;
-        let line_count = source.lines().count().max(1);
-        let line_to_tokens = Self::build_line_map(&tokens, line_count);
-
-        Ok(Self {
+        let mut this = Self {
             source: source.to_string(),
-            tokens,
-            line_to_tokens,
-            line_count,
-        })
+            tokens: Vec::new(),
+            line_to_tokens: Vec::new(),
+            line_count: 0,
+            resumable: Vec::new(),
+            multiline_strings: Vec::new(),
+        };
+        this.full_retokenize()?;
+        Ok(this)
     }
 
     /// Build mapping from line numbers to token index ranges
@@ -2341,6 +3342,13 @@ impl IncrementalTokenizer {
         map
     }
 
+    /// Byte offset where each line starts; one more entry than there are `\n`s
+    fn line_starts(source: &str) -> Vec<usize> {
+        std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect()
+    }
+
     /// Get all tokens
     pub fn tokens(&self) -> &[Token] {
         &self.tokens
@@ -2353,70 +3361,158 @@ impl IncrementalTokenizer {
 
     /// Apply a text change incrementally
     ///
+    /// Re-tokenizes from the last resumable line before the change until the new
+    /// tokens line up with the old ones again, then splices them in and shifts the
+    /// untouched tokens after them. Falls back to [`Self::full_retokenize`] when
+    /// they don't line up within [`RESYNC_LOOKAHEAD_LINES`].
+    ///
     /// Returns the range of tokens that were affected (for potential re-generation)
     pub fn update(&mut self, change: TextChange) -> ParseResult<(usize, usize)> {
-        // Calculate the new source
-        let lines: Vec<&str> = self.source.lines().collect();
-        let mut new_lines: Vec<String> = Vec::new();
-
-        // Lines before the change
-        for line in lines.iter().take(change.start_line) {
-            new_lines.push(line.to_string());
+        let old_starts = Self::line_starts(&self.source);
+        let byte_of_line = |line: usize| old_starts.get(line).copied().unwrap_or(self.source.len());
+        let start_line = change.start_line.min(old_starts.len() - 1);
+        let end_line = change.end_line.clamp(start_line, old_starts.len());
+        let start_byte = byte_of_line(start_line);
+        let end_byte = byte_of_line(end_line);
+
+        let mut new_text = change.new_text;
+        if !new_text.is_empty() && !new_text.ends_with('\n') && end_byte < self.source.len() {
+            new_text.push('\n');
         }
+        let new_source = format!(
+            "{}{}{}",
+            &self.source[..start_byte],
+            new_text,
+            &self.source[end_byte..]
+        );
 
-        // New lines from the change
-        for line in change.new_text.lines() {
-            new_lines.push(line.to_string());
-        }
-        // Handle case where new_text is empty or ends with newline
-        if change.new_text.is_empty() || change.new_text.ends_with('\n') {
-            // The split handles this correctly
+        if let Some(affected) = self.retokenize_window(&new_source, start_line, end_line)? {
+            self.source = new_source;
+            return Ok(affected);
         }
 
-        // Lines after the change
-        for line in lines.iter().skip(change.end_line) {
-            new_lines.push(line.to_string());
+        let old_source = std::mem::replace(&mut self.source, new_source);
+        if let Err(error) = self.full_retokenize() {
+            self.source = old_source;
+            return Err(error);
         }
+        Ok((0, self.tokens.len()))
+    }
 
-        // Build new source
-        let new_source = if new_lines.is_empty() {
-            String::new()
-        } else {
-            new_lines.join("\n") + "\n"
-        };
+    /// Re-tokenize `new_source` around an edit of old lines `start_line..end_line`,
+    /// splicing the result into the tokens. Returns `None`, changing nothing, when
+    /// the caller must fall back to a full re-tokenize.
+    fn retokenize_window(
+        &mut self,
+        new_source: &str,
+        start_line: usize,
+        end_line: usize,
+    ) -> ParseResult<Option<(usize, usize)>> {
+        // Front matter spans lines, so an edit that changes it restarts from scratch
+        if front_matter(&self.source).map(|(raw, _)| raw)
+            != front_matter(new_source).map(|(raw, _)| raw)
+        {
+            return Ok(None);
+        }
 
-        // Calculate affected token range in old tokens
-        let old_token_start = if change.start_line < self.line_to_tokens.len() {
-            self.line_to_tokens[change.start_line].0
-        } else {
-            self.tokens.len().saturating_sub(1) // EOF token
+        let old_starts = Self::line_starts(&self.source);
+        let new_starts = Self::line_starts(new_source);
+        let line_delta = new_starts.len() as isize - old_starts.len() as isize;
+
+        // Restart at or before the previous non-blank line: its classification can
+        // depend on the lines after it (a `for` opening an indented block).
+        let previous = (0..start_line)
+            .rev()
+            .find(|&line| {
+                self.source[old_starts[line]..]
+                    .lines()
+                    .next()
+                    .is_some_and(|text| !text.trim().is_empty())
+            })
+            .unwrap_or(0);
+        let Some(restart_line) = (0..=previous).rev().find(|&line| self.resumable[line]) else {
+            return Ok(None);
         };
 
-        let _old_token_end = if change.end_line < self.line_to_tokens.len() {
-            self.line_to_tokens[change.end_line.saturating_sub(1).max(change.start_line)].1
-        } else {
-            self.tokens.len()
+        let mut tokenizer = Tokenizer::new(new_source);
+        tokenizer.position = Position {
+            byte: new_starts[restart_line],
+            line: restart_line,
+            col: 0,
         };
+        tokenizer.in_multiline_string = self.multiline_strings[restart_line];
+
+        // First line after the edit, in new line numbers
+        let edit_end = end_line.saturating_add_signed(line_delta);
+        let mut window = Vec::new();
+        let mut window_resumable = Vec::new();
+        let mut window_strings = Vec::new();
+        let mut resync_line = None;
+        while !tokenizer.at_eof() {
+            let line = tokenizer.position.line;
+            let clean = tokenizer.at_resumable_state();
+            if line >= edit_end && clean {
+                let old_line = line.saturating_add_signed(-line_delta);
+                if self.resumable.get(old_line) == Some(&true)
+                    && self.multiline_strings[old_line] == tokenizer.in_multiline_string
+                {
+                    resync_line = Some((line, old_line));
+                    break;
+                }
+            }
+            if line > edit_end + RESYNC_LOOKAHEAD_LINES {
+                return Ok(None);
+            }
+            let offset = line - restart_line;
+            window_resumable.resize(offset + 1, false);
+            window_resumable[offset] = clean;
+            window_strings.resize(offset + 1, None);
+            window_strings[offset] = tokenizer.in_multiline_string;
+            tokenizer.tokenize_line(&mut window)?;
+        }
 
-        // For now, use a simple approach: re-tokenize from the changed line to end
-        // A more sophisticated approach would only re-tokenize affected lines
-        // and adjust positions for lines after
-
-        // Full re-tokenize (simpler, still much faster than full transpile)
-        let new_tokens = tokenize(&new_source)?;
-        let new_line_count = new_source.lines().count().max(1);
-        let new_line_map = Self::build_line_map(&new_tokens, new_line_count);
-
-        // Calculate how many new tokens replaced the old range
-        let new_token_start = old_token_start.min(new_tokens.len());
-        let new_token_end = new_tokens.len();
-
-        self.source = new_source;
-        self.tokens = new_tokens;
-        self.line_to_tokens = new_line_map;
-        self.line_count = new_line_count;
+        let head_end = self
+            .tokens
+            .partition_point(|token| token.range().start.byte < old_starts[restart_line]);
+        let mut tokens = self.tokens[..head_end].to_vec();
+        let affected = (head_end, head_end + window.len());
+        tokens.append(&mut window);
+
+        let mut resumable = self.resumable[..restart_line].to_vec();
+        resumable.append(&mut window_resumable);
+        let mut multiline_strings = self.multiline_strings[..restart_line].to_vec();
+        multiline_strings.append(&mut window_strings);
+        match resync_line {
+            Some((line, old_line)) => {
+                resumable.resize(line, false);
+                resumable.extend_from_slice(&self.resumable[old_line..]);
+                multiline_strings.resize(line, None);
+                multiline_strings.extend_from_slice(&self.multiline_strings[old_line..]);
+
+                let byte_delta = new_starts[line] as isize - old_starts[old_line] as isize;
+                let tail_start = self
+                    .tokens
+                    .partition_point(|token| token.range().start.byte < old_starts[old_line]);
+                tokens.extend(self.tokens[tail_start..].iter().cloned().map(|mut token| {
+                    token.shift(byte_delta, line_delta);
+                    token
+                }));
+            }
+            None => {
+                resumable.resize(new_starts.len(), false);
+                multiline_strings.resize(new_starts.len(), None);
+                tokens.push(Token::Eof {
+                    position: tokenizer.position,
+                });
+            }
+        }
 
-        Ok((new_token_start, new_token_end))
+        self.tokens = tokens;
+        self.resumable = resumable;
+        self.multiline_strings = multiline_strings;
+        self.line_count = new_source.lines().count().max(1);
+        self.line_to_tokens = Self::build_line_map(&self.tokens, self.line_count);
+        Ok(Some(affected))
     }
 
     /// Get tokens for a specific line range
@@ -2438,15 +3534,33 @@ impl IncrementalTokenizer {
 
     /// Re-tokenize completely (for when incremental update isn't sufficient)
     pub fn full_retokenize(&mut self) -> ParseResult<()> {
-        self.tokens = tokenize(&self.source)?;
+        let mut tokenizer = Tokenizer::new(&self.source);
+        let mut tokens = Vec::new();
+        let line_count = Self::line_starts(&self.source).len();
+        let mut resumable = vec![false; line_count];
+        let mut multiline_strings = vec![None; line_count];
+        while !tokenizer.at_eof() {
+            let line = tokenizer.position.line;
+            resumable[line] = tokenizer.at_resumable_state();
+            multiline_strings[line] = tokenizer.in_multiline_string;
+            tokenizer.tokenize_line(&mut tokens)?;
+        }
+        tokens.push(Token::Eof {
+            position: tokenizer.position,
+        });
+
+        self.tokens = tokens;
+        self.resumable = resumable;
+        self.multiline_strings = multiline_strings;
         self.line_count = self.source.lines().count().max(1);
         self.line_to_tokens = Self::build_line_map(&self.tokens, self.line_count);
         Ok(())
     }
 }
 
-/// Tokenize a single line (for incremental updates)
-pub fn tokenize_line(line: &str, line_number: usize) -> ParseResult<Vec<Token>> {
+/// Tokenize a single line (for incremental updates) that begins at `start`
+/// in its file, so token and error positions match a full [`tokenize`].
+pub fn tokenize_line(line: &str, start: Position) -> ParseResult<Vec<Token>> {
     // Add newline if not present for consistent tokenization
     let source = if line.ends_with('\n') {
         line.to_string()
@@ -2456,14 +3570,25 @@ pub fn tokenize_line(line: &str, line_number: usize) -> ParseResult<Vec<Token>>
 
     let mut tokenizer = Tokenizer::new(&source);
 
-    // Adjust the starting position to reflect the actual line number
-    tokenizer.position.line = line_number;
+    // Lines and columns count from `start`; bytes are shifted into place after
+    tokenizer.position.line = start.line;
+    tokenizer.position.col = start.col;
 
     let mut tokens = Vec::new();
-    tokenizer.tokenize_line(&mut tokens)?;
+    let shift = start.byte as isize;
+    if let Err(mut error) = tokenizer.tokenize_line(&mut tokens) {
+        for range in std::iter::once(&mut error.range).chain(error.related_range.as_mut()) {
+            range.start.shift(shift, 0);
+            range.end.shift(shift, 0);
+        }
+        return Err(error);
+    }
 
     // Remove the Eof token if present
     tokens.retain(|t| !matches!(t, Token::Eof { .. }));
+    for token in &mut tokens {
+        token.shift(shift, 0);
+    }
 
     Ok(tokens)
 }
@@ -2516,6 +3641,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_block_comment_is_dropped() {
+        let tokens = tokenize("<p>a {# hidden #} b</p>\n");
+        let texts: Vec<_> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(texts, ["a ", " b"]);
+        assert!(!tokens.iter().any(|t| matches!(t, Token::Comment { .. })));
+    }
+
+    #[test]
+    fn test_block_comment_marker_in_attribute_string() {
+        let tokens = tokenize("<a href=\"{#x}\">Link</a>\n");
+        assert!(
+            matches!(&tokens[0], Token::HtmlElementOpen { attributes, .. }
+            if matches!(&attributes[0].value, AttributeValue::String(value) if value == "{#x}"))
+        );
+        assert!(matches!(&tokens[1], Token::Text { text, .. } if text == "Link"));
+    }
+
     #[test]
     fn test_control_flow() {
         let tokens = tokenize("if count > 0:\n");
@@ -2543,6 +3692,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_literal_angle_brackets() {
+        // `<` before a space is plain text
+        let tokens = tokenize("5 < 10\n");
+        assert!(matches!(&tokens[0], Token::Text { text, .. } if text == "5 < 10"));
+
+        // `\<` before a letter is an escaped `<`, not a tag
+        let tokens = tokenize("a \\<b c\n");
+        let kinds: Vec<_> = tokens
+            .iter()
+            .map(|t| match t {
+                Token::Text { text, .. } => format!("Text {text:?}"),
+                Token::EscapedBrace { brace, .. } => format!("Escaped {brace}"),
+                other => format!("{:?}", std::mem::discriminant(other)),
+            })
+            .take(3)
+            .collect();
+        assert_eq!(kinds, ["Text \"a \"", "Escaped <", "Text \"b c\""]);
+
+        // Real elements still open
+        let tokens = tokenize("a <b>c</b>\n");
+        assert!(
+            tokens
+                .iter()
+                .any(|t| matches!(t, Token::HtmlElementOpen { tag, .. } if tag == "b"))
+        );
+    }
+
     #[test]
     fn test_component_self_closing() {
         let tokens = tokenize("<{Button} type=\"submit\" />\n");
@@ -2590,6 +3767,28 @@ mod tests {
         assert!(matches!(&tokens[0], Token::Decorator { code, .. } if code == "@cache"));
     }
 
+    #[test]
+    fn test_multiline_decorator() {
+        let source = "@route(\n    \"/path\",\n    methods=[\"GET\"],\n)\ndef view():\n";
+        let tokens = tokenize(source);
+        let Token::Decorator { code, range } = &tokens[0] else {
+            panic!("Expected Decorator, got {:?}", tokens[0]);
+        };
+        assert_eq!(code, "@route(\n    \"/path\",\n    methods=[\"GET\"],\n)");
+        assert_eq!(range.end.line, 3);
+        assert!(matches!(&tokens[1], Token::Newline { .. }));
+        assert!(matches!(&tokens[2], Token::ControlStart { keyword, .. } if keyword == "def"));
+    }
+
+    #[test]
+    fn test_css_at_rule_is_not_a_decorator() {
+        let tokens = tokenize("@media (max-width: 600px) {{\n");
+        assert!(!tokens.iter().any(|t| matches!(t, Token::Decorator { .. })));
+        assert!(
+            matches!(&tokens[0], Token::Text { text, .. } if text.starts_with("@media (max-width: 600px) "))
+        );
+    }
+
     #[test]
     fn test_indent() {
         let tokens = tokenize("    <span>Indented</span>\n");
@@ -2667,6 +3866,175 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_script_body_is_opaque() {
+        let tokens = tokenize("<script>if (a < b) { render('<div>{x}</div>'); }</script>\n");
+        assert!(
+            !tokens
+                .iter()
+                .any(|t| matches!(t, Token::Expression { .. } | Token::EscapedBrace { .. }))
+        );
+        let opens = tokens
+            .iter()
+            .filter(|t| matches!(t, Token::HtmlElementOpen { .. }))
+            .count();
+        assert_eq!(opens, 1, "<div inside a JS string is not an element");
+        assert!(tokens.iter().any(
+            |t| matches!(t, Token::Text { text, .. } if text == "if (a < b) { render('<div>{x}</div>'); }")
+        ));
+        assert!(matches!(
+            tokens.iter().rev().nth(2),
+            Some(Token::HtmlElementClose { tag, .. }) if tag == "script"
+        ));
+    }
+
+    #[test]
+    fn test_raw_text_closing_tag_mid_line() {
+        let tokens = tokenize("<script>\n  run({a: 1}); </script><p>{x}</p>\n");
+        assert!(
+            tokens
+                .iter()
+                .any(|t| matches!(t, Token::Text { text, .. } if text == "run({a: 1}); "))
+        );
+        assert!(
+            tokens
+                .iter()
+                .any(|t| matches!(t, Token::HtmlElementClose { tag, .. } if tag == "script"))
+        );
+        // Normal content resumes after the closing tag
+        assert!(
+            tokens
+                .iter()
+                .any(|t| matches!(t, Token::Expression { code, .. } if code == "x"))
+        );
+    }
+
+    #[test]
+    fn test_raw_text_interpolate_opt_in() {
+        let tokens = tokenize("<style hyper-interpolate>.a {{ color: {color}; }}</style>\n");
+        let Token::HtmlElementOpen { attributes, .. } = &tokens[0] else {
+            panic!("expected <style> open, got {:?}", tokens[0]);
+        };
+        assert!(attributes.is_empty(), "directive attribute is not emitted");
+        assert!(
+            tokens
+                .iter()
+                .any(|t| matches!(t, Token::Expression { code, .. } if code == "color"))
+        );
+        let braces = tokens
+            .iter()
+            .filter(|t| matches!(t, Token::EscapedBrace { .. }))
+            .count();
+        assert_eq!(braces, 2);
+    }
+
+    #[test]
+    fn test_pre_keeps_indentation_as_text() {
+        let tokens = tokenize("<pre>\n  if x:\n\t{y}\n</pre>\n    <p>z</p>\n");
+        let texts: Vec<&str> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(texts, ["  ", "if x:", "\t", "z"]);
+        assert!(
+            tokens
+                .iter()
+                .any(|t| matches!(t, Token::Expression { code, .. } if code == "y"))
+        );
+        // Only the line after </pre> is indented
+        let indents = tokens
+            .iter()
+            .filter(|t| matches!(t, Token::Indent { .. }))
+            .count();
+        assert_eq!(indents, 1);
+    }
+
+    /// Debug dump of the tokens with byte offsets removed: CRLF shifts bytes
+    /// but must leave values, lines and columns as for LF.
+    fn tokens_without_bytes(source: &str) -> String {
+        let dump = format!("{:?}", tokenize(source));
+        let mut out = String::new();
+        let mut rest = dump.as_str();
+        while let Some(at) = rest.find("byte: ") {
+            out.push_str(&rest[..at]);
+            rest = rest[at + "byte: ".len()..].trim_start_matches(|c: char| c.is_ascii_digit());
+        }
+        out.push_str(rest);
+        out
+    }
+
+    #[test]
+    fn test_crlf_tokens_match_lf() {
+        let lf = "title: str\n---\n<div class={[\n    \"a\",\n]}\n    title=\"x\ny\">\n    if title:\n        <p>{\n            title\n        }</p>\n    end\n    items = [\n        1,\n    ]\n</div>\n";
+        let crlf = lf.replace('\n', "\r\n");
+        assert_eq!(tokens_without_bytes(&crlf), tokens_without_bytes(lf));
+
+        // Byte offsets still index the CRLF source
+        let tokens = tokenize(&crlf);
+        let Some(Token::Expression { range, .. }) = tokens
+            .iter()
+            .find(|t| matches!(t, Token::Expression { .. }))
+        else {
+            panic!("expected an expression token");
+        };
+        assert_eq!(
+            &crlf[range.start.byte..range.end.byte],
+            "{\r\n            title\r\n        }"
+        );
+    }
+
+    #[test]
+    fn test_raw_expression_marker() {
+        let tokens = tokenize("<p>{!html}{...}</p>\n");
+        let exprs: Vec<_> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::Expression { code, raw, .. } => Some((code.as_str(), *raw)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(exprs, vec![("html", true), ("children", false)]);
+    }
+
+    #[test]
+    fn test_escape_expression_marker() {
+        let tokens = tokenize("<p>{&name}{name}</p>\n");
+        let exprs: Vec<_> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::Expression {
+                    code, force_escape, ..
+                } => Some((code.as_str(), *force_escape)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(exprs, vec![("name", true), ("name", false)]);
+    }
+
+    #[test]
+    fn test_whitespace_control_markers() {
+        let tokens = tokenize("<p>{- x -}{-x}{ y -}</p>\n");
+        let exprs: Vec<_> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::Expression {
+                    code,
+                    trim_left,
+                    trim_right,
+                    ..
+                } => Some((code.as_str(), *trim_left, *trim_right)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            exprs,
+            vec![("x", true, true), ("-x", false, false), (" y", false, true)]
+        );
+    }
+
     #[test]
     fn test_nested_expression() {
         // Nested braces in expressions
@@ -2681,6 +4049,71 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_nested_fstring_expression() {
+        let expression_codes = |source: &str| -> Vec<String> {
+            tokenize(source)
+                .into_iter()
+                .filter_map(|t| match t {
+                    Token::Expression { code, .. } => Some(code),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(expression_codes("<p>{f\"{x}\"}</p>\n"), ["f\"{x}\""]);
+        assert_eq!(
+            expression_codes("<p>{f\"{a:>{width}}\"} {f\"{d[\"}\"]}\"}</p>\n"),
+            ["f\"{a:>{width}}\"", "f\"{d[\"}\"]}\""]
+        );
+        // Doubled braces are literal inside the f-string
+        assert_eq!(expression_codes("<p>{f'{{x}}'}</p>\n"), ["f'{{x}}'"]);
+        assert_eq!(
+            expression_codes("<p>{ {\"k\": v} }</p>\n"),
+            [" {\"k\": v} "]
+        );
+    }
+
+    #[test]
+    fn test_inline_assignment() {
+        let source = "<p>{ total = price * qty }{total}</p>\n";
+        let tokens = tokenize(source);
+        let Some(Token::PythonStatement { code, range }) = tokens
+            .iter()
+            .find(|t| matches!(t, Token::PythonStatement { .. }))
+        else {
+            panic!("Expected PythonStatement, got {tokens:?}");
+        };
+        assert_eq!(code, "total = price * qty");
+        assert_eq!(&source[range.start.byte..range.end.byte], code);
+        assert_eq!((range.start.col, range.end.col), (5, 24));
+        assert!(
+            tokens
+                .iter()
+                .any(|t| matches!(t, Token::Expression { code, .. } if code == "total"))
+        );
+    }
+
+    #[test]
+    fn test_expressions_that_are_not_assignments() {
+        for code in [
+            "user['name']",
+            "a == b",
+            "x=",
+            "f(key=1)",
+            "{'a': b}",
+            "x:0=8",
+            "!html",
+        ] {
+            let tokens = tokenize(&format!("<span>{{{code}}}</span>\n"));
+            assert!(
+                !tokens
+                    .iter()
+                    .any(|t| matches!(t, Token::PythonStatement { .. })),
+                "{code:?} should stay an expression"
+            );
+        }
+    }
+
     #[test]
     fn test_dict_comprehension_looks_like_escape() {
         // {{k: v for k, v in d.items()}} - dict comprehension looks like double-escape
@@ -2710,21 +4143,48 @@ mod tests {
 
     #[test]
     fn test_content_with_for() {
-        // "for your information" currently matches "for " prefix
-        // This is a known limitation - could be improved by checking for "in" clause
-        // For now, this is classified as control (which will fail at Python level)
-        let tokens = tokenize("for your information\n");
-        let token_types: Vec<&str> = tokens
-            .iter()
-            .map(|t| match t {
-                Token::Text { .. } => "Text",
-                Token::ControlStart { .. } => "Control",
-                _ => "Other",
-            })
-            .collect();
-        // Currently classified as control - could improve by validating for/in pattern
-        // This is acceptable because invalid Python will fail at transpile time
-        assert!(token_types.contains(&"Control") || token_types.contains(&"Text"));
+        // A `for` line without an `in` clause is prose, even with a trailing colon
+        let tokens = tokenize("<p>\nfor your information:\n</p>\n");
+        assert!(
+            !tokens
+                .iter()
+                .any(|t| matches!(t, Token::ControlStart { .. }))
+        );
+        assert!(
+            tokens
+                .iter()
+                .any(|t| matches!(t, Token::Text { text, .. } if text == "for your information:"))
+        );
+    }
+
+    #[test]
+    fn test_for_in_is_control_flow() {
+        let tokens = tokenize("for x in items:\n<li>{x}</li>\nend\n");
+        assert!(matches!(
+            &tokens[0],
+            Token::ControlStart { keyword, rest, .. } if keyword == "for" && rest.starts_with("x in items")
+        ));
+    }
+
+    #[test]
+    fn test_for_in_without_spaces_is_control_flow() {
+        for source in [
+            "for x in[1, 2]:\n<li>{x}</li>\nend\n",
+            "for x in\titems:\n<li>{x}</li>\nend\n",
+        ] {
+            let tokens = tokenize(source);
+            assert!(
+                matches!(&tokens[0], Token::ControlStart { keyword, .. } if keyword == "for"),
+                "{source:?}: {tokens:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_for_without_in_opening_block_is_control_flow() {
+        // Malformed loops that open a block still reach the parser, which reports them
+        let tokens = tokenize("for item items:\n    <li>{item}</li>\nend\n");
+        assert!(matches!(&tokens[0], Token::ControlStart { keyword, .. } if keyword == "for"));
     }
 
     #[test]
@@ -2779,6 +4239,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_except_tuple_without_space() {
+        let tokens = tokenize("except(ValueError, KeyError):\n");
+        assert!(matches!(
+            &tokens[0],
+            Token::ControlContinuation { keyword, rest: Some(rest), .. }
+                if keyword == "except" && rest == "(ValueError, KeyError):"
+        ));
+    }
+
     #[test]
     fn test_import_statement() {
         let tokens = tokenize("from datetime import datetime\n");
@@ -2799,6 +4269,92 @@ mod tests {
         assert!(matches!(&tokens[0], Token::PythonStatement { .. }));
     }
 
+    #[test]
+    fn test_walrus_statement() {
+        let tokens = tokenize("(user := get_user())\n");
+        assert!(
+            matches!(&tokens[0], Token::PythonStatement { code, .. } if code == "(user := get_user())")
+        );
+
+        // Prose in parentheses stays content
+        let tokens = tokenize("(see below)\n");
+        assert!(matches!(&tokens[0], Token::Text { .. }));
+    }
+
+    #[test]
+    fn test_walrus_in_condition() {
+        let tokens = tokenize("if (n := len(items)) > 0:\n");
+        assert!(matches!(
+            &tokens[0],
+            Token::ControlStart { keyword, rest, .. } if keyword == "if" && rest == "(n := len(items)) > 0:"
+        ));
+
+        let tokens = tokenize("while (line := read()) is not None:\n");
+        assert!(matches!(
+            &tokens[0],
+            Token::ControlStart { keyword, rest, .. }
+                if keyword == "while" && rest == "(line := read()) is not None:"
+        ));
+    }
+
+    #[test]
+    fn test_fenced_code_block_is_verbatim() {
+        let tokens = tokenize("```html\n<div>{x}</div>\n  if y:\n```\n<p>{z}</p>\n");
+        let texts: Vec<_> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(texts, ["```html", "<div>{x}</div>", "if y:", "```"]);
+        assert!(
+            tokens
+                .iter()
+                .any(|t| matches!(t, Token::Indent { level: 2, .. }))
+        );
+        assert!(
+            tokens
+                .iter()
+                .any(|t| matches!(t, Token::Expression { code, .. } if code == "z"))
+        );
+    }
+
+    #[test]
+    fn test_inline_code_is_not_a_fence() {
+        let tokens = tokenize("```x``` and {y}\n");
+        assert!(
+            tokens
+                .iter()
+                .any(|t| matches!(t, Token::Expression { code, .. } if code == "y"))
+        );
+    }
+
+    #[test]
+    fn test_banner_is_not_a_fence() {
+        let tokens = tokenize("~~~ Welcome ~~~\n<p>{y}</p>\n");
+        assert!(
+            tokens
+                .iter()
+                .any(|t| matches!(t, Token::Expression { code, .. } if code == "y"))
+        );
+    }
+
+    #[test]
+    fn test_less_than_in_text_is_not_a_fragment() {
+        for source in ["<p>a < b and c <= d</p>\n", "<p>a <> b</p>\n"] {
+            let tokens = tokenize(source);
+            assert!(
+                !tokens
+                    .iter()
+                    .any(|t| matches!(t, Token::FragmentOpen { .. } | Token::FragmentClose { .. }))
+            );
+        }
+        let tokens = tokenize("<p><>{x}</></p>\n");
+        assert!(matches!(&tokens[1], Token::FragmentOpen { .. }));
+        assert!(matches!(&tokens[3], Token::FragmentClose { .. }));
+    }
+
     #[test]
     fn test_alpine_js_x_data() {
         // Alpine.js x-data attribute with JS object
@@ -2827,6 +4383,66 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_expression_attribute_range_excludes_braces() {
+        let source = "<div data-n={x + 1} hidden={!flag}></div>\n";
+        let tokens = tokenize(source);
+        let Token::HtmlElementOpen { attributes, .. } = &tokens[0] else {
+            panic!("Expected HtmlElementOpen");
+        };
+        let spans: Vec<_> = attributes
+            .iter()
+            .map(|attr| match &attr.value {
+                AttributeValue::Expression(_, range) => &source[range.start.byte..range.end.byte],
+                other => panic!("Expected an expression, got {other:?}"),
+            })
+            .collect();
+        assert_eq!(spans, ["x + 1", "!flag"]);
+    }
+
+    #[test]
+    fn test_attribute_values_with_brackets_and_braces_in_strings() {
+        let source = "<div a={d[\"}\"]} b=\"{d['}']}!\" c=\":)\" d=\"[x\">y</div>\n";
+        let tokens = tokenize(source);
+        let Token::HtmlElementOpen { attributes, .. } = &tokens[0] else {
+            panic!("Expected HtmlElementOpen, got {:?}", tokens[0]);
+        };
+        let values: Vec<_> = attributes
+            .iter()
+            .map(|attr| match &attr.value {
+                AttributeValue::Expression(value, _) | AttributeValue::String(value) => {
+                    value.as_str()
+                }
+                other => panic!("Expected a value, got {other:?}"),
+            })
+            .collect();
+        assert_eq!(values, ["d[\"}\"]", "{d['}']}!", ":)", "[x"]);
+        assert!(matches!(&tokens[1], Token::Text { text, .. } if text == "y"));
+    }
+
+    #[test]
+    fn test_optional_attribute_flag() {
+        let tokens = tokenize("<div data-x?={value} data-y={value}></div>\n");
+        let Token::HtmlElementOpen { attributes, .. } = &tokens[0] else {
+            panic!("Expected HtmlElementOpen");
+        };
+        assert_eq!(attributes[0].name, "data-x");
+        assert!(attributes[0].optional);
+        assert!(
+            matches!(&attributes[0].value, AttributeValue::Expression(code, _) if code == "value")
+        );
+        assert!(!attributes[1].optional);
+    }
+
+    #[test]
+    fn test_optional_attribute_needs_expression() {
+        let err = super::tokenize("<div data-x?=\"a\"></div>\n").unwrap_err();
+        assert_eq!(
+            err.message,
+            "Attribute `data-x?` needs an expression value."
+        );
+    }
+
     #[test]
     fn test_component_shorthand() {
         let tokens = tokenize("<{Input} {value} {disabled} />\n");
@@ -2843,6 +4459,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_unicode_attribute_names() {
+        let tokens = tokenize("<p {café} données-x=\"1\" título={naïve}></p>\n");
+        let Token::HtmlElementOpen { attributes, .. } = &tokens[0] else {
+            panic!("Expected HtmlElementOpen");
+        };
+        assert_eq!(attributes.len(), 3);
+        assert!(
+            matches!(&attributes[0].value, AttributeValue::Shorthand(name, _) if name == "café")
+        );
+        assert_eq!(attributes[1].name, "données-x");
+        assert_eq!(attributes[2].name, "título");
+        assert!(
+            matches!(&attributes[2].value, AttributeValue::Expression(code, _) if code == "naïve")
+        );
+    }
+
     #[test]
     fn test_empty_lines() {
         let tokens = tokenize("\n\n\n");
@@ -2918,6 +4551,74 @@ mod tests {
         assert_eq!(stmt_tokens.len(), 1);
     }
 
+    #[test]
+    fn test_backslash_continues_python_statement() {
+        let tokens = tokenize("total = price + \\\n    tax\n<p>{total}</p>\n");
+        assert!(
+            matches!(&tokens[0], Token::PythonStatement { code, .. } if code == "total = price + \\\n    tax"),
+            "{tokens:?}"
+        );
+
+        let tokens = tokenize("if a and \\\n        b:\n    <p>yes</p>\nend\n");
+        assert!(
+            matches!(&tokens[0], Token::ControlStart { keyword, rest, .. } if keyword == "if" && rest == "a and \\\n        b:"),
+            "{tokens:?}"
+        );
+    }
+
+    #[test]
+    fn test_trailing_backslash_in_content_is_text() {
+        let tokens = tokenize("<p>path \\\nnext</p>\nsee \\\n");
+        let text: Vec<_> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, ["path \\", "next", "see \\"]);
+        assert!(
+            !tokens
+                .iter()
+                .any(|t| matches!(t, Token::PythonStatement { .. }))
+        );
+    }
+
+    #[test]
+    fn test_multiline_opening_tag_attributes() {
+        let source =
+            "<div\n    class=\"card\"\n    id=\"main\"\n    data-x={x}\n>\nHello\n</div>\n";
+        let tokens = tokenize(source);
+
+        let Token::HtmlElementOpen {
+            tag, attributes, ..
+        } = &tokens[0]
+        else {
+            panic!("expected a single opening tag, got {:?}", tokens[0]);
+        };
+        assert_eq!(tag, "div");
+        let names: Vec<_> = attributes.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["class", "id", "data-x"]);
+        assert!(matches!(&tokens[1], Token::Newline { .. }));
+        assert!(matches!(&tokens[2], Token::Text { text, .. } if text == "Hello"));
+    }
+
+    #[test]
+    fn test_multiline_opening_tag_stops_at_blank_line() {
+        let error = super::tokenize("<div\n    class=\"card\"\n\nHello\n</div>\n")
+            .expect_err("blank line should end the opening tag");
+        assert_eq!(error.message, "Unclosed opening tag.");
+        assert_eq!(error.range.end.byte, "<div\n    class=\"card\"".len());
+    }
+
+    #[test]
+    fn test_multiline_opening_tag_stops_at_next_tag() {
+        let error = super::tokenize("<div\n    class=\"card\"\n<p>Hello</p>\n")
+            .expect_err("another tag should end the opening tag");
+        assert_eq!(error.message, "Unclosed opening tag.");
+        assert_eq!(error.range.end.line, 1);
+    }
+
     #[test]
     fn test_single_line_statement_unchanged() {
         // Single line statements should still work
@@ -2935,6 +4636,45 @@ mod tests {
         assert!(matches!(&tokens[0], Token::Separator { .. }));
     }
 
+    #[test]
+    fn test_front_matter() {
+        let source = "---\ntitle: Home\nlayout: base\n---\nname: str\n---\n<p>{name}</p>\n";
+        let tokens = tokenize(source);
+
+        let Token::FrontMatter { raw, range } = &tokens[0] else {
+            panic!("expected front matter, got {:?}", tokens[0]);
+        };
+        assert_eq!(raw, "title: Home\nlayout: base");
+        assert_eq!((range.start.byte, range.end.byte), (0, 32));
+        assert_eq!(range.end.line, 3);
+        assert!(matches!(&tokens[1], Token::Newline { .. }));
+        assert!(matches!(&tokens[2], Token::PythonStatement { code, .. } if code == "name: str"));
+        let separators = tokens
+            .iter()
+            .filter(|t| matches!(t, Token::Separator { .. }))
+            .count();
+        assert_eq!(separators, 1);
+    }
+
+    #[test]
+    fn test_lone_leading_separator_is_not_front_matter() {
+        let tokens = tokenize("---\n<p>hi</p>\n");
+        assert!(matches!(&tokens[0], Token::Separator { .. }));
+        assert!(
+            !tokens
+                .iter()
+                .any(|t| matches!(t, Token::FrontMatter { .. }))
+        );
+
+        // Only a fence on the very first line opens front matter
+        let tokens = tokenize("name: str\n---\n<p>{name}</p>\n---\n");
+        assert!(
+            !tokens
+                .iter()
+                .any(|t| matches!(t, Token::FrontMatter { .. }))
+        );
+    }
+
     #[test]
     fn test_separator_with_content() {
         let source = "name: str\n---\n<div>{name}</div>\n";
@@ -2967,6 +4707,29 @@ mod tests {
             "HTML content should come after separator"
         );
     }
+
+    #[test]
+    fn test_doctype_is_single_text_token() {
+        let doctype = r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" "x>y.dtd">"#;
+        let tokens = tokenize(&format!("{doctype}<html>{{x}}</html>\n"));
+        assert!(matches!(&tokens[0], Token::Text { text, .. } if text == doctype));
+        assert!(matches!(&tokens[1], Token::HtmlElementOpen { tag, .. } if tag == "html"));
+    }
+
+    #[test]
+    fn test_doctype_round_trips_into_yield() {
+        let doctype = r#"<!doctype html SYSTEM "about:legacy-compat" [ <!ENTITY x "{}"> ]>"#;
+        let source = format!("{doctype}<html>{{x}}</html>\n");
+        let result = crate::compile(&source, &Default::default()).expect("should compile");
+        let expected = doctype.replace('{', "{{").replace('}', "}}");
+        assert!(
+            result
+                .code
+                .contains(&format!("yield f\"\"\"{expected}<html>")),
+            "doctype should be emitted verbatim:\n{}",
+            result.code
+        );
+    }
 }
 
 #[cfg(test)]
@@ -2984,10 +4747,12 @@ mod rest_span_tests {
             rest,
             range: _,
             rest_range,
+            comment,
         } = &tokens[0]
         {
             assert_eq!(keyword, "if");
             assert_eq!(rest, "is_active:");
+            assert_eq!(comment, &None);
             // rest_range should start after "if " (3 bytes)
             assert_eq!(rest_range.start.byte, 3);
             assert_eq!(
@@ -2998,4 +4763,189 @@ mod rest_span_tests {
             panic!("Expected ControlStart token");
         }
     }
+
+    #[test]
+    fn test_trailing_comment_kept_off_rest() {
+        let source = "if flag:  # note\n";
+        let tokens = tokenize(source).expect("test source should tokenize");
+
+        if let Token::ControlStart {
+            rest,
+            rest_range,
+            comment,
+            ..
+        } = &tokens[0]
+        {
+            assert_eq!(rest, "flag:");
+            assert_eq!(&source[rest_range.start.byte..rest_range.end.byte], "flag:");
+            assert_eq!(comment.as_deref(), Some("# note"));
+        } else {
+            panic!("Expected ControlStart token");
+        }
+    }
+}
+
+#[cfg(test)]
+mod incremental_tests {
+    use super::*;
+
+    /// A template mixing single-line and multi-line constructs, repeated
+    const BLOCK: &str = "<div class=\"item\">\n    for item in items:\n        <li>{item}</li>\n    end\n    config = {\n        \"key\": item,\n    }\n    <a\n        href={url}\n    >link</a>\n</div>\n\n";
+
+    fn sample(blocks: usize) -> String {
+        format!("items: list\nurl: str\n---\n{}", BLOCK.repeat(blocks))
+    }
+
+    fn edit(source: &str, start_line: usize, end_line: usize, new_text: &str) -> TextChange {
+        assert!(end_line <= source.lines().count());
+        TextChange {
+            start_line,
+            end_line,
+            new_text: new_text.to_string(),
+        }
+    }
+
+    fn assert_matches_full(incremental: &IncrementalTokenizer) {
+        let full = tokenize(incremental.source()).expect("edited source should tokenize");
+        assert_eq!(incremental.tokens(), full.as_slice());
+    }
+
+    #[test]
+    fn test_edit_in_large_file_matches_full_tokenize() {
+        let source = sample(84);
+        assert!(source.lines().count() >= 1000);
+        let mut incremental = IncrementalTokenizer::new(&source).unwrap();
+        let total = incremental.tokens().len();
+
+        let change = edit(&source, 1, 2, "url: str = \"/home\"\n");
+        let (start, end) = incremental.update(change).unwrap();
+
+        assert_matches_full(&incremental);
+        assert!(end - start < 10, "re-tokenized {start}..{end} of {total}");
+    }
+
+    #[test]
+    fn test_edits_that_add_and_remove_lines_shift_later_tokens() {
+        let source = sample(6);
+        let mut incremental = IncrementalTokenizer::new(&source).unwrap();
+
+        incremental
+            .update(edit(
+                &source,
+                5,
+                5,
+                "        <li>new</li>\n        <li>{more}</li>\n",
+            ))
+            .unwrap();
+        assert_matches_full(&incremental);
+
+        let source = incremental.source().to_string();
+        incremental.update(edit(&source, 16, 19, "")).unwrap();
+        assert_matches_full(&incremental);
+    }
+
+    #[test]
+    fn test_edits_that_open_or_change_front_matter_match_full_tokenize() {
+        let source = format!("---\n{}", BLOCK);
+        let mut incremental = IncrementalTokenizer::new(&source).unwrap();
+
+        // A second fence turns the leading separator into front matter
+        incremental.update(edit(&source, 2, 2, "---\n")).unwrap();
+        assert_matches_full(&incremental);
+        assert!(matches!(incremental.tokens()[0], Token::FrontMatter { .. }));
+
+        let source = incremental.source().to_string();
+        incremental
+            .update(edit(&source, 1, 2, "title: Home\n"))
+            .unwrap();
+        assert_matches_full(&incremental);
+
+        let source = incremental.source().to_string();
+        incremental.update(edit(&source, 2, 3, "")).unwrap();
+        assert_matches_full(&incremental);
+        assert!(matches!(incremental.tokens()[0], Token::Separator { .. }));
+    }
+
+    #[test]
+    fn test_every_line_edit_matches_full_tokenize() {
+        let source = format!("\"\"\"\nDocs\n\"\"\"\n{}", sample(2));
+        let replacements = [
+            "",
+            "<p>{x}</p>\n",
+            "    x = (\n",
+            "if x:\n",
+            "end\n",
+            ">\n",
+            "\"\"\"\n",
+            "<style>\n",
+            "</style>\n",
+        ];
+        for line in 0..source.lines().count() {
+            for replacement in replacements {
+                let Ok(mut incremental) = IncrementalTokenizer::new(&source) else {
+                    continue;
+                };
+                let change = edit(&source, line, line + 1, replacement);
+                if incremental.update(change).is_ok() {
+                    assert_matches_full(&incremental);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_edit_inside_docstring_keeps_string_state() {
+        let mut source =
+            "\"\"\"\nCard docs.\n<p>Not markup</p>\n\"\"\"\ntitle: str\n---\n".to_string();
+        source.push_str(&"<p>{title}</p>\n".repeat(40));
+        let mut incremental = IncrementalTokenizer::new(&source).unwrap();
+
+        let (start, end) = incremental
+            .update(edit(&source, 1, 2, "if this: docs are {changed}\n"))
+            .unwrap();
+
+        assert_matches_full(&incremental);
+        assert!(end - start <= 4, "re-tokenized {start}..{end}");
+        let docstring = incremental.tokens_for_lines(0, 3);
+        assert!(
+            docstring
+                .iter()
+                .all(|t| matches!(t, Token::PythonStatement { .. } | Token::Newline { .. })),
+            "{docstring:?}"
+        );
+    }
+
+    #[test]
+    fn test_tokenize_line_matches_full_tokenize() {
+        let source = "name: str\n---\n<ul>\n    for item in items:\n        <li>{item}</li>\n    <li class={name}>é {name}</li>\n</ul>\n";
+        let full = tokenize(source).unwrap();
+        let line_start = source.match_indices('\n').nth(4).unwrap().0 + 1;
+        let line_end = source[line_start..].find('\n').unwrap() + line_start + 1;
+        let start = Position {
+            byte: line_start,
+            line: 5,
+            col: 0,
+        };
+
+        let tokens = super::tokenize_line(&source[line_start..line_end], start).unwrap();
+        let expected: Vec<_> = full
+            .into_iter()
+            .filter(|t| t.range().start.line == 5)
+            .collect();
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn test_unterminated_multiline_string_falls_back_to_full_retokenize() {
+        let mut source = "x = 1\n".to_string();
+        source.push_str(&"<p>{x}</p>\n".repeat(40));
+        let mut incremental = IncrementalTokenizer::new(&source).unwrap();
+
+        let (start, end) = incremental
+            .update(edit(&source, 0, 1, "\"\"\"Docs\n"))
+            .unwrap();
+
+        assert_matches_full(&incremental);
+        assert_eq!((start, end), (0, incremental.tokens().len()));
+    }
 }
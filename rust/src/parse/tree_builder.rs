@@ -1,4 +1,4 @@
-use super::tokenizer::{Position, TextRange, Token};
+use super::tokenizer::{Position, TextRange, Token, find_for_in, find_top_level_assignment};
 use crate::ast::*;
 use crate::error::{ErrorKind, ParseError, ParseResult};
 use crate::html;
@@ -14,7 +14,18 @@ pub struct TreeBuilder {
     source: Arc<str>,
     in_header: bool, // Track if we're before the --- separator
     has_separator: bool,
-    element_stack: Vec<String>, // Parent element names for nesting validation
+    front_matter: Option<String>,
+    element_stack: Vec<OpenElement>, // Enclosing elements, for nesting validation
+    open_fragments: usize,           // Enclosing `<>` fragments
+    open_blocks: usize,              // Enclosing `end`-terminated blocks
+}
+
+/// An element whose children are being parsed. Components and slots push an
+/// entry without a tag: their children render elsewhere, so nesting rules
+/// don't look past them.
+struct OpenElement {
+    tag: Option<String>,
+    range: TextRange,
 }
 
 impl TreeBuilder {
@@ -28,7 +39,10 @@ impl TreeBuilder {
             source,
             in_header: true, // Start in header zone
             has_separator,
+            front_matter: None,
             element_stack: Vec::new(),
+            open_fragments: 0,
+            open_blocks: 0,
         }
     }
 
@@ -36,18 +50,59 @@ impl TreeBuilder {
         self.has_separator
     }
 
+    /// The front-matter block's text, once `build` has read past it
+    pub fn take_front_matter(&mut self) -> Option<String> {
+        self.front_matter.take()
+    }
+
     pub fn build(&mut self) -> ParseResult<Vec<Node>> {
         let mut nodes = Vec::new();
 
         while !self.is_at_end() {
             if let Some(node) = self.parse_node()? {
-                nodes.push(node);
+                Self::push_node(&mut nodes, node);
             }
         }
 
         Ok(nodes)
     }
 
+    /// Append a parsed sibling, applying whitespace control: `{- expr}` strips
+    /// whitespace at the end of the preceding text, `{expr -}` at the start of
+    /// the following text.
+    fn push_node(nodes: &mut Vec<Node>, mut node: Node) {
+        match &mut node {
+            Node::Text(text) if matches!(nodes.last(), Some(Node::Expression(expr)) if expr.trim_right) =>
+            {
+                let content = text
+                    .content
+                    .trim_start_matches(|c: char| c.is_ascii_whitespace());
+                let removed = text.content.len() - content.len();
+                if content.is_empty() {
+                    return;
+                }
+                text.range.start = text.range.start.advanced_over(&text.content[..removed]);
+                text.content = content.to_string();
+            }
+            Node::Expression(expr) if expr.trim_left => {
+                while let Some(Node::Text(text)) = nodes.last_mut() {
+                    let content = text
+                        .content
+                        .trim_end_matches(|c: char| c.is_ascii_whitespace());
+                    if content.is_empty() {
+                        nodes.pop();
+                        continue;
+                    }
+                    text.range.end = text.range.start.advanced_over(content);
+                    text.content.truncate(content.len());
+                    break;
+                }
+            }
+            _ => {}
+        }
+        nodes.push(node);
+    }
+
     /// Multiline opening syntax is formatting, including its child boundary lines.
     fn trim_tag_boundary_whitespace(nodes: &mut Vec<Node>) {
         let leading = nodes
@@ -63,6 +118,33 @@ impl TreeBuilder {
         nodes.truncate(keep);
     }
 
+    /// Strip up to `amount` spaces of indentation from every line, including
+    /// the lines inside nested elements.
+    fn dedent_lines(nodes: &mut [Node], amount: usize) {
+        if amount == 0 {
+            return;
+        }
+        let mut line_start = false;
+        for node in nodes.iter_mut() {
+            match node {
+                Node::Text(text) => {
+                    if line_start && text.content.bytes().all(|b| b == b' ') {
+                        let removed = amount.min(text.content.len());
+                        text.content.drain(..removed);
+                        text.range.start.byte += removed;
+                        text.range.start.col += removed;
+                    }
+                    line_start = text.content.ends_with('\n');
+                }
+                Node::Element(el) => {
+                    Self::dedent_lines(&mut el.children, amount);
+                    line_start = false;
+                }
+                _ => line_start = false,
+            }
+        }
+    }
+
     /// Get a range at the current position (for EOF or current token)
     fn current_range(&self) -> TextRange {
         if let Some(token) = self.peek() {
@@ -108,12 +190,15 @@ impl TreeBuilder {
             // After content → always preserve
             Token::Text { .. }
             | Token::Expression { .. }
+            | Token::EscapedBrace { .. }
             | Token::HtmlElementOpen { .. }
             | Token::HtmlElementClose { .. }
             | Token::ComponentOpen { .. }
             | Token::ComponentClose { .. }
             | Token::SlotOpen { .. }
-            | Token::SlotClose { .. } => true,
+            | Token::SlotClose { .. }
+            | Token::FragmentOpen { .. }
+            | Token::FragmentClose { .. } => true,
             // After another Newline → blank line, preserve
             Token::Newline { .. } => {
                 // But only if we already emitted the previous newline as content.
@@ -157,6 +242,7 @@ impl TreeBuilder {
                     | Token::Text { .. }
                     | Token::ComponentOpen { .. }
                     | Token::SlotOpen { .. }
+                    | Token::FragmentOpen { .. }
             );
             if is_content_token {
                 self.in_header = false;
@@ -205,7 +291,14 @@ impl TreeBuilder {
                 Ok(Some(node))
             }
 
-            Token::Expression { code, range } => {
+            Token::Expression {
+                code,
+                range,
+                raw,
+                force_escape,
+                trim_left,
+                trim_right,
+            } => {
                 // Check if this is a slot reference (tokenizer converts {...} to {children})
                 // Slot names start with "children" (default slot or named slots like children_sidebar)
                 let trimmed = code.trim();
@@ -227,14 +320,32 @@ impl TreeBuilder {
                     self.advance();
                     Ok(Some(node))
                 } else {
-                    let (expr, format_spec, conversion, debug) = Self::parse_expression_parts(code);
+                    let (expr, format_spec, conversion, debug) = parse_expression_parts(code);
+                    // Whitespace between a trim marker and the code
+                    let marker = usize::from(*raw || *force_escape);
+                    let inner = &self.source[range.start.byte + 1 + marker..range.end.byte - 1];
+                    let padding_left = if *trim_left {
+                        inner[1..].len() - inner[1..].trim_start().len()
+                    } else {
+                        0
+                    };
+                    let padding_right = if *trim_right {
+                        let inner = &inner[..inner.len() - 1];
+                        inner.len() - inner.trim_end().len()
+                    } else {
+                        0
+                    };
                     let node = Node::Expression(ExpressionNode {
                         expr,
                         range: *range,
-                        escape: true, // Default to escaping
+                        escape: !raw,
+                        force_escape: *force_escape,
                         format_spec,
                         conversion,
                         debug,
+                        trim_left: *trim_left,
+                        trim_right: *trim_right,
+                        padding: (padding_left, padding_right),
                     });
                     self.advance();
                     Ok(Some(node))
@@ -260,6 +371,12 @@ impl TreeBuilder {
 
                 // Void elements cannot have children or closing tags
                 if !is_self_closing && html::is_void_element(&element_tag) {
+                    if let Some(Token::HtmlElementClose { tag, range }) =
+                        self.tokens.get(self.pos + 1)
+                        && *tag == element_tag
+                    {
+                        return Err(Self::void_close_tag_error(tag, *range));
+                    }
                     let examples: Vec<&str> = ["br", "img", "input", "hr", "meta"]
                         .iter()
                         .copied()
@@ -294,7 +411,10 @@ impl TreeBuilder {
                 } else {
                     self.parse_until_element_close(&element_tag, &element_range)?
                 };
-                if element_range.start.line != element_range.end.line {
+                // Whitespace inside <pre> is content
+                if element_range.start.line != element_range.end.line
+                    && !element_tag.eq_ignore_ascii_case("pre")
+                {
                     Self::trim_tag_boundary_whitespace(&mut children);
                 }
 
@@ -309,11 +429,25 @@ impl TreeBuilder {
                 })))
             }
 
-            Token::HtmlElementClose { .. } => {
-                // Unexpected closing tag at top level - skip it
+            Token::HtmlElementClose { tag, .. } if self.is_open_element(tag) => {
+                // Closes an enclosing element from inside an unclosed block or
+                // slot - skip it so that construct reports itself as unclosed
                 self.advance();
                 Ok(None)
             }
+            Token::HtmlElementClose { tag, range } if html::is_void_element(tag) => {
+                Err(Self::void_close_tag_error(tag, *range))
+            }
+            Token::HtmlElementClose { tag, range } => Err(ParseError::new(
+                ErrorKind::MismatchedCloseTag,
+                format!("</{}> has no opening tag.", tag),
+                *range,
+            )
+            .with_help(format!(
+                "Remove it, or open the element first with <{}>",
+                tag
+            ))
+            .boxed()),
 
             Token::ComponentOpen {
                 name,
@@ -329,6 +463,21 @@ impl TreeBuilder {
                 let is_self_closing = *self_closing;
 
                 self.check_duplicate_attributes(&component_attrs, &component_range)?;
+                if let Some(attr) = attributes.iter().find(|attr| attr.optional) {
+                    return Err(ParseError::new(
+                        ErrorKind::InvalidSyntax,
+                        format!(
+                            "`{}?` is conditional, which only works on HTML elements.",
+                            attr.name
+                        ),
+                        attr.range,
+                    )
+                    .with_help(format!(
+                        "Pass `{}={{...}}` and let <{{{}}}> decide whether to render it.",
+                        attr.name, component_name
+                    ))
+                    .boxed());
+                }
 
                 self.advance();
 
@@ -350,6 +499,8 @@ impl TreeBuilder {
                     attributes: component_attrs,
                     children,
                     slots,
+                    is_async: false,
+                    async_bodies: false,
                     range: component_range,
                     close_range,
                 })))
@@ -372,12 +523,14 @@ impl TreeBuilder {
                 rest,
                 range,
                 rest_range,
+                comment,
             } => {
                 let keyword = keyword.clone();
                 let rest = rest.clone();
                 let range = *range;
                 let rest_range = *rest_range;
-                self.parse_control_flow(&keyword, &rest, &range, &rest_range)
+                let comment = comment.clone();
+                self.parse_control_flow(&keyword, &rest, &range, &rest_range, comment)
             }
 
             Token::PythonStatement { code, range } => {
@@ -392,7 +545,12 @@ impl TreeBuilder {
                     self.advance();
                     Ok(Some(node))
                 } else {
-                    let node = Node::Statement(StatementNode { stmt: code, range });
+                    let inline = self.is_inline_assignment(range);
+                    let node = Node::Statement(StatementNode {
+                        stmt: code,
+                        range,
+                        inline,
+                    });
                     self.advance();
                     Ok(Some(node))
                 }
@@ -428,6 +586,12 @@ impl TreeBuilder {
                 Ok(None)
             }
 
+            Token::FrontMatter { raw, .. } => {
+                self.front_matter = Some(raw.clone());
+                self.advance();
+                Ok(None)
+            }
+
             Token::SlotOpen { name, range } => {
                 let slot_name = name.clone();
                 let slot_range = *range;
@@ -455,6 +619,48 @@ impl TreeBuilder {
                 Ok(None)
             }
 
+            Token::FragmentOpen { range } => {
+                let open_range = *range;
+                self.advance();
+                let (mut children, close_range) = self.parse_until_fragment_close(&open_range)?;
+                if open_range.start.line != close_range.start.line {
+                    // Fragment children join the surrounding text in place of
+                    // the `<>` line, so they shift back to its column
+                    let first_indent = match children.get(1) {
+                        Some(Node::Text(text)) if text.content.trim().is_empty() => {
+                            text.content.len()
+                        }
+                        _ => 0,
+                    };
+                    Self::trim_tag_boundary_whitespace(&mut children);
+                    Self::dedent_lines(
+                        &mut children,
+                        first_indent.saturating_sub(open_range.start.col),
+                    );
+                }
+                Ok(Some(Node::Fragment(FragmentNode {
+                    children,
+                    range: TextRange {
+                        start: open_range.start,
+                        end: close_range.end,
+                    },
+                })))
+            }
+
+            Token::FragmentClose { .. } if self.open_fragments > 0 => {
+                // Closes an enclosing fragment from inside an unclosed
+                // element - skip it so that element reports itself as unclosed
+                self.advance();
+                Ok(None)
+            }
+            Token::FragmentClose { range } => Err(ParseError::new(
+                ErrorKind::MismatchedCloseTag,
+                "</> has no opening <>.",
+                *range,
+            )
+            .with_help("Remove it, or open the fragment first with <>")
+            .boxed()),
+
             Token::EscapedBrace { brace, range } => {
                 // Treat escaped brace as text
                 let node = Node::Text(TextNode {
@@ -470,10 +676,31 @@ impl TreeBuilder {
                 Ok(None)
             }
 
-            Token::End { .. } | Token::ControlContinuation { .. } => {
-                // Unexpected at top level - skip and continue
-                self.advance();
-                Ok(None)
+            Token::End { range } => Err(ParseError::new(
+                ErrorKind::UnexpectedEnd,
+                "`end` has no open block to close.",
+                *range,
+            )
+            .with_help("Remove it, or open a block (such as `if` or `for`) before it.")
+            .boxed()),
+
+            Token::ControlContinuation { keyword, range, .. } => {
+                let opener = match keyword.as_str() {
+                    "elif" => "an `if`",
+                    "except" | "finally" => "a `try`",
+                    "case" => "a `match`",
+                    _ => "an `if`, `for`, `while`, or `try`",
+                };
+                Err(ParseError::new(
+                    ErrorKind::UnexpectedToken,
+                    format!("`{}` has no open block to continue.", keyword),
+                    *range,
+                )
+                .with_help(format!(
+                    "`{}` must follow {} block in the same scope, before its `end`.",
+                    keyword, opener
+                ))
+                .boxed())
             }
         }
     }
@@ -484,16 +711,17 @@ impl TreeBuilder {
         rest: &str,
         range: &TextRange,
         rest_range: &TextRange,
+        comment: Option<String>,
     ) -> ParseResult<Option<Node>> {
         match keyword {
-            "if" => self.parse_if(rest, range, rest_range),
-            "for" => self.parse_for(rest, range, rest_range, false),
-            "async for" => self.parse_for(rest, range, rest_range, true),
-            "while" => self.parse_while(rest, range, rest_range),
-            "match" => self.parse_match(rest, range, rest_range),
-            "with" => self.parse_with(rest, range, rest_range, false),
-            "async with" => self.parse_with(rest, range, rest_range, true),
-            "try" => self.parse_try(range),
+            "if" => self.parse_if(rest, range, rest_range, comment),
+            "for" => self.parse_for(rest, range, rest_range, false, comment),
+            "async for" => self.parse_for(rest, range, rest_range, true, comment),
+            "while" => self.parse_while(rest, range, rest_range, comment),
+            "match" => self.parse_match(rest, range, rest_range, comment),
+            "with" => self.parse_with(rest, range, rest_range, false, comment),
+            "async with" => self.parse_with(rest, range, rest_range, true, comment),
+            "try" => self.parse_try(range, comment),
             "def" | "async def" => self.parse_function(keyword, rest, range),
             "class" => self.parse_class(rest, range),
             _ => Err(ParseError::new(
@@ -510,6 +738,7 @@ impl TreeBuilder {
         condition: &str,
         range: &TextRange,
         rest_range: &TextRange,
+        comment: Option<String>,
     ) -> ParseResult<Option<Node>> {
         let condition_range = *rest_range;
         let if_range = *range;
@@ -519,14 +748,17 @@ impl TreeBuilder {
 
         let mut elif_branches = Vec::new();
         let mut else_branch = None;
+        let mut else_comment = None;
 
         while let Some(Token::ControlContinuation {
             keyword,
             rest,
             range,
             rest_range,
+            comment,
         }) = self.peek()
         {
+            let comment = comment.clone();
             match keyword.as_str() {
                 "elif" => {
                     let elif_cond = rest.clone().unwrap_or_default();
@@ -534,11 +766,12 @@ impl TreeBuilder {
                     let elif_range = rest_range.unwrap_or(*range);
                     self.advance();
                     let elif_body = self.parse_until_block_end()?;
-                    elif_branches.push((elif_cond, elif_range, elif_body));
+                    elif_branches.push((elif_cond, elif_range, elif_body, comment));
                 }
                 "else" => {
                     self.advance();
                     else_branch = Some(self.parse_until_block_end()?);
+                    else_comment = comment;
                     break;
                 }
                 _ => break,
@@ -554,6 +787,8 @@ impl TreeBuilder {
             then_branch,
             elif_branches,
             else_branch,
+            comment,
+            else_comment,
             range: if_range,
         })))
     }
@@ -564,10 +799,10 @@ impl TreeBuilder {
         range: &TextRange,
         rest_range: &TextRange,
         is_async: bool,
+        comment: Option<String>,
     ) -> ParseResult<Option<Node>> {
         // Parse "binding in iterable"
-        let parts: Vec<&str> = rest.splitn(2, " in ").collect();
-        if parts.len() != 2 {
+        let Some(in_pos) = find_for_in(rest) else {
             let keyword = if is_async { "async for" } else { "for" };
             return Err(ParseError::new(
                 ErrorKind::InvalidSyntax,
@@ -576,21 +811,24 @@ impl TreeBuilder {
             )
             .with_help(format!("Syntax: {} x in items:", keyword))
             .boxed());
-        }
+        };
 
-        let binding = parts[0].trim().to_string();
-        let iterable = parts[1].trim().to_string();
+        let binding_text = rest[..in_pos].trim_end();
+        let after_in = &rest[in_pos + "in".len()..];
+        let iterable_text = after_in.trim_start();
+        let binding = binding_text.trim().to_string();
+        let iterable = iterable_text.trim().to_string();
         // Calculate binding range: from rest_range start to end of binding text
         let binding_range = TextRange {
             start: rest_range.start,
             end: Position {
                 line: rest_range.start.line,
-                col: rest_range.start.col + parts[0].len(),
-                byte: rest_range.start.byte + parts[0].len(),
+                col: rest_range.start.col + binding_text.len(),
+                byte: rest_range.start.byte + binding_text.len(),
             },
         };
-        // Calculate iterable range: rest_range start + offset to "in " + "in ".len()
-        let binding_and_in_len = parts[0].len() + " in ".len();
+        // Calculate iterable range: rest_range start + offset past `in` and its spacing
+        let binding_and_in_len = rest.len() - iterable_text.len();
         let iterable_range = TextRange {
             start: Position {
                 line: rest_range.start.line,
@@ -603,6 +841,7 @@ impl TreeBuilder {
 
         self.advance();
         let body = self.parse_until_block_end()?;
+        let (else_branch, else_comment) = self.parse_loop_else()?.unzip();
 
         // Require 'end' token
         let keyword = if is_async { "async for" } else { "for" };
@@ -614,7 +853,10 @@ impl TreeBuilder {
             iterable,
             iterable_range,
             body,
+            else_branch,
             is_async,
+            comment,
+            else_comment: else_comment.flatten(),
             range: for_range,
         })))
     }
@@ -624,12 +866,14 @@ impl TreeBuilder {
         condition: &str,
         range: &TextRange,
         rest_range: &TextRange,
+        comment: Option<String>,
     ) -> ParseResult<Option<Node>> {
         let condition_range = *rest_range;
         let while_range = *range;
 
         self.advance();
         let body = self.parse_until_block_end()?;
+        let (else_branch, else_comment) = self.parse_loop_else()?.unzip();
 
         // Require 'end' token
         self.expect_end("while", &while_range)?;
@@ -638,15 +882,34 @@ impl TreeBuilder {
             condition: condition.to_string(),
             condition_range,
             body,
+            else_branch,
+            comment,
+            else_comment: else_comment.flatten(),
             range: while_range,
         })))
     }
 
+    /// Parse an optional `else:` clause closing a `for` or `while` loop, with
+    /// the trailing comment on its line
+    fn parse_loop_else(&mut self) -> ParseResult<Option<(Vec<Node>, Option<String>)>> {
+        match self.peek() {
+            Some(Token::ControlContinuation {
+                keyword, comment, ..
+            }) if keyword == "else" => {
+                let comment = comment.clone();
+                self.advance();
+                Ok(Some((self.parse_until_block_end()?, comment)))
+            }
+            _ => Ok(None),
+        }
+    }
+
     fn parse_match(
         &mut self,
         expr: &str,
         range: &TextRange,
         rest_range: &TextRange,
+        comment: Option<String>,
     ) -> ParseResult<Option<Node>> {
         let expr_range = *rest_range;
         let match_range = *range;
@@ -662,18 +925,44 @@ impl TreeBuilder {
             rest,
             range,
             rest_range,
+            comment,
         }) = self.peek()
         {
             if keyword == "case" {
+                let comment = comment.clone();
                 let pattern = rest.clone().unwrap_or_default();
                 let pattern_range = rest_range.unwrap_or(*range);
                 let case_range = *range;
+                if pattern.trim().trim_end_matches(':').trim().is_empty() {
+                    return Err(ParseError::new(
+                        ErrorKind::InvalidSyntax,
+                        "This 'case' has no pattern.",
+                        case_range,
+                    )
+                    .with_help("Use 'case _:' to match any value.")
+                    .boxed());
+                }
+                if let Some(catch_all) = cases
+                    .iter()
+                    .find(|case: &&CaseNode| is_irrefutable_pattern(&case.pattern))
+                {
+                    return Err(ParseError::new(
+                        ErrorKind::InvalidSyntax,
+                        "This 'case' can never match.",
+                        case_range,
+                    )
+                    .with_related(catch_all.range)
+                    .with_related_label("this case matches every value")
+                    .with_help("Move the catch-all case last.")
+                    .boxed());
+                }
                 self.advance();
                 let body = self.parse_until_case_end()?;
                 cases.push(CaseNode {
                     pattern,
                     pattern_range,
                     body,
+                    comment,
                     range: case_range,
                 });
 
@@ -687,10 +976,21 @@ impl TreeBuilder {
         // Require 'end' token
         self.expect_end("match", &match_range)?;
 
+        if cases.is_empty() {
+            return Err(ParseError::new(
+                ErrorKind::InvalidSyntax,
+                "This 'match' block has no cases.",
+                match_range,
+            )
+            .with_help("Add a 'case <pattern>:' branch, or 'case _:' to match any value.")
+            .boxed());
+        }
+
         Ok(Some(Node::Match(MatchNode {
             expr: expr.to_string(),
             expr_range,
             cases,
+            comment,
             range: match_range,
         })))
     }
@@ -701,6 +1001,7 @@ impl TreeBuilder {
         range: &TextRange,
         rest_range: &TextRange,
         is_async: bool,
+        comment: Option<String>,
     ) -> ParseResult<Option<Node>> {
         let items_range = *rest_range;
         let with_range = *range;
@@ -717,11 +1018,16 @@ impl TreeBuilder {
             items_range,
             body,
             is_async,
+            comment,
             range: with_range,
         })))
     }
 
-    fn parse_try(&mut self, range: &TextRange) -> ParseResult<Option<Node>> {
+    fn parse_try(
+        &mut self,
+        range: &TextRange,
+        comment: Option<String>,
+    ) -> ParseResult<Option<Node>> {
         let try_range = *range;
 
         self.advance();
@@ -730,35 +1036,58 @@ impl TreeBuilder {
         let mut except_clauses = Vec::new();
         let mut else_clause = None;
         let mut finally_clause = None;
+        let mut else_comment = None;
+        let mut finally_comment = None;
 
         while let Some(Token::ControlContinuation {
             keyword,
             rest,
             range,
             rest_range,
+            comment: clause_comment,
         }) = self.peek()
         {
+            let clause_comment = clause_comment.clone();
             match keyword.as_str() {
                 "except" => {
-                    let exception = rest.clone();
-                    let exception_range = rest_range.or_else(|| rest.as_ref().map(|_| *range));
+                    let (exception, exception_range) = except_target(rest, rest_range);
                     let except_range = *range;
+                    if let Some(bare) = except_clauses
+                        .iter()
+                        .find(|clause: &&ExceptClause| clause.exception.is_none())
+                        && exception.is_some()
+                    {
+                        return Err(ParseError::new(
+                            ErrorKind::InvalidSyntax,
+                            "A bare `except:` must be the last `except` clause.",
+                            except_range,
+                        )
+                        .with_related(bare.range)
+                        .with_related_label("bare `except:` here")
+                        .with_help(
+                            "Move the bare `except:` after this clause, since it catches everything.",
+                        )
+                        .boxed());
+                    }
                     self.advance();
                     let except_body = self.parse_until_block_end()?;
                     except_clauses.push(ExceptClause {
                         exception,
                         exception_range,
                         body: except_body,
+                        comment: clause_comment,
                         range: except_range,
                     });
                 }
                 "else" => {
                     self.advance();
                     else_clause = Some(self.parse_until_block_end()?);
+                    else_comment = clause_comment;
                 }
                 "finally" => {
                     self.advance();
                     finally_clause = Some(self.parse_until_block_end()?);
+                    finally_comment = clause_comment;
                     break;
                 }
                 _ => break,
@@ -773,6 +1102,9 @@ impl TreeBuilder {
             except_clauses,
             else_clause,
             finally_clause,
+            comment,
+            else_comment,
+            finally_comment,
             range: try_range,
         })))
     }
@@ -811,7 +1143,9 @@ impl TreeBuilder {
         let def_range = *range;
 
         self.advance();
-        let body = if self.in_header {
+        // Inside an `end`-terminated block the definition closes with its
+        // own `end`; only top-level header definitions end on dedent.
+        let body = if self.in_header && self.open_blocks == 0 {
             self.parse_header_block_body(def_range.start.col)?
         } else {
             let body = self.parse_until_block_end()?;
@@ -836,7 +1170,9 @@ impl TreeBuilder {
         let class_range = *range;
 
         self.advance();
-        let body = if self.in_header {
+        // Inside an `end`-terminated block the definition closes with its
+        // own `end`; only top-level header definitions end on dedent.
+        let body = if self.in_header && self.open_blocks == 0 {
             self.parse_header_block_body(class_range.start.col)?
         } else {
             let body = self.parse_until_block_end()?;
@@ -891,7 +1227,7 @@ impl TreeBuilder {
                 // Content token within an indented line
                 _ => {
                     if let Some(node) = self.parse_node()? {
-                        nodes.push(node);
+                        Self::push_node(&mut nodes, node);
                     }
                 }
             }
@@ -900,6 +1236,13 @@ impl TreeBuilder {
     }
 
     fn parse_until_block_end(&mut self) -> ParseResult<Vec<Node>> {
+        self.open_blocks += 1;
+        let nodes = self.parse_block_body();
+        self.open_blocks -= 1;
+        nodes
+    }
+
+    fn parse_block_body(&mut self) -> ParseResult<Vec<Node>> {
         let mut nodes = Vec::new();
 
         while !self.is_at_end() {
@@ -907,7 +1250,7 @@ impl TreeBuilder {
                 Some(Token::End { .. }) | Some(Token::ControlContinuation { .. }) => break,
                 _ => {
                     if let Some(node) = self.parse_node()? {
-                        nodes.push(node);
+                        Self::push_node(&mut nodes, node);
                     }
                 }
             }
@@ -917,6 +1260,13 @@ impl TreeBuilder {
     }
 
     fn parse_until_case_end(&mut self) -> ParseResult<Vec<Node>> {
+        self.open_blocks += 1;
+        let nodes = self.parse_case_body();
+        self.open_blocks -= 1;
+        nodes
+    }
+
+    fn parse_case_body(&mut self) -> ParseResult<Vec<Node>> {
         let mut nodes = Vec::new();
 
         while !self.is_at_end() {
@@ -930,7 +1280,7 @@ impl TreeBuilder {
             }
 
             if let Some(node) = self.parse_node()? {
-                nodes.push(node);
+                Self::push_node(&mut nodes, node);
             }
         }
 
@@ -942,7 +1292,10 @@ impl TreeBuilder {
         tag: &str,
         open_range: &TextRange,
     ) -> ParseResult<(Vec<Node>, Option<TextRange>)> {
-        self.element_stack.push(tag.to_string());
+        self.element_stack.push(OpenElement {
+            tag: Some(tag.to_string()),
+            range: *open_range,
+        });
         let mut nodes = Vec::new();
 
         while !self.is_at_end() {
@@ -957,9 +1310,35 @@ impl TreeBuilder {
                     self.element_stack.pop();
                     return Ok((nodes, Some(close_range)));
                 }
+                Some(Token::HtmlElementClose {
+                    tag: close_tag,
+                    range: close_range,
+                }) => {
+                    // Closing an enclosing element means this one was left open;
+                    // anything else is a typo for this element's closing tag.
+                    let closes_ancestor = self.element_stack[..self.element_stack.len() - 1]
+                        .iter()
+                        .any(|open| open.tag.as_ref() == Some(close_tag));
+                    let error = if closes_ancestor {
+                        ParseError::new(
+                            ErrorKind::UnclosedElement,
+                            format!("<{}> is never closed.", tag),
+                            *close_range,
+                        )
+                        .with_help(format!("Close with </{}> before </{}>", tag, close_tag))
+                    } else {
+                        ParseError::new(
+                            ErrorKind::MismatchedCloseTag,
+                            format!("</{}> does not match <{}>.", close_tag, tag),
+                            *close_range,
+                        )
+                        .with_help(format!("Did you mean </{}>?", tag))
+                    };
+                    return Err(error.with_related(*open_range).boxed());
+                }
                 _ => {
                     if let Some(node) = self.parse_node()? {
-                        nodes.push(node);
+                        Self::push_node(&mut nodes, node);
                     }
                 }
             }
@@ -984,6 +1363,10 @@ impl TreeBuilder {
         let mut children = Vec::new();
         // Caller slot syntax binds after parsing, in the component-slot plugin.
         let slots = HashMap::new();
+        self.element_stack.push(OpenElement {
+            tag: None,
+            range: *open_range,
+        });
 
         while !self.is_at_end() {
             match self.peek() {
@@ -994,11 +1377,12 @@ impl TreeBuilder {
                 }) if close_name == name => {
                     let close_range = *close_range;
                     self.advance();
+                    self.element_stack.pop();
                     return Ok((children, slots, Some(close_range)));
                 }
                 _ => {
                     if let Some(node) = self.parse_node()? {
-                        children.push(node);
+                        Self::push_node(&mut children, node);
                     }
                 }
             }
@@ -1020,6 +1404,10 @@ impl TreeBuilder {
         open_range: &TextRange,
     ) -> ParseResult<(Vec<Node>, Option<TextRange>)> {
         let mut nodes = Vec::new();
+        self.element_stack.push(OpenElement {
+            tag: None,
+            range: *open_range,
+        });
 
         while !self.is_at_end() {
             match self.peek() {
@@ -1030,11 +1418,12 @@ impl TreeBuilder {
                 }) if close_name == name => {
                     let close_range = *close_range;
                     self.advance();
+                    self.element_stack.pop();
                     return Ok((nodes, Some(close_range)));
                 }
                 _ => {
                     if let Some(node) = self.parse_node()? {
-                        nodes.push(node);
+                        Self::push_node(&mut nodes, node);
                     }
                 }
             }
@@ -1054,6 +1443,40 @@ impl TreeBuilder {
         .boxed())
     }
 
+    fn parse_until_fragment_close(
+        &mut self,
+        open_range: &TextRange,
+    ) -> ParseResult<(Vec<Node>, TextRange)> {
+        let mut nodes = Vec::new();
+        self.open_fragments += 1;
+
+        while !self.is_at_end() {
+            match self.peek() {
+                Some(Token::FragmentClose { range }) => {
+                    let close_range = *range;
+                    self.advance();
+                    self.open_fragments -= 1;
+                    return Ok((nodes, close_range));
+                }
+                _ => {
+                    if let Some(node) = self.parse_node()? {
+                        Self::push_node(&mut nodes, node);
+                    }
+                }
+            }
+        }
+
+        self.open_fragments -= 1;
+        Err(ParseError::new(
+            ErrorKind::UnclosedElement,
+            "<> is never closed.",
+            self.current_range(),
+        )
+        .with_related(*open_range)
+        .with_help("Close with </>")
+        .boxed())
+    }
+
     fn convert_attributes(&self, token_attrs: &[super::tokenizer::Attribute]) -> Vec<Attribute> {
         token_attrs
             .iter()
@@ -1062,10 +1485,17 @@ impl TreeBuilder {
 
                 let kind = match &attr.value {
                     AttributeValue::String(s) => {
-                        // Check if the string contains unescaped expressions like {expr}
-                        // (ignoring {{ and }} which are escaped braces)
-                        let without_escaped = s.replace("{{", "").replace("}}", "");
-                        if without_escaped.contains('{') && without_escaped.contains('}') {
+                        // A value with an {expr} is a template; otherwise it's
+                        // static, with {{ and }} unescaped
+                        let mut text = String::new();
+                        let mut is_template = false;
+                        for part in template_parts(s) {
+                            match part {
+                                TemplatePart::Text(part) => text.push_str(&part),
+                                TemplatePart::Expression { .. } => is_template = true,
+                            }
+                        }
+                        if is_template {
                             AttributeKind::Template {
                                 name: attr.name.clone(),
                                 value: s.clone(),
@@ -1073,15 +1503,40 @@ impl TreeBuilder {
                         } else {
                             AttributeKind::Static {
                                 name: attr.name.clone(),
-                                value: s.clone(),
+                                value: text,
                             }
                         }
                     }
-                    AttributeValue::Expression(code, range) => AttributeKind::Expression {
-                        name: attr.name.clone(),
-                        expr: code.clone(),
-                        expr_range: *range,
-                    },
+                    AttributeValue::Expression(code, range) => {
+                        // `{!expr}` opts out of escaping and `{&expr}` in, as
+                        // in content
+                        let (expr, escape, force_escape) = match code.strip_prefix('!') {
+                            Some(rest) => (rest, false, false),
+                            None => match code.strip_prefix('&') {
+                                Some(rest) => (rest, true, true),
+                                None => (code.as_str(), true, false),
+                            },
+                        };
+                        let mut expr_range = *range;
+                        if !escape || force_escape {
+                            expr_range.start.byte += 1;
+                            expr_range.start.col += 1;
+                        }
+                        // The token's range sits just inside the braces
+                        let mut brace_range = *range;
+                        brace_range.start.byte -= 1;
+                        brace_range.start.col -= 1;
+                        brace_range.end = brace_range.end.advanced_over("}");
+                        AttributeKind::Expression {
+                            name: attr.name.clone(),
+                            expr: expr.to_string(),
+                            expr_range,
+                            brace_range,
+                            escape,
+                            force_escape,
+                            optional: attr.optional,
+                        }
+                    }
                     AttributeValue::Bool => AttributeKind::Boolean {
                         name: attr.name.clone(),
                     },
@@ -1109,37 +1564,98 @@ impl TreeBuilder {
             .collect()
     }
 
+    /// Reject element nesting that browsers silently repair into a different
+    /// tree than the template shows:
+    ///
+    /// - block elements (`<div>`, `<ul>`, ...) directly inside `<p>`;
+    /// - `<li>` directly inside anything but `<ul>`, `<ol>` or `<menu>`;
+    /// - `<td>` and `<th>` directly inside anything but `<tr>`;
+    /// - `<a>` or `<button>` anywhere inside another `<a>` or `<button>`.
+    ///
+    /// Only elements in this file count: at the top level or directly inside
+    /// a component or slot, the real parent isn't known, so anything goes.
+    /// Errors point at the child, with the offending ancestor as related.
     fn check_nesting(&self, child_tag: &str, child_range: &TextRange) -> ParseResult<()> {
-        if let Some(parent) = self.element_stack.last() {
+        // `<template>` content is inert and browsers don't move it, so like
+        // components and slots it hides the elements around it
+        let ancestors = || {
+            self.element_stack
+                .iter()
+                .rev()
+                .map_while(|open| Some((open.tag.as_deref()?, open.range)))
+                .take_while(|(tag, _)| !tag.eq_ignore_ascii_case("template"))
+        };
+        let nesting_error = |parent: &str, parent_range: TextRange| {
+            ParseError::new(
+                ErrorKind::InvalidNesting,
+                format!("<{}> cannot appear inside <{}>.", child_tag, parent),
+                *child_range,
+            )
+            .with_related(parent_range)
+        };
+
+        if let Some((parent, parent_range)) = ancestors().next() {
             // Block elements cannot appear inside <p>
             if html::is_auto_close_element(parent) && html::is_block_element(child_tag) {
-                return Err(ParseError::new(
-                    ErrorKind::InvalidNesting,
-                    format!("<{}> cannot appear inside <{}>.", child_tag, parent),
-                    *child_range,
-                )
-                .with_help(format!(
-                    "Browsers silently close <{}> when they encounter <{}>, so this renders\n\
-                     as <{0}></{0}><{1}>...</{1}> — probably not what you want.",
-                    parent, child_tag
-                ))
-                .boxed());
+                return Err(nesting_error(parent, parent_range)
+                    .with_help(format!(
+                        "Browsers silently close <{}> when they encounter <{}>, so this renders\n\
+                         as <{0}></{0}><{1}>...</{1}> — probably not what you want.",
+                        parent, child_tag
+                    ))
+                    .boxed());
             }
 
-            // Interactive elements cannot nest inside themselves
-            if html::is_interactive_element(parent) && html::is_interactive_element(child_tag) {
-                return Err(ParseError::new(
-                    ErrorKind::InvalidNesting,
-                    format!("<{}> cannot appear inside <{}>.", child_tag, parent),
-                    *child_range,
-                )
+            // List items and table cells need their own parent
+            if let Some(parents) = html::required_parents(child_tag)
+                && !parents.contains(&parent.to_ascii_lowercase().as_str())
+            {
+                let mut expected = format!("<{}>", parents[0]);
+                for (i, p) in parents.iter().enumerate().skip(1) {
+                    let sep = if i + 1 == parents.len() { " or " } else { ", " };
+                    expected.push_str(&format!("{}<{}>", sep, p));
+                }
+                return Err(nesting_error(parent, parent_range)
+                    .with_help(format!(
+                        "<{}> belongs directly inside {}; browsers move it elsewhere.",
+                        child_tag, expected
+                    ))
+                    .boxed());
+            }
+        }
+
+        // Interactive elements cannot nest inside each other, at any depth
+        if html::is_interactive_element(child_tag)
+            && let Some((parent, parent_range)) =
+                ancestors().find(|(tag, _)| html::is_interactive_element(tag))
+        {
+            return Err(nesting_error(parent, parent_range)
                 .with_help("Nesting clickable elements is invalid HTML and causes unpredictable behavior across browsers.")
                 .boxed());
-            }
         }
         Ok(())
     }
 
+    /// `</br>` and friends: void elements never take a closing tag
+    fn void_close_tag_error(tag: &str, range: TextRange) -> Box<ParseError> {
+        ParseError::new(
+            ErrorKind::VoidElementCloseTag,
+            format!("<{tag}> is a void element and doesn't take a closing tag."),
+            range,
+        )
+        .with_help(format!(
+            "Remove </{tag}> and write the element as <{tag} />."
+        ))
+        .boxed()
+    }
+
+    /// Whether an element named `tag` is open around the current position
+    fn is_open_element(&self, tag: &str) -> bool {
+        self.element_stack
+            .iter()
+            .any(|open| open.tag.as_deref() == Some(tag))
+    }
+
     fn check_duplicate_attributes(
         &self,
         attrs: &[Attribute],
@@ -1202,152 +1718,6 @@ impl TreeBuilder {
         }
     }
 
-    /// Parse an expression string into (expr, format_spec, conversion, debug).
-    /// Handles `{count:03d}`, `{items!r}`, `{value=}` syntax.
-    fn parse_expression_parts(code: &str) -> (String, Option<String>, Option<char>, bool) {
-        let mut expr = code.to_string();
-        let mut format_spec = None;
-        let mut conversion = None;
-        let mut debug = false;
-
-        // 1. Check for debug format: trailing `=` (but not ==, !=, <=, >=)
-        let trimmed = expr.trim_end();
-        if trimmed.ends_with('=')
-            && !trimmed.ends_with("==")
-            && !trimmed.ends_with("!=")
-            && !trimmed.ends_with("<=")
-            && !trimmed.ends_with(">=")
-        {
-            debug = true;
-            expr = trimmed[..trimmed.len() - 1].to_string();
-            // Debug format skips escape and uses Python's = format directly
-            return (expr, format_spec, conversion, debug);
-        }
-
-        // 2. Check for conversion flag: !r, !s, !a at end (at depth 0)
-        //    Must be at the very end of the expression
-        let trimmed = expr.trim_end();
-        if trimmed.len() >= 2 {
-            let last_two = &trimmed[trimmed.len() - 2..];
-            if matches!(last_two, "!r" | "!s" | "!a") {
-                // Verify the '!' is at depth 0
-                let bang_pos = trimmed.len() - 2;
-                if Self::depth_at_position(trimmed, bang_pos) == 0 {
-                    conversion = Some(trimmed.as_bytes()[trimmed.len() - 1] as char);
-                    expr = trimmed[..trimmed.len() - 2].to_string();
-                    return (expr, format_spec, conversion, debug);
-                }
-            }
-        }
-
-        // 3. Check for format spec: `:` at depth 0 (scanning from right)
-        //    Must not be inside brackets, parens, strings, or dict literals
-        if let Some(colon_pos) = Self::find_format_colon(&expr) {
-            format_spec = Some(expr[colon_pos + 1..].trim_end().to_string());
-            expr = expr[..colon_pos].to_string();
-        }
-
-        (expr, format_spec, conversion, debug)
-    }
-
-    /// Calculate nesting depth at a given byte position in an expression.
-    fn depth_at_position(expr: &str, target: usize) -> usize {
-        let mut depth: usize = 0;
-        let mut in_string = false;
-        let mut string_char = ' ';
-        let bytes = expr.as_bytes();
-        let mut i = 0;
-
-        while i < target && i < bytes.len() {
-            let ch = bytes[i] as char;
-            if in_string {
-                if ch == '\\' {
-                    i += 1; // skip escaped char
-                } else if ch == string_char {
-                    in_string = false;
-                }
-            } else {
-                match ch {
-                    '"' | '\'' => {
-                        in_string = true;
-                        string_char = ch;
-                    }
-                    '(' | '[' | '{' => {
-                        depth += 1;
-                    }
-                    ')' | ']' | '}' => {
-                        depth = depth.saturating_sub(1);
-                    }
-                    _ => {}
-                }
-            }
-            i += 1;
-        }
-        depth
-    }
-
-    /// Find the position of the format spec colon (`:` at depth 0).
-    /// Scans from right to left to find the last `:` at depth 0.
-    /// Returns None if no format spec colon is found.
-    fn find_format_colon(expr: &str) -> Option<usize> {
-        // Scan left-to-right tracking depth, record the last `:` at depth 0
-        let mut depth: usize = 0;
-        let mut in_string = false;
-        let mut string_char = ' ';
-        let mut last_colon_at_depth_0 = None;
-        let bytes = expr.as_bytes();
-        let mut i = 0;
-
-        while i < bytes.len() {
-            let ch = bytes[i] as char;
-            if in_string {
-                if ch == '\\' {
-                    i += 2; // skip escaped char
-                    continue;
-                }
-                if ch == string_char {
-                    in_string = false;
-                }
-            } else {
-                match ch {
-                    '"' | '\'' => {
-                        in_string = true;
-                        string_char = ch;
-                    }
-                    '(' | '[' | '{' => {
-                        depth += 1;
-                    }
-                    ')' | ']' | '}' => {
-                        depth = depth.saturating_sub(1);
-                    }
-                    ':' if depth == 0 => {
-                        last_colon_at_depth_0 = Some(i);
-                    }
-                    _ => {}
-                }
-            }
-            i += 1;
-        }
-
-        // Validate: the part after `:` should look like a format spec
-        // (not like a dict value or slice). Format specs are typically short
-        // and contain format characters like d, f, s, >, <, ^, 0, etc.
-        if let Some(pos) = last_colon_at_depth_0 {
-            let after = expr[pos + 1..].trim();
-            // Reject if empty or if it looks like a dict/ternary (contains spaces with keywords)
-            if after.is_empty() {
-                return None;
-            }
-            // Simple heuristic: format specs don't contain unquoted spaces or start with keywords
-            // Format specs: "03d", ".2f", ">20", "#x", ",", "+.2f"
-            if !after.contains(' ') && !after.contains('\t') {
-                return Some(pos);
-            }
-        }
-
-        None
-    }
-
     fn is_parameter_declaration(&self, code: &str) -> bool {
         let trimmed = code.trim();
 
@@ -1375,9 +1745,12 @@ impl TreeBuilder {
         }
 
         // Check if ":" comes before "=" (parameter with default)
-        // or if there's no "=" at all (parameter without default)
+        // or if there's no "=" at all (parameter without default).
+        // A walrus `:=` is an expression, not an annotation.
         if let Some(colon_pos) = code.find(':') {
-            if let Some(equals_pos) = code.find('=') {
+            if code[colon_pos + 1..].starts_with('=') {
+                false
+            } else if let Some(equals_pos) = code.find('=') {
                 colon_pos < equals_pos
             } else {
                 true
@@ -1392,6 +1765,20 @@ impl TreeBuilder {
         trimmed.starts_with("import ") || trimmed.starts_with("from ")
     }
 
+    /// Whether the statement at `range` is a `{ name = expr }` sharing its
+    /// line with other content, like `<p>Hi { n = len(xs) } there</p>`
+    fn is_inline_assignment(&self, range: TextRange) -> bool {
+        let Some(before) = self.source[..range.start.byte].trim_end().strip_suffix('{') else {
+            return false;
+        };
+        let Some(after) = self.source[range.end.byte..].trim_start().strip_prefix('}') else {
+            return false;
+        };
+        let line_before = before.rsplit('\n').next().unwrap_or_default();
+        let line_after = after.split('\n').next().unwrap_or_default();
+        !line_before.trim().is_empty() || !line_after.trim().is_empty()
+    }
+
     fn parse_parameter(&mut self, code: &str, range: &TextRange) -> ParseResult<Option<Node>> {
         // Parse "name: type", "name: type = default", or "**kwargs"
         let parts: Vec<&str> = code.splitn(2, ':').collect();
@@ -1416,12 +1803,11 @@ impl TreeBuilder {
                 .boxed());
             }
 
-            if rest.contains('=') {
-                let eq_parts: Vec<&str> = rest.splitn(2, '=').collect();
+            if let Some(equals_pos) = find_top_level_assignment(rest) {
                 (
                     name,
-                    Some(eq_parts[0].trim().to_string()),
-                    Some(eq_parts[1].trim().to_string()),
+                    Some(rest[..equals_pos].trim().to_string()),
+                    Some(rest[equals_pos + 1..].trim().to_string()),
                 )
             } else {
                 (name, Some(rest.to_string()), None)
@@ -1434,6 +1820,7 @@ impl TreeBuilder {
             let node = Node::Statement(StatementNode {
                 stmt: code.to_string(),
                 range: *range,
+                inline: false,
             });
             self.advance();
             return Ok(Some(node));
@@ -1458,3 +1845,48 @@ impl TreeBuilder {
         Ok(Some(node))
     }
 }
+
+/// Exception types (and any `as` binding) of an `except` line, without the
+/// trailing colon, so the injected range covers exactly what the editor sees
+/// as Python: `(ValueError, KeyError) as e`.
+fn except_target(
+    rest: &Option<String>,
+    rest_range: &Option<TextRange>,
+) -> (Option<String>, Option<TextRange>) {
+    let Some(rest) = rest else {
+        return (None, None);
+    };
+    let target = rest.trim_end().trim_end_matches(':').trim_end();
+    if target.is_empty() {
+        return (None, None);
+    }
+    let trimmed = rest.len() - target.len();
+    let range = rest_range.map(|range| TextRange {
+        start: range.start,
+        end: Position {
+            line: range.end.line,
+            col: range.end.col - trimmed,
+            byte: range.end.byte - trimmed,
+        },
+    });
+    (Some(target.to_string()), range)
+}
+
+/// Whether a `case` pattern matches every value: `_`, a bare capture name or
+/// an alternative with one of those, as in `case 0 | _:`. A guard makes any
+/// pattern refutable.
+fn is_irrefutable_pattern(pattern: &str) -> bool {
+    let pattern = pattern.trim().trim_end_matches(':');
+    if pattern.contains(" if ") {
+        return false;
+    }
+    pattern.split('|').any(|alternative| {
+        let alternative = alternative.trim();
+        let is_name = alternative
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_')
+            && alternative.chars().all(|c| c.is_alphanumeric() || c == '_');
+        is_name && !matches!(alternative, "None" | "True" | "False")
+    })
+}
@@ -1,8 +1,16 @@
 use super::{Flow, Plugin, walk};
-use crate::ast::{Function, Node};
-use crate::error::CompileError;
+use crate::ast::{Ast, Attribute, AttributeKind, Function, Node};
+use crate::error::{CompileError, ErrorKind, ParseError};
+use std::collections::HashSet;
 
-/// Marks the function async when the template awaits anything.
+/// Marks the function async when the template awaits anything. A function
+/// declared `async component` stays async either way.
+///
+/// Only code that runs in the function itself counts: the body, control-flow
+/// bodies, and element or component attributes. A nested `def` and a
+/// component's children (compiled into their own generator) don't make the
+/// enclosing function async; awaiting in the children makes that generator
+/// async instead.
 #[derive(Default)]
 pub struct Async {
     is_async: bool,
@@ -12,7 +20,7 @@ impl Plugin for Async {
     fn run(&mut self, function: &mut Function) -> Result<(), CompileError> {
         walk(&mut function.params, self)?;
         walk(&mut function.body, self)?;
-        function.is_async = self.is_async;
+        function.is_async |= self.is_async;
         Ok(())
     }
 
@@ -27,8 +35,136 @@ impl Plugin for Async {
                 self.is_async = true
             }
             Node::If(if_node) if if_node.condition.contains("await ") => self.is_async = true,
+            Node::Element(el) if awaits_in_attributes(&el.attributes) => self.is_async = true,
+            Node::Component(c) => {
+                self.is_async |= awaits_in_attributes(&c.attributes);
+                let mut bodies = Async::default();
+                walk(&mut c.children, &mut bodies)?;
+                for slot in c.slots.values_mut() {
+                    walk(slot, &mut bodies)?;
+                }
+                c.async_bodies |= bodies.is_async;
+                return Ok(Flow::SkipChildren);
+            }
+            Node::Definition(_) => return Ok(Flow::SkipChildren),
             _ => {}
         }
         Ok(Flow::Continue)
     }
 }
+
+fn awaits_in_attributes(attributes: &[Attribute]) -> bool {
+    attributes.iter().any(|attr| match &attr.kind {
+        AttributeKind::Expression { expr, .. } | AttributeKind::Spread { expr, .. } => {
+            expr.contains("await ")
+        }
+        AttributeKind::Template { value, .. } => value.contains("await "),
+        _ => false,
+    })
+}
+
+/// Mark calls to async components, and make their callers async in turn.
+///
+/// A call is async when it names a component this file defines as async, or
+/// carries an `async` attribute for one defined elsewhere. Repeats until no
+/// new component turns async, since each one may make its own callers async.
+///
+/// An async component reads its content with `async for`, so content passed
+/// to one is always async. Content that has to be async for a component that
+/// isn't is an error.
+pub fn propagate_async_calls(ast: &mut Ast) -> Result<(), CompileError> {
+    let mut names: HashSet<String> = ast
+        .definitions
+        .iter()
+        .filter(|d| d.function.is_async)
+        .map(|d| d.name.clone())
+        .collect();
+
+    loop {
+        let mut newly_async = Vec::new();
+        let functions = ast
+            .definitions
+            .iter_mut()
+            .map(|d| (Some(&d.name), &mut d.function))
+            .chain(std::iter::once((None, &mut ast.function)));
+        for (name, function) in functions {
+            let mut calls = AsyncCalls::new(&names);
+            // Inspect-and-mark only, so it never fails
+            let _ = calls.run(function);
+            if calls.calls_async && !function.is_async {
+                function.is_async = true;
+                newly_async.extend(name.cloned());
+            }
+        }
+        if newly_async.is_empty() {
+            break;
+        }
+        names.extend(newly_async);
+    }
+
+    for definition in &mut ast.definitions {
+        SyncContent.run(&mut definition.function)?;
+    }
+    SyncContent.run(&mut ast.function)
+}
+
+struct AsyncCalls<'a> {
+    names: &'a HashSet<String>,
+    calls_async: bool,
+}
+
+impl<'a> AsyncCalls<'a> {
+    fn new(names: &'a HashSet<String>) -> Self {
+        Self {
+            names,
+            calls_async: false,
+        }
+    }
+}
+
+impl Plugin for AsyncCalls<'_> {
+    fn enter(&mut self, node: &mut Node) -> Result<Flow, CompileError> {
+        match node {
+            Node::Component(c) => {
+                let marked = c.attributes.len();
+                c.attributes
+                    .retain(|attr| !matches!(&attr.kind, AttributeKind::Boolean { name } if name == "async"));
+                c.is_async |= c.attributes.len() != marked || self.names.contains(&c.name);
+                self.calls_async |= c.is_async;
+
+                // Children and slot fills compile to their own generators
+                let mut bodies = AsyncCalls::new(self.names);
+                walk(&mut c.children, &mut bodies)?;
+                for slot in c.slots.values_mut() {
+                    walk(slot, &mut bodies)?;
+                }
+                c.async_bodies |= c.is_async || bodies.calls_async;
+                Ok(Flow::SkipChildren)
+            }
+            Node::Definition(_) => Ok(Flow::SkipChildren),
+            _ => Ok(Flow::Continue),
+        }
+    }
+}
+
+/// Rejects async content passed to a component that iterates it with `for`
+struct SyncContent;
+
+impl Plugin for SyncContent {
+    fn enter(&mut self, node: &mut Node) -> Result<Flow, CompileError> {
+        match node {
+            Node::Component(c) if c.async_bodies && !c.is_async => Err(ParseError::new(
+                ErrorKind::InvalidSyntax,
+                format!("Content passed to `{}` is async, but `{}` isn't.", c.name, c.name),
+                c.range,
+            )
+            .with_help(format!(
+                "Mark the call `<{{{}}} async>` if `{}` is async, or move the awaits and async calls out of its content.",
+                c.name, c.name
+            ))
+            .boxed()
+            .into()),
+            _ => Ok(Flow::Continue),
+        }
+    }
+}
@@ -45,7 +45,7 @@ fn bind_slots(component: &mut ComponentNode) -> Result<(), CompileError> {
             validate_slot_name(&name, range)?;
             if let Some(first_range) = ranges.get(&name) {
                 return Err(ParseError::new(
-                    ErrorKind::InvalidSyntax,
+                    ErrorKind::DuplicateSlot,
                     format!("The `{name}` slot is filled more than once."),
                     range,
                 )
@@ -4,14 +4,42 @@ use crate::ast::{
     ParameterNode, Position, TextRange,
 };
 use crate::error::{CompileError, ErrorKind, ParseError};
+use crate::generate::DEFAULT_DECORATOR;
+use crate::parse::with_python_parser;
 
-#[derive(Default)]
 pub struct Components {
+    /// Decorator name for component functions; `None` leaves them undecorated.
+    decorator: Option<String>,
     children: Vec<Vec<String>>,
     definitions: Vec<FunctionDefinition>,
 }
 
 impl Components {
+    pub fn new(decorator: Option<&str>) -> Self {
+        Self {
+            decorator: decorator.map(str::to_string),
+            children: Vec::new(),
+            definitions: Vec::new(),
+        }
+    }
+
+    /// `@component`, listing nested components as `subcomponents`. Custom
+    /// decorators get no arguments.
+    fn decorators(&self, children: &[String]) -> Vec<DecoratorNode> {
+        let Some(name) = &self.decorator else {
+            return Vec::new();
+        };
+        let decorator = if children.is_empty() || name != DEFAULT_DECORATOR {
+            format!("@{name}")
+        } else {
+            format!("@{name}(subcomponents=[{}])", children.join(", "))
+        };
+        vec![DecoratorNode {
+            decorator,
+            range: TextRange::synthetic(),
+        }]
+    }
+
     pub fn into_definitions(self) -> Vec<FunctionDefinition> {
         self.definitions
     }
@@ -22,7 +50,7 @@ impl Plugin for Components {
         self.children.push(Vec::new());
         walk(&mut function.body, self)?;
         let children = self.children.pop().expect("root component scope");
-        function.decorators.push(component_decorator(&children));
+        function.decorators.extend(self.decorators(&children));
         Ok(())
     }
 
@@ -55,7 +83,7 @@ impl Plugin for Components {
                 is_async,
                 params: params.into_iter().map(Node::Parameter).collect(),
                 imports: Vec::new(),
-                decorators: vec![component_decorator(&children)],
+                decorators: self.decorators(&children),
                 header_comments: Vec::new(),
                 body: std::mem::take(&mut definition.body),
             },
@@ -74,18 +102,6 @@ impl Plugin for Components {
     }
 }
 
-fn component_decorator(children: &[String]) -> DecoratorNode {
-    let decorator = if children.is_empty() {
-        "@component".to_string()
-    } else {
-        format!("@component(subcomponents=[{}])", children.join(", "))
-    };
-    DecoratorNode {
-        decorator,
-        range: TextRange::synthetic(),
-    }
-}
-
 fn parse_signature(
     definition: &crate::ast::DefinitionNode,
 ) -> Result<(String, TextRange, Vec<ParameterNode>, bool), CompileError> {
@@ -99,12 +115,7 @@ fn parse_signature(
     };
 
     let source = format!("{python}\n    pass");
-    let mut parser = tree_sitter::Parser::new();
-    parser
-        .set_language(&tree_sitter_python::LANGUAGE.into())
-        .expect("tree-sitter Python language");
-    let tree = parser
-        .parse(&source, None)
+    let tree = with_python_parser(|parser| parser.parse(&source, None))
         .expect("tree-sitter returned no tree");
     let root = tree.root_node();
     let function = root
@@ -287,19 +298,5 @@ fn mapped_range(
 }
 
 fn position_at(base: Position, source: &str, offset: usize) -> Position {
-    let mut line = base.line;
-    let mut col = base.col;
-    for ch in source[..offset].chars() {
-        if ch == '\n' {
-            line += 1;
-            col = 0;
-        } else {
-            col += 1;
-        }
-    }
-    Position {
-        byte: base.byte + offset,
-        line,
-        col,
-    }
+    base.advanced_over(&source[..offset])
 }
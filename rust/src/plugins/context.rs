@@ -6,6 +6,7 @@ pub enum Helper {
     RenderClass,
     RenderStyle,
     RenderAttr,
+    RenderOptionalAttr,
     RenderData,
     RenderAria,
     SpreadAttrs,
@@ -20,6 +21,7 @@ impl Helper {
             Helper::RenderClass => "render_class",
             Helper::RenderStyle => "render_style",
             Helper::RenderAttr => "render_attr",
+            Helper::RenderOptionalAttr => "render_optional_attr",
             Helper::RenderData => "render_data",
             Helper::RenderAria => "render_aria",
             Helper::SpreadAttrs => "spread_attrs",
@@ -33,6 +35,7 @@ impl Helper {
         Helper::RenderClass,
         Helper::RenderStyle,
         Helper::RenderAttr,
+        Helper::RenderOptionalAttr,
         Helper::RenderData,
         Helper::RenderAria,
         Helper::SpreadAttrs,
@@ -0,0 +1,44 @@
+use super::{Flow, Plugin};
+use crate::ast::Node;
+use crate::error::{CompileError, Warning};
+
+/// Warns when a component opened with `<{X}>` closes without children or slot
+/// fills, e.g. `<{X}></{X}>`, which reads as a forgotten body. It passes empty
+/// content, which skips the component's `{...}` fallback, where `<{X} />`
+/// passes none.
+///
+/// Inspect only. Whitespace doesn't count as children; a comment does.
+#[derive(Default)]
+pub struct EmptyComponents {
+    warnings: Vec<Warning>,
+}
+
+impl EmptyComponents {
+    pub fn into_warnings(self) -> Vec<Warning> {
+        self.warnings
+    }
+}
+
+impl Plugin for EmptyComponents {
+    fn enter(&mut self, node: &mut Node) -> Result<Flow, CompileError> {
+        let Node::Component(component) = node else {
+            return Ok(Flow::Continue);
+        };
+        let is_empty = component.close_range.is_some()
+            && component.slots.is_empty()
+            && component
+                .children
+                .iter()
+                .all(|child| matches!(child, Node::Text(text) if text.content.trim().is_empty()));
+        if is_empty {
+            self.warnings.push(Warning {
+                message: format!(
+                    "<{{{name}}}> has no children, so it passes empty content; write <{{{name}}} /> to pass none.",
+                    name = component.name
+                ),
+                range: component.range,
+            });
+        }
+        Ok(Flow::Continue)
+    }
+}
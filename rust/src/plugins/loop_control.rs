@@ -0,0 +1,105 @@
+use super::{Flow, Plugin, walk};
+use crate::ast::{Node, StatementNode};
+use crate::error::{CompileError, ErrorKind, ParseError};
+
+/// Rejects `break` and `continue` outside a `for` or `while` body, which
+/// Python only reports when the generated module is imported.
+///
+/// A loop's `else` branch isn't part of its body. Nested `def`s and content
+/// passed to a component become functions of their own, so a loop around
+/// them doesn't count.
+#[derive(Default)]
+pub struct LoopControl {
+    /// Loops enclosing the current node in this function
+    depth: usize,
+    /// Whether a function boundary sits between the node and a loop
+    loop_outside_function: bool,
+}
+
+impl LoopControl {
+    /// Walk `nodes` as the body of a nested function
+    fn walk_function(&mut self, nodes: &mut [Node]) -> Result<(), CompileError> {
+        let outer = (self.depth, self.loop_outside_function);
+        self.loop_outside_function |= self.depth > 0;
+        self.depth = 0;
+        walk(nodes, self)?;
+        (self.depth, self.loop_outside_function) = outer;
+        Ok(())
+    }
+
+    /// Walk a loop's body one level deeper, and its `else` branch at this level
+    fn walk_loop(
+        &mut self,
+        body: &mut [Node],
+        else_branch: Option<&mut Vec<Node>>,
+    ) -> Result<(), CompileError> {
+        self.depth += 1;
+        walk(body, self)?;
+        self.depth -= 1;
+        if let Some(else_branch) = else_branch {
+            walk(else_branch, self)?;
+        }
+        Ok(())
+    }
+
+    fn check(&self, statement: &StatementNode) -> Result<(), CompileError> {
+        if self.depth > 0 {
+            return Ok(());
+        }
+        let Some(keyword) = ["break", "continue"]
+            .into_iter()
+            .find(|keyword| starts_with_keyword(&statement.stmt, keyword))
+        else {
+            return Ok(());
+        };
+        let help = if self.loop_outside_function {
+            format!(
+                "Content inside a component tag or `def` runs in a function of its own, so `{}` can't reach the loop around it.",
+                keyword
+            )
+        } else {
+            format!("Use `{}` inside a `for` or `while` body.", keyword)
+        };
+        Err(ParseError::new(
+            ErrorKind::InvalidSyntax,
+            format!("`{}` is outside a loop.", keyword),
+            statement.range,
+        )
+        .with_help(help)
+        .boxed()
+        .into())
+    }
+}
+
+/// Whether `code` is the statement `keyword`, possibly with a trailing comment
+fn starts_with_keyword(code: &str, keyword: &str) -> bool {
+    code.trim()
+        .strip_prefix(keyword)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t', ';', '#']))
+}
+
+impl Plugin for LoopControl {
+    fn enter(&mut self, node: &mut Node) -> Result<Flow, CompileError> {
+        match node {
+            Node::For(for_node) => {
+                self.walk_loop(&mut for_node.body, for_node.else_branch.as_mut())?;
+            }
+            Node::While(while_node) => {
+                self.walk_loop(&mut while_node.body, while_node.else_branch.as_mut())?;
+            }
+            Node::Definition(def) => self.walk_function(&mut def.body)?,
+            Node::Component(component) => {
+                self.walk_function(&mut component.children)?;
+                for slot in component.slots.values_mut() {
+                    self.walk_function(slot)?;
+                }
+            }
+            Node::Statement(statement) => {
+                self.check(statement)?;
+                return Ok(Flow::Continue);
+            }
+            _ => return Ok(Flow::Continue),
+        }
+        Ok(Flow::SkipChildren)
+    }
+}
@@ -0,0 +1,76 @@
+use super::{Flow, Plugin};
+use crate::ast::{AttributeKind, Node};
+use crate::error::{CompileError, Warning};
+
+/// Renders `key={expr}` on the first element of a `for` body as
+/// `data-key="{expr}"`, a stable per-item key for morphing libraries.
+///
+/// A `key` anywhere else renders unchanged, with a warning. So does a key that
+/// can't tell the items apart: a constant `key="x"` or a bare `key`.
+#[derive(Default)]
+pub struct LoopKeys {
+    warnings: Vec<Warning>,
+}
+
+impl LoopKeys {
+    pub fn into_warnings(self) -> Vec<Warning> {
+        self.warnings
+    }
+}
+
+impl Plugin for LoopKeys {
+    fn enter(&mut self, node: &mut Node) -> Result<Flow, CompileError> {
+        match node {
+            Node::For(for_node) => {
+                let first = for_node.body.iter_mut().find(|node| {
+                    !matches!(node, Node::Text(text) if text.content.trim().is_empty())
+                        && !matches!(node, Node::Comment(_))
+                });
+                if let Some(Node::Element(el)) = first {
+                    for attr in &mut el.attributes {
+                        let (AttributeKind::Static { name, .. }
+                        | AttributeKind::Boolean { name }
+                        | AttributeKind::Expression { name, .. }
+                        | AttributeKind::Template { name, .. }) = &mut attr.kind
+                        else {
+                            continue;
+                        };
+                        if name != "key" {
+                            continue;
+                        }
+                        *name = "data-key".to_string();
+                        if matches!(
+                            attr.kind,
+                            AttributeKind::Static { .. } | AttributeKind::Boolean { .. }
+                        ) {
+                            self.warnings.push(Warning {
+                                message: "This `key` is the same for every item; give it a per-item value like `key={item.id}`."
+                                    .to_string(),
+                                range: attr.range,
+                            });
+                        }
+                    }
+                }
+            }
+            Node::Element(el) => {
+                for attr in &el.attributes {
+                    let (AttributeKind::Static { name, .. }
+                    | AttributeKind::Expression { name, .. }
+                    | AttributeKind::Template { name, .. }) = &attr.kind
+                    else {
+                        continue;
+                    };
+                    if name == "key" {
+                        self.warnings.push(Warning {
+                            message: "`key` only becomes `data-key` on the first element of a `for` body."
+                                .to_string(),
+                            range: attr.range,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(Flow::Continue)
+    }
+}
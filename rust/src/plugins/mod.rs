@@ -3,23 +3,38 @@ mod component_control_flow;
 mod component_slots;
 mod components;
 mod context;
+mod empty_components;
+mod loop_control;
+mod loop_keys;
 mod mutable_defaults;
+mod prop_validation;
+mod referenced_components;
 mod rename_reserved_keywords;
 mod slots;
 mod spread_kwargs;
+mod ternaries;
+mod unused_params;
 
-pub use r#async::Async;
+pub use r#async::{Async, propagate_async_calls};
 pub use component_control_flow::ComponentControlFlow;
 pub use component_slots::ComponentSlots;
 pub use components::Components;
 pub use context::{BLESSED_SPREAD_NAMES, Helper};
+pub use empty_components::EmptyComponents;
+pub use loop_control::LoopControl;
+pub use loop_keys::LoopKeys;
 pub use mutable_defaults::MutableDefaults;
+pub use prop_validation::PropValidation;
+pub use referenced_components::ReferencedComponents;
 pub use rename_reserved_keywords::{RenameReservedKeywords, rename_reserved_keywords};
 pub use slots::{DEFAULT_SLOT_PARAM, Slots, slot_param_name};
 pub use spread_kwargs::SpreadKwargs;
+pub use ternaries::{Ternaries, lower_ternary};
+pub use unused_params::UnusedParams;
 
 use crate::ast::{Ast, FileMode, Function, Node};
-use crate::error::CompileError;
+use crate::error::{CompileError, Warning};
+use std::collections::HashMap;
 
 /// Whether [`walk`] descends into a node's children after `enter`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -69,20 +84,30 @@ pub fn walk<P: Plugin + ?Sized>(nodes: &mut [Node], plugin: &mut P) -> Result<()
                 Node::Slot(s) => walk(&mut s.fallback, plugin)?,
                 Node::If(if_node) => {
                     walk(&mut if_node.then_branch, plugin)?;
-                    for (_, _, branch) in &mut if_node.elif_branches {
+                    for (_, _, branch, _) in &mut if_node.elif_branches {
                         walk(branch, plugin)?;
                     }
                     if let Some(else_branch) = &mut if_node.else_branch {
                         walk(else_branch, plugin)?;
                     }
                 }
-                Node::For(for_node) => walk(&mut for_node.body, plugin)?,
+                Node::For(for_node) => {
+                    walk(&mut for_node.body, plugin)?;
+                    if let Some(else_branch) = &mut for_node.else_branch {
+                        walk(else_branch, plugin)?;
+                    }
+                }
                 Node::Match(match_node) => {
                     for case in &mut match_node.cases {
                         walk(&mut case.body, plugin)?;
                     }
                 }
-                Node::While(while_node) => walk(&mut while_node.body, plugin)?,
+                Node::While(while_node) => {
+                    walk(&mut while_node.body, plugin)?;
+                    if let Some(else_branch) = &mut while_node.else_branch {
+                        walk(else_branch, plugin)?;
+                    }
+                }
                 Node::With(with_node) => walk(&mut with_node.body, plugin)?,
                 Node::Try(try_node) => {
                     walk(&mut try_node.body, plugin)?;
@@ -115,6 +140,7 @@ pub fn walk<P: Plugin + ?Sized>(nodes: &mut [Node], plugin: &mut P) -> Result<()
 pub fn standard_plugins() -> Vec<Box<dyn Plugin>> {
     vec![
         Box::new(ComponentSlots),
+        Box::new(Ternaries),
         Box::new(RenameReservedKeywords),
         Box::new(Async::default()),
         Box::new(Slots::default()),
@@ -123,30 +149,83 @@ pub fn standard_plugins() -> Vec<Box<dyn Plugin>> {
     ]
 }
 
-fn run_scoped(function: &mut Function) -> Result<(), CompileError> {
+fn run_scoped(
+    function: &mut Function,
+    extra: &mut [&mut dyn Plugin],
+    warnings: &mut Vec<Warning>,
+) -> Result<(), CompileError> {
+    LoopControl::default().run(function)?;
     for mut plugin in standard_plugins() {
         plugin.run(function)?;
     }
+    let mut loop_keys = LoopKeys::default();
+    loop_keys.run(function)?;
+    warnings.extend(loop_keys.into_warnings());
+    let mut empty_components = EmptyComponents::default();
+    empty_components.run(function)?;
+    warnings.extend(empty_components.into_warnings());
+    let mut unused_params = UnusedParams::default();
+    unused_params.run(function)?;
+    warnings.extend(unused_params.into_warnings());
+    for plugin in extra {
+        plugin.run(function)?;
+    }
     Ok(())
 }
 
-fn run_component(function: &mut Function) -> Result<(), CompileError> {
+fn run_component(
+    function: &mut Function,
+    extra: &mut [&mut dyn Plugin],
+    warnings: &mut Vec<Warning>,
+) -> Result<(), CompileError> {
     ComponentControlFlow.run(function)?;
-    run_scoped(function)
+    run_scoped(function, extra, warnings)
 }
 
-/// Lower components, then run standard plugins once per function scope.
-pub fn run(ast: &mut Ast) -> Result<(), CompileError> {
-    let mut components = Components::default();
+/// Lower components, then run standard plugins once per function scope,
+/// followed by `extra` in order. Unlike the standard plugins, which start
+/// fresh in each scope, the `extra` instances see every scope in turn.
+/// Returns the warnings the standard plugins raised.
+pub fn run(
+    ast: &mut Ast,
+    decorator: Option<&str>,
+    extra: &mut [&mut dyn Plugin],
+) -> Result<Vec<Warning>, CompileError> {
+    let mut components = Components::new(decorator);
     components.run(&mut ast.function)?;
     ast.definitions = components.into_definitions();
 
+    let mut warnings = Vec::new();
     for definition in &mut ast.definitions {
-        run_component(&mut definition.function)?;
+        run_component(&mut definition.function, extra, &mut warnings)?;
     }
     if ast.mode == FileMode::ImplicitComponent {
-        run_component(&mut ast.function)
+        run_component(&mut ast.function, extra, &mut warnings)?;
     } else {
-        run_scoped(&mut ast.function)
+        run_scoped(&mut ast.function, extra, &mut warnings)?;
+    }
+    propagate_async_calls(ast)?;
+    Ok(warnings)
+}
+
+/// Check component calls against known props: `props` (components defined in
+/// other files) plus the components this file defines. Runs after [`run`], so
+/// it sees final signatures, including injected `**kwargs`.
+pub fn validate_props(ast: &mut Ast, props: &HashMap<String, Vec<String>>) -> Vec<Warning> {
+    let mut known = props.clone();
+    for definition in &ast.definitions {
+        match prop_validation::declared_props(&definition.function.params) {
+            Some(declared) => known.insert(definition.name.clone(), declared),
+            None => known.remove(&definition.name),
+        };
+    }
+
+    let mut validation = PropValidation::new(&known);
+    for function in std::iter::once(&mut ast.function)
+        .chain(ast.definitions.iter_mut().map(|d| &mut d.function))
+    {
+        // Inspect-only, so it never fails
+        let _ = validation.run(function);
     }
+    validation.into_warnings()
 }
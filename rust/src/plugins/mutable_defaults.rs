@@ -24,9 +24,12 @@ impl Plugin for MutableDefaults {
                 then_branch: vec![Node::Statement(StatementNode {
                     stmt: format!("{name} = {default}"),
                     range: TextRange::synthetic(),
+                    inline: false,
                 })],
                 elif_branches: Vec::new(),
                 else_branch: None,
+                comment: None,
+                else_comment: None,
                 range: TextRange::synthetic(),
             })
         });
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use super::rename_reserved_keywords;
+use super::{Flow, Plugin};
+use crate::ast::{AttributeKind, Node, ParamKind};
+use crate::error::{CompileError, Warning};
+
+/// Warns when a component call passes a keyword the component doesn't declare,
+/// e.g. `<{Card} titel={x}>` against `title: str`.
+///
+/// Inspect only: calls to components missing from the registry, and
+/// components that accept `**kwargs`, are never checked. Names are compared
+/// after reserved keyword renaming, so `class` matches a `class_` param.
+pub struct PropValidation<'a> {
+    props: &'a HashMap<String, Vec<String>>,
+    warnings: Vec<Warning>,
+}
+
+impl<'a> PropValidation<'a> {
+    pub fn new(props: &'a HashMap<String, Vec<String>>) -> Self {
+        Self {
+            props,
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn into_warnings(self) -> Vec<Warning> {
+        self.warnings
+    }
+}
+
+impl Plugin for PropValidation<'_> {
+    fn enter(&mut self, node: &mut Node) -> Result<Flow, CompileError> {
+        let Node::Component(component) = node else {
+            return Ok(Flow::Continue);
+        };
+        let Some(known) = self.props.get(&component.name) else {
+            return Ok(Flow::Continue);
+        };
+
+        for attr in &component.attributes {
+            let name = match &attr.kind {
+                AttributeKind::Static { name, .. }
+                | AttributeKind::Expression { name, .. }
+                | AttributeKind::Template { name, .. }
+                | AttributeKind::Boolean { name }
+                | AttributeKind::Shorthand { name, .. } => name,
+                AttributeKind::Spread { .. } | AttributeKind::SlotAssignment { .. } => continue,
+            };
+            if !known
+                .iter()
+                .any(|prop| rename_reserved_keywords(prop) == *name)
+            {
+                // Report the name as written, not its renamed form
+                let written = name
+                    .strip_suffix('_')
+                    .filter(|original| rename_reserved_keywords(original) == *name)
+                    .unwrap_or(name);
+                self.warnings.push(Warning {
+                    message: format!("<{{{}}}> has no prop `{written}`.", component.name),
+                    range: attr.range,
+                });
+            }
+        }
+        Ok(Flow::Continue)
+    }
+}
+
+/// The props each same-file component definition accepts, or `None` when it
+/// takes `**kwargs` and so accepts anything.
+pub(super) fn declared_props(params: &[Node]) -> Option<Vec<String>> {
+    let mut props = Vec::new();
+    for param in params {
+        if let Node::Parameter(param) = param {
+            if param.kind == ParamKind::VarKeyword {
+                return None;
+            }
+            props.push(param.name.clone());
+        }
+    }
+    Some(props)
+}
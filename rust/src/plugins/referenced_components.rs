@@ -0,0 +1,28 @@
+use super::{Flow, Plugin};
+use crate::ast::Node;
+use crate::error::CompileError;
+use std::collections::BTreeSet;
+
+/// Collects the name of every component a template calls, as written
+/// (`Card`, `ui.Button`), so build tools can map dependencies between files.
+///
+/// Inspect only. Components this file defines are included too.
+#[derive(Default)]
+pub struct ReferencedComponents {
+    names: BTreeSet<String>,
+}
+
+impl ReferencedComponents {
+    pub fn into_names(self) -> BTreeSet<String> {
+        self.names
+    }
+}
+
+impl Plugin for ReferencedComponents {
+    fn enter(&mut self, node: &mut Node) -> Result<Flow, CompileError> {
+        if let Node::Component(component) = node {
+            self.names.insert(component.name.clone());
+        }
+        Ok(Flow::Continue)
+    }
+}
@@ -125,7 +125,7 @@ impl Plugin for RenameReservedKeywords {
             }
             Node::If(if_node) => {
                 if_node.condition = rename_reserved_keywords(&if_node.condition);
-                for (condition, _, _) in &mut if_node.elif_branches {
+                for (condition, _, _, _) in &mut if_node.elif_branches {
                     *condition = rename_reserved_keywords(condition);
                 }
             }
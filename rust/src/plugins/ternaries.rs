@@ -0,0 +1,105 @@
+use super::{Flow, Plugin};
+use crate::ast::{AttributeKind, Node};
+use crate::error::CompileError;
+
+/// Lowers JS-style conditionals, `{ok ? "yes" : "no"}`, to Python's
+/// `("yes") if (ok) else ("no")` in content and attribute expressions.
+/// Python has no `?` operator, so an expression without one at the top level
+/// is left untouched.
+pub struct Ternaries;
+
+/// Rewrite `cond ? a : b` to `(a) if (cond) else (b)`. Nests either way:
+/// `a ? b : c ? d : e` and `a ? b ? c : d : e`. Only `?` and `:` outside
+/// strings and brackets count, so slices and dict literals are left alone.
+/// Returns None when there's no ternary or its `:` is missing.
+pub fn lower_ternary(expr: &str) -> Option<String> {
+    let marks = top_level_marks(expr);
+    let &(question, _) = marks.iter().find(|(_, mark)| *mark == '?')?;
+
+    // The `:` that pairs with the first `?`, skipping nested pairs
+    let mut open = 0;
+    let mut colon = None;
+    for &(pos, mark) in marks.iter().filter(|(pos, _)| *pos > question) {
+        match mark {
+            '?' => open += 1,
+            _ if open > 0 => open -= 1,
+            _ => {
+                colon = Some(pos);
+                break;
+            }
+        }
+    }
+    let colon = colon?;
+
+    let branch = |part: &str| {
+        let part = part.trim();
+        lower_ternary(part).unwrap_or_else(|| part.to_string())
+    };
+    Some(format!(
+        "({}) if ({}) else ({})",
+        branch(&expr[question + 1..colon]),
+        expr[..question].trim(),
+        branch(&expr[colon + 1..]),
+    ))
+}
+
+/// Byte offsets of each `?` and `:` outside strings and brackets
+fn top_level_marks(expr: &str) -> Vec<(usize, char)> {
+    let mut marks = Vec::new();
+    let mut depth: usize = 0;
+    let mut chars = expr.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' | '\'' => {
+                let triple = expr[i..].starts_with(&c.to_string().repeat(3));
+                if triple {
+                    chars.nth(1);
+                }
+                while let Some((j, next)) = chars.next() {
+                    if next == '\\' {
+                        chars.next();
+                    } else if next == c
+                        && (!triple || expr[j..].starts_with(&c.to_string().repeat(3)))
+                    {
+                        if triple {
+                            chars.nth(1);
+                        }
+                        break;
+                    }
+                }
+            }
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            '?' | ':' if depth == 0 => marks.push((i, c)),
+            _ => {}
+        }
+    }
+    marks
+}
+
+fn lower_in_place(expr: &mut String) {
+    if let Some(lowered) = lower_ternary(expr) {
+        *expr = lowered;
+    }
+}
+
+impl Plugin for Ternaries {
+    fn enter(&mut self, node: &mut Node) -> Result<Flow, CompileError> {
+        let attributes = match node {
+            Node::Expression(expr) => {
+                lower_in_place(&mut expr.expr);
+                return Ok(Flow::Continue);
+            }
+            Node::Element(element) => &mut element.attributes,
+            Node::Component(component) => &mut component.attributes,
+            _ => return Ok(Flow::Continue),
+        };
+        for attr in attributes {
+            if let AttributeKind::Expression { expr, .. } = &mut attr.kind {
+                lower_in_place(expr);
+            }
+        }
+        Ok(Flow::Continue)
+    }
+}
@@ -0,0 +1,131 @@
+use super::{Flow, Plugin, slot_param_name, walk};
+use crate::ast::{AttributeKind, Function, Node, ParamKind};
+use crate::error::{CompileError, Warning};
+
+/// Warns about each declared prop the body never mentions, which usually
+/// means a typo between the declaration and its use.
+///
+/// Inspect only, and conservative: a prop counts as used wherever its name
+/// appears as a whole word in any code, including inside strings.
+#[derive(Default)]
+pub struct UnusedParams {
+    /// Python code from the body, one snippet per expression or statement
+    code: Vec<String>,
+    warnings: Vec<Warning>,
+}
+
+impl UnusedParams {
+    pub fn into_warnings(self) -> Vec<Warning> {
+        self.warnings
+    }
+
+    fn uses(&self, name: &str) -> bool {
+        self.code.iter().any(|code| mentions(code, name))
+    }
+}
+
+/// Whether `name` appears in `code` with no identifier character either side
+fn mentions(code: &str, name: &str) -> bool {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    code.match_indices(name).any(|(i, _)| {
+        let before = code[..i].chars().next_back();
+        let after = code[i + name.len()..].chars().next();
+        !before.is_some_and(is_ident) && !after.is_some_and(is_ident)
+    })
+}
+
+impl Plugin for UnusedParams {
+    fn run(&mut self, function: &mut Function) -> Result<(), CompileError> {
+        walk(&mut function.body, self)?;
+
+        for node in &function.params {
+            let Node::Parameter(param) = node else {
+                continue;
+            };
+            // Slot params the plugins added aren't the user's, and `**kwargs`
+            // is often declared just to accept extra props
+            if param.range.is_synthetic() || param.kind == ParamKind::VarKeyword {
+                continue;
+            }
+            if !self.uses(&param.name) {
+                self.warnings.push(Warning {
+                    message: format!("`{}` is declared but never used.", param.name),
+                    range: param.range,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn enter(&mut self, node: &mut Node) -> Result<Flow, CompileError> {
+        let attributes = match node {
+            Node::Expression(expr) => {
+                self.code.push(expr.expr.clone());
+                return Ok(Flow::Continue);
+            }
+            Node::Element(element) => &element.attributes,
+            // `<{ui.Button}>` may call through a prop
+            Node::Component(component) => {
+                self.code.push(component.name.clone());
+                &component.attributes
+            }
+            Node::Slot(slot) => {
+                self.code.push(slot_param_name(slot.name.as_deref()));
+                return Ok(Flow::Continue);
+            }
+            Node::If(if_node) => {
+                self.code.push(if_node.condition.clone());
+                for (condition, _, _, _) in &if_node.elif_branches {
+                    self.code.push(condition.clone());
+                }
+                return Ok(Flow::Continue);
+            }
+            Node::For(for_node) => {
+                self.code.push(for_node.iterable.clone());
+                return Ok(Flow::Continue);
+            }
+            Node::While(while_node) => {
+                self.code.push(while_node.condition.clone());
+                return Ok(Flow::Continue);
+            }
+            Node::With(with_node) => {
+                self.code.push(with_node.items.clone());
+                return Ok(Flow::Continue);
+            }
+            Node::Match(match_node) => {
+                self.code.push(match_node.expr.clone());
+                for case in &match_node.cases {
+                    self.code.push(case.pattern.clone());
+                }
+                return Ok(Flow::Continue);
+            }
+            Node::Try(try_node) => {
+                let exceptions = try_node.except_clauses.iter();
+                self.code
+                    .extend(exceptions.filter_map(|clause| clause.exception.clone()));
+                return Ok(Flow::Continue);
+            }
+            Node::Statement(stmt) => {
+                self.code.push(stmt.stmt.clone());
+                return Ok(Flow::Continue);
+            }
+            Node::Definition(def) => {
+                self.code.push(def.signature.clone());
+                return Ok(Flow::Continue);
+            }
+            _ => return Ok(Flow::Continue),
+        };
+        for attr in attributes {
+            match &attr.kind {
+                AttributeKind::Expression { expr, .. } | AttributeKind::Spread { expr, .. } => {
+                    self.code.push(expr.clone());
+                }
+                // `{data-id}` reads `data_id`
+                AttributeKind::Shorthand { name, .. } => self.code.push(name.replace('-', "_")),
+                AttributeKind::Template { value, .. } => self.code.push(value.clone()),
+                _ => {}
+            }
+        }
+        Ok(Flow::Continue)
+    }
+}
@@ -0,0 +1,38 @@
+use std::sync::{Mutex, Once};
+
+use wasm_bindgen::prelude::*;
+
+/// The message of the panic that stopped the last `transpile_json` call.
+/// Panics abort on `wasm32`, so the hook records it before the trap.
+static LAST_PANIC: Mutex<Option<String>> = Mutex::new(None);
+
+/// `transpile_json` for JavaScript: the JSON `hyper generate --json` prints,
+/// or an `{"error": ...}` object for a compile error. A panic inside the
+/// compiler traps, which JavaScript sees as a `RuntimeError`;
+/// `last_panic_json` then describes it.
+#[wasm_bindgen]
+pub fn transpile_json(source: &str, include_injections: bool) -> String {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        std::panic::set_hook(Box::new(|info| {
+            let message = info.payload_as_str().unwrap_or_default().to_string();
+            if let Ok(mut last) = LAST_PANIC.lock() {
+                *last = Some(message);
+            }
+        }));
+    });
+    if let Ok(mut last) = LAST_PANIC.lock() {
+        *last = None;
+    }
+    crate::json::transpile_json(source, include_injections)
+}
+
+/// The `{"error": ...}` object for the panic that stopped the last
+/// `transpile_json` call, or `undefined` if it didn't panic. A trap can leave
+/// the module's memory inconsistent, so instantiate it again before the next
+/// call.
+#[wasm_bindgen]
+pub fn last_panic_json() -> Option<String> {
+    let message = LAST_PANIC.lock().ok()?.take()?;
+    Some(crate::json::panic_to_json(&message))
+}
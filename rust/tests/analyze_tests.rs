@@ -0,0 +1,55 @@
+use hyper::{ParameterInfo, analyze};
+
+#[test]
+fn reports_params_slots_and_components() {
+    let source = "title: str\ncount: int = 0\n---\n<{Card} heading={title}>\n    <p>{count}</p>\n    {...footer}\n</{Card}>\n";
+    let info = analyze(source).unwrap();
+
+    assert_eq!(
+        info.parameters,
+        [
+            ParameterInfo {
+                name: "title".to_string(),
+                type_hint: Some("str".to_string()),
+                default: None,
+            },
+            ParameterInfo {
+                name: "count".to_string(),
+                type_hint: Some("int".to_string()),
+                default: Some("0".to_string()),
+            },
+        ]
+    );
+    assert_eq!(info.slots.iter().collect::<Vec<_>>(), ["footer"]);
+    assert!(!info.is_async);
+    assert_eq!(
+        info.referenced_components.iter().collect::<Vec<_>>(),
+        ["Card"]
+    );
+    assert!(info.warnings.is_empty());
+}
+
+#[test]
+fn reports_default_slot_and_async() {
+    let source = "user_id: int\n---\nuser = await load(user_id)\n<p>{user}</p>\n{...}\n";
+    let info = analyze(source).unwrap();
+
+    let names: Vec<_> = info.parameters.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["user_id"]);
+    assert_eq!(info.slots.iter().collect::<Vec<_>>(), ["content"]);
+    assert!(info.is_async);
+}
+
+#[test]
+fn info_serializes_to_json() {
+    let info = analyze("name: str\n---\n<p>{name}</p>\n").unwrap();
+    let json = serde_json::to_value(&info).unwrap();
+
+    assert_eq!(json["parameters"][0]["name"], "name");
+    assert_eq!(json["slots"], serde_json::json!([]));
+}
+
+#[test]
+fn parse_errors_are_returned() {
+    assert!(analyze("<div>\n").is_err());
+}
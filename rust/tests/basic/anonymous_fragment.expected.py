@@ -0,0 +1,29 @@
+from app.components import Card
+from hyperhtml import component, escape
+
+
+@component
+def AnonymousFragment(
+        *,
+        items: list[str],
+):
+    # <{Card}>
+    def _card_content():
+        yield f"""\
+<h2>Items</h2>
+<p>{escape(len(items))} in total</p>"""
+    yield from Card.stream(content=_card_content())
+    # </{Card}>
+    yield """<ul>"""
+    for item in items:
+        yield f"""<li>{escape(item)}</li><li class="divider" />"""
+    yield """</ul>"""
+    yield """\
+<p>1 < 2, a <> b, <b>inline</b></p>
+<div>
+    <p>First</p>
+    <p>Second</p>
+</div>
+<p>Before</p>"""
+    if items:
+        yield """<p>Some</p>"""
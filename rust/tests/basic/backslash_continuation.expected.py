@@ -0,0 +1,20 @@
+from hyperhtml import component, escape
+
+
+@component
+def BackslashContinuation(
+        *,
+        price: float,
+        tax: float,
+):
+    total = price + \
+        tax
+    if total > 100 and \
+        tax > 0:
+        yield """<p>Includes tax</p>"""
+    yield f"""\
+<p>Total: {escape(total)}</p>
+<code>C:\\Users\\docs</code>
+Line ends in a backslash \\
+and the next line stays text.
+<a href="C:\\Users\\docs" title="Saved in C:\\new {escape(total)}">Docs</a>"""
@@ -0,0 +1,36 @@
+from hyperhtml import component, escape
+
+
+@component
+def BlockComments(
+        *,
+        items: list,
+        flag: bool,
+):
+    if flag:  # shown to admins only
+        yield """<p>Admin</p>"""
+    elif items:  # members
+        yield """<p>Member</p>"""
+    else:  # everyone else
+        yield """<p>Guest</p>"""
+    for item in items:  # newest first
+        yield f"""<li>{escape(item)}</li>"""
+    else:  # no items
+        yield """<p>Empty</p>"""
+    while flag:  # e.g. "retry"
+        flag = False
+    else:  # gave up
+        yield """<p>Done</p>"""
+    match len(items):  # by count
+        case 0:  # none
+            yield """<p>None</p>"""
+        case _:  # some
+            yield """<p>Some</p>"""
+    try:  # lookup
+        yield f"""<p>{escape(items[0])}</p>"""
+    except IndexError:  # empty list
+        yield """<p>Missing</p>"""
+    else:  # found
+        yield """<p>Found</p>"""
+    finally:  # always
+        yield """<p>Checked</p>"""
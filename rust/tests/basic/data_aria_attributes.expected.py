@@ -0,0 +1,15 @@
+from hyperhtml import component, render_data, render_aria
+
+
+@component
+def DataAriaAttributes(
+        *,
+        data_user_id: int,
+        aria_hidden: bool = False,
+        aria_label: str | None = None,
+):
+    # Prefixed shorthands render through the data and aria helpers
+    yield f"""<div data-active{render_data({"user-id": data_user_id})}{render_aria({"hidden": aria_hidden})}{render_aria({"label": aria_label})}>Content</div>"""
+
+    # Written out, they stay plain attributes
+    yield """<div data-role="admin" aria-live="polite">Status</div>"""
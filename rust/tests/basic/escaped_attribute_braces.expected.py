@@ -0,0 +1,13 @@
+from hyperhtml import component, escape
+
+
+@component
+def EscapedAttributeBraces(
+        *,
+        state: str,
+):
+    # {{ and }} print a single brace, in static values and next to an {expr}
+    yield f"""\
+<div x-data="{{ open: false }}">
+    <p x-text="{{ label: '{escape(state)}' }}">Hi</p>
+</div>"""
@@ -0,0 +1,18 @@
+from hyperhtml import component, escape
+
+
+@component
+def FencedCode(
+        *,
+        name: str,
+):
+    yield f"""\
+<article>
+    <p>Hello {escape(name)}, render a prop like this:</p>
+    ```hyper
+    <div class="greeting">
+        {{name}}
+    </div>
+    ```
+    <p>That is all.</p>
+</article>"""
@@ -0,0 +1,19 @@
+from datetime import datetime
+from hyperhtml import component, escape
+
+
+@component
+def FormatSpecs(
+        *,
+        price: float,
+        item: object,
+        items: list,
+        ts: datetime,
+):
+    yield f"""\
+<p title="{escape(format(price, ".2f"))}">Total: {escape(format(price, ".2f"))}</p>
+<p>Item: {escape(repr(item))}</p>
+<p>Padded: {escape(format(str(item), ">8"))}</p>
+<p>Slice: {escape(items[1:2])}</p>
+<p>Backslash fill: {escape(format(price, chr(92) + "^12.2f"))}</p>
+<time title="{escape(format(ts, "%H:%M"))}">{escape(format(ts, "%H:%M"))}</time>"""
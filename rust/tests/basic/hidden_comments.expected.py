@@ -0,0 +1,14 @@
+from hyperhtml import component, escape
+
+
+@component
+def HiddenComments(
+        *,
+        name: str,
+):
+    yield f"""\
+<p>a  b</p>
+<div>
+    <span>{escape(name)}</span>
+    <span>after</span>
+</div>"""
@@ -0,0 +1,20 @@
+from hyperhtml import component, escape
+
+
+@component
+def InlineAssignments(
+        *,
+        items: list,
+):
+    # An inline assignment binds a name and keeps the text around it
+    yield """<p>"""
+    yield """Showing """
+    count = len(items)
+    yield f""" {escape(count)} items"""
+    yield """</p>"""
+
+    # On its own line it's a plain statement
+    yield """<ul>"""
+    first = items[0]
+    yield f"""<li>{escape(first)}</li>"""
+    yield """</ul>"""
@@ -0,0 +1,22 @@
+from hyperhtml import component, escape
+
+
+@component
+def LoopControl(
+        *,
+        items: list[str],
+):
+    yield """<ul>"""
+    for item in items:
+        if not item:
+            continue
+        if item == "stop":
+            break
+        yield f"""<li>{escape(item)}</li>"""
+    yield """</ul>"""
+    count = 0
+    while True:
+        count += 1
+        if count > 3:
+            break
+        yield f"""<span>{escape(count)}</span>"""
@@ -0,0 +1,22 @@
+from hyperhtml import component, escape
+
+
+@component
+def LoopElse(
+        *,
+        items: list,
+):
+    yield """<ul>"""
+    for item in items:
+        if item.hidden:
+            break
+        yield f"""<li>{escape(item.name)}</li>"""
+    else:
+        yield f"""<li>All {escape(len(items))} items shown</li>"""
+    yield """</ul>"""
+
+    attempts = 0
+    while attempts < 3:
+        attempts += 1
+    else:
+        yield f"""<p>Gave up after {escape(attempts)} attempts</p>"""
@@ -0,0 +1,18 @@
+from hyperhtml import component, escape
+
+
+@component
+def LoopKeys(
+        *,
+        items: list,
+):
+    yield """<ul>"""
+    for item in items:
+        yield f"""<li data-key="{escape(item.id)}">{escape(item.name)}</li>"""
+    yield """</ul>"""
+
+    for item in items:
+        yield f"""\
+<div data-key="{escape(item.slug)}">
+    <span key="{escape(item.id)}">{escape(item.name)}</span>
+</div>"""
@@ -0,0 +1,16 @@
+from hyperhtml import component, escape
+
+
+@component
+def MatchCases(
+        *,
+        status: str,
+        count: int,
+):
+    match status:
+        case "active" | "pending" if count > 0:
+            yield f"""<p>Working on {escape(count)}</p>"""
+        case "active":
+            yield """<p>Idle</p>"""
+        case _:
+            yield """<p>Unknown</p>"""
@@ -0,0 +1,25 @@
+from hyperhtml import component, escape
+
+
+@component
+def MatchPatterns(
+        *,
+        point: tuple,
+        code: int,
+):
+    match point:
+        case Point(x=0, y=0):
+            yield """<p>Origin</p>"""
+        case Point(x=0, y=y):
+            yield f"""<p>On the y axis at {escape(y)}</p>"""
+        case other:
+            yield f"""<p>Somewhere else: {escape(other)}</p>"""
+    match code:
+        case 200 | 201 | 204:
+            yield """<p>Success</p>"""
+        case _ if code >= 500:
+            yield """<p>Server error</p>"""
+        case None:
+            yield """<p>No response</p>"""
+        case _:
+            yield """<p>Other</p>"""
@@ -0,0 +1,16 @@
+from hyperhtml import component, escape
+
+
+@component
+def NestedDefInLoop(
+        *,
+        items: list[int],
+):
+    for item in items:
+        def label(x):
+            return x + 1
+        yield f"""<p>{escape(label(item))}</p>"""
+    if items:
+        class Box:
+            value = 1
+        yield f"""<span>{escape(Box.value)}</span>"""
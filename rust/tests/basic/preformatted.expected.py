@@ -0,0 +1,16 @@
+from hyperhtml import component, escape
+
+
+@component
+def Preformatted(
+        *,
+        code: str,
+        show: bool = True,
+):
+    # Whitespace inside <pre> is kept exactly as written, even inside blocks
+    if show:
+        yield f"""\
+<pre>
+def greet():
+    print({escape(code)})
+    </pre>"""
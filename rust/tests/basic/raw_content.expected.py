@@ -45,6 +45,15 @@ def RawContent(
     }
 </style>"""
 
+    # Markup and braces inside a single-line script stay literal
+    yield """<script>document.body.innerHTML = "<p>{}</p>";</script>"""
+
+    # hyper-interpolate opts into {expr}; literal braces are escaped
+    yield f"""\
+<style>
+    .theme {{ color: {escape(theme_color)}; }}
+</style>"""
+
     # Explicit raw block
     yield """\
 <div>
@@ -0,0 +1,16 @@
+from hyperhtml import component
+
+
+@component
+def TemplateListItems():
+    yield """\
+<ul x-data="list">
+    <template x-for="item in items">
+        <li x-text="item"></li>
+    </template>
+</ul>
+<table>
+    <template x-if="rows">
+        <tr><td>Row</td></tr>
+    </template>
+</table>"""
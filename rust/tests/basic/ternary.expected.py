@@ -0,0 +1,18 @@
+from hyperhtml import component, escape, render_class
+
+
+@component
+def Ternary(
+        *,
+        ok: bool,
+        items: list,
+        price: float,
+):
+    yield f"""\
+<p>{escape(("yes") if (ok) else ("no"))}</p>
+<p>{escape(items[1:2])}</p>
+<p>{escape((items[1:]) if (ok) else (items[:1]))}</p>
+<p>{escape(("Ready?") if (ok) else ("Wait: loading"))}</p>
+<p>{escape(("none") if (not items) else (("one") if (len(items) == 1) else ("many")))}</p>
+<p>{escape(format((price) if (ok) else (0), ".2f"))}</p>
+<button class="{render_class(("primary") if (ok) else ("secondary"))}">Go</button>"""
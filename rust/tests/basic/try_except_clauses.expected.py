@@ -0,0 +1,16 @@
+from hyperhtml import component, escape
+
+
+@component
+def TryExceptClauses(
+        *,
+        user_id: int,
+):
+    try:
+        yield f"""<p>{escape(load(user_id))}</p>"""
+    except KeyError as e:
+        yield f"""<p>Missing key: {escape(e)}</p>"""
+    except (ValueError, TypeError) as e:
+        yield f"""<p>Bad input: {escape(e)}</p>"""
+    except:
+        yield """<p>Something went wrong</p>"""
@@ -0,0 +1,13 @@
+from hyperhtml import component, escape
+
+
+@component
+def Walrus(
+        *,
+        items: list,
+):
+    (first := items[0] if items else None)
+    if (count := len(items)) > 0:
+        yield f"""<p>{escape(count)} items, starting with {escape(first)}</p>"""
+    while (item := items.pop()) is not None:
+        yield f"""<li>{escape(item)}</li>"""
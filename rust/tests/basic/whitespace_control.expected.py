@@ -0,0 +1,26 @@
+from hyperhtml import component, escape
+
+
+@component
+def WhitespaceControl(
+        *,
+        name: str,
+        items: list,
+):
+    # {- strips whitespace before the expression, -} strips whitespace after
+    yield f"""<p>{escape(name)}</p>"""
+
+    yield """<ul>"""
+    for item in items:
+        yield f"""<li>{escape(item)}</li>"""
+    yield """</ul>"""
+
+    # Only one side
+    yield f"""\
+<span>{escape(name)}
+</span>
+<span>
+    {escape(name)}</span>"""
+
+    # Without surrounding whitespace, -x is still unary minus
+    yield f"""<b>{escape(-len(items))}</b>"""
@@ -0,0 +1,17 @@
+from hyperhtml import component, escape
+
+
+@component
+def WithManagers(
+        *,
+        path: str,
+):
+    with open(path) as src, open(path + ".bak", "w") as dst:
+        dst.write(src.read())
+    yield """<section>"""
+    with (
+        open(path) as first,
+        open(path) as second,
+    ):  # compare both reads
+        yield f"""<p>{escape(first.read() == second.read())}</p>"""
+    yield """</section>"""
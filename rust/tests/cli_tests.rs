@@ -68,6 +68,34 @@ fn stdin_invalid_source_exits_nonzero() {
     );
 }
 
+#[test]
+fn stdin_filename_names_the_component() {
+    let mut child = Command::new(hyper_bin())
+        .args([
+            "generate",
+            "--stdin",
+            "--stdin-filename",
+            "components/user_card.hyper",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start hyper");
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"<div>Hello</div>")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success(), "Should exit 0 for valid source");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("def UserCard("), "{stdout}");
+}
+
 // ========================================================================
 // --json output
 // ========================================================================
@@ -136,6 +164,50 @@ fn json_error_output_has_error_field() {
     );
 }
 
+fn generate_stdin_output(args: &[&str], source: &str) -> String {
+    let mut child = Command::new(hyper_bin())
+        .args(["generate", "--stdin"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start hyper");
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(source.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "Should exit 0");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn pretty_json_matches_compact_json() {
+    let source = "name: str\n---\n<div class={name}>{name}</div>";
+    let compact = generate_stdin_output(&["--json", "--injection"], source);
+    let pretty = generate_stdin_output(&["--json", "--injection", "--pretty"], source);
+
+    assert_eq!(compact.lines().count(), 1, "{compact}");
+    assert!(pretty.contains("\n  \"compiled\": "), "{pretty}");
+    let compact: serde_json::Value = serde_json::from_str(&compact).unwrap();
+    let pretty: serde_json::Value = serde_json::from_str(&pretty).unwrap();
+    assert_eq!(pretty, compact);
+}
+
+#[test]
+fn pretty_is_ignored_without_json() {
+    let source = "<div>Hello</div>";
+
+    assert_eq!(
+        generate_stdin_output(&["--pretty"], source),
+        generate_stdin_output(&[], source)
+    );
+}
+
 // ========================================================================
 // --daemon protocol
 // ========================================================================
@@ -219,3 +291,518 @@ fn daemon_ready_and_compile() {
         "Daemon should exit cleanly when stdin closes"
     );
 }
+
+/// Fresh, empty scratch directory for a single test
+fn scratch_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("hyper-cli-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Poll until `path` exists and contains `needle`, or give up after a few seconds
+fn wait_for_contents(path: &std::path::Path, needle: &str) -> bool {
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    while std::time::Instant::now() < deadline {
+        if std::fs::read_to_string(path).is_ok_and(|s| s.contains(needle)) {
+            return true;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(25));
+    }
+    false
+}
+
+// ========================================================================
+// --out-dir
+// ========================================================================
+
+#[test]
+fn out_dir_mirrors_input_layout() {
+    let dir = scratch_dir("out-dir");
+    let templates = dir.join("templates");
+    std::fs::create_dir_all(templates.join("nested")).unwrap();
+    std::fs::write(templates.join("card.hyper"), "<p>card</p>\n").unwrap();
+    std::fs::write(templates.join("nested/badge.hyper"), "<b>badge</b>\n").unwrap();
+    let out = dir.join("out");
+
+    let output = Command::new(hyper_bin())
+        .args(["generate", templates.to_str().unwrap(), "--out-dir"])
+        .arg(&out)
+        .output()
+        .expect("Failed to run hyper");
+
+    assert!(output.status.success(), "Should exit 0");
+    assert!(out.join("card.py").is_file());
+    assert!(out.join("nested/badge.py").is_file());
+    assert!(
+        !templates.join("card.py").exists(),
+        "Nothing should be written next to the source"
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn stubs_are_written_next_to_generated_modules() {
+    let dir = scratch_dir("stubs");
+    std::fs::write(
+        dir.join("card.hyper"),
+        "title: str\n---\n<h1>{title}</h1>\n{...}\n",
+    )
+    .unwrap();
+
+    let output = Command::new(hyper_bin())
+        .args(["generate", dir.to_str().unwrap(), "--stubs"])
+        .output()
+        .expect("Failed to run hyper");
+
+    assert!(output.status.success(), "Should exit 0");
+    assert!(dir.join("card.py").is_file());
+    let stub = std::fs::read_to_string(dir.join("card.pyi")).unwrap();
+    assert!(
+        stub.contains(
+            "def __call__(self, *, title: str, content: Iterable[str] | None = ...) -> HtmlResult: ..."
+        ) && stub.ends_with("\n\nCard: _Card\n"),
+        "{stub}"
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn out_dir_pointing_at_file_is_an_error() {
+    let dir = scratch_dir("out-dir-file");
+    std::fs::write(dir.join("card.hyper"), "<p>card</p>\n").unwrap();
+    std::fs::write(dir.join("out"), "").unwrap();
+
+    let output = Command::new(hyper_bin())
+        .args(["generate", dir.to_str().unwrap(), "--out-dir"])
+        .arg(dir.join("out"))
+        .output()
+        .expect("Failed to run hyper");
+
+    assert!(!output.status.success(), "Should exit non-zero");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("not a directory"),
+        "Should explain the problem: {stderr}"
+    );
+    assert!(!dir.join("card.py").exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn out_dir_collision_names_both_sources() {
+    let dir = scratch_dir("out-dir-collision");
+    for sub in ["a", "b", "c"] {
+        std::fs::create_dir_all(dir.join(sub)).unwrap();
+        std::fs::write(dir.join(sub).join("card.hyper"), "<p>card</p>\n").unwrap();
+    }
+    let out = dir.join("out");
+
+    let output = Command::new(hyper_bin())
+        .arg("generate")
+        .arg(dir.join("a/card.hyper"))
+        .arg(dir.join("b/card.hyper"))
+        .arg(dir.join("c/card.hyper"))
+        .arg("--out-dir")
+        .arg(&out)
+        .output()
+        .expect("Failed to run hyper");
+
+    assert!(!output.status.success(), "Should exit non-zero");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("would both generate"), "{stderr}");
+    assert!(stderr.contains("a/card.hyper") && stderr.contains("b/card.hyper"));
+    // Each later source is reported against the first one to claim the path
+    let collisions: Vec<_> = stderr
+        .lines()
+        .filter(|line| line.contains("would both generate"))
+        .collect();
+    assert_eq!(collisions.len(), 2, "{stderr}");
+    assert!(collisions[1].contains("a/card.hyper") && collisions[1].contains("c/card.hyper"));
+    assert!(!out.join("card.py").exists(), "Nothing should be written");
+
+    // The same file reached twice is generated once
+    let output = Command::new(hyper_bin())
+        .arg("generate")
+        .arg(dir.join("a"))
+        .arg(dir.join("a/card.hyper"))
+        .output()
+        .expect("Failed to run hyper");
+    assert!(output.status.success(), "Should exit 0");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Generated 1 file"), "{stderr}");
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+// ========================================================================
+// .hyperignore
+// ========================================================================
+
+#[test]
+fn out_dir_collision_ignores_case() {
+    let dir = scratch_dir("out-dir-collision-case");
+    std::fs::create_dir_all(dir.join("a")).unwrap();
+    std::fs::create_dir_all(dir.join("b")).unwrap();
+    std::fs::write(dir.join("a/Card.hyper"), "<p>card</p>\n").unwrap();
+    std::fs::write(dir.join("b/card.hyper"), "<p>card</p>\n").unwrap();
+    let out = dir.join("out");
+
+    let output = Command::new(hyper_bin())
+        .arg("generate")
+        .arg(dir.join("a/Card.hyper"))
+        .arg(dir.join("b/card.hyper"))
+        .arg("--out-dir")
+        .arg(&out)
+        .output()
+        .expect("Failed to run hyper");
+
+    assert!(!output.status.success(), "Should exit non-zero");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("would both generate"), "{stderr}");
+    assert!(stderr.contains("a/Card.hyper") && stderr.contains("b/card.hyper"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn hyperignore_excludes_partials_from_generation_and_exports() {
+    let dir = scratch_dir("hyperignore");
+    let templates = dir.join("templates");
+    std::fs::create_dir_all(templates.join("cards")).unwrap();
+    std::fs::write(templates.join(".hyperignore"), "# partials\n_*.hyper\n").unwrap();
+    std::fs::write(templates.join("Button.hyper"), "<b>button</b>\n").unwrap();
+    std::fs::write(templates.join("_Draft.hyper"), "<b>draft</b>\n").unwrap();
+    std::fs::write(templates.join("cards/Card.hyper"), "<p>card</p>\n").unwrap();
+    std::fs::write(templates.join("cards/_Header.hyper"), "<h1>header</h1>\n").unwrap();
+
+    let output = Command::new(hyper_bin())
+        .args(["generate", templates.to_str().unwrap(), "--flat-exports"])
+        .output()
+        .expect("Failed to run hyper");
+
+    assert!(output.status.success(), "Should exit 0");
+    assert!(templates.join("Button.py").exists());
+    assert!(templates.join("cards/Card.py").exists());
+    assert!(!templates.join("_Draft.py").exists());
+    assert!(!templates.join("cards/_Header.py").exists());
+    let init = std::fs::read_to_string(templates.join("__init__.py")).unwrap();
+    assert_eq!(
+        init,
+        "# Generated by hyper --flat-exports. Do not edit.\n\
+         from .Button import Button\n\
+         from .cards.Card import Card\n\
+         \n\
+         __all__ = [\"Button\", \"Card\"]\n"
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn nested_hyperignore_applies_to_its_subtree() {
+    let dir = scratch_dir("hyperignore-nested");
+    std::fs::create_dir_all(dir.join("fixtures")).unwrap();
+    std::fs::write(dir.join("fixtures/.hyperignore"), "*.hyper\n").unwrap();
+    std::fs::write(dir.join("fixtures/Sample.hyper"), "<p>sample</p>\n").unwrap();
+    std::fs::write(dir.join("Sample.hyper"), "<p>sample</p>\n").unwrap();
+
+    let output = Command::new(hyper_bin())
+        .args(["generate", dir.to_str().unwrap()])
+        .output()
+        .expect("Failed to run hyper");
+
+    assert!(output.status.success(), "Should exit 0");
+    assert!(dir.join("Sample.py").exists());
+    assert!(!dir.join("fixtures/Sample.py").exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+// ========================================================================
+// --flat-exports
+// ========================================================================
+
+#[test]
+fn flat_exports_reexport_nested_components() {
+    let dir = scratch_dir("flat-exports");
+    let templates = dir.join("templates");
+    std::fs::create_dir_all(templates.join("cards/deep")).unwrap();
+    std::fs::write(templates.join("Button.hyper"), "<b>button</b>\n").unwrap();
+    std::fs::write(templates.join("cards/Card.hyper"), "<p>card</p>\n").unwrap();
+    std::fs::write(templates.join("cards/deep/Badge.hyper"), "<i>badge</i>\n").unwrap();
+    std::fs::write(
+        templates.join("Layout.hyper"),
+        "component Header():\n    <h1>Site</h1>\nend\n\n<{Header} />\n",
+    )
+    .unwrap();
+    std::fs::write(templates.join("my-card.hyper"), "<p>card</p>\n").unwrap();
+
+    let output = Command::new(hyper_bin())
+        .args(["generate", templates.to_str().unwrap(), "--flat-exports"])
+        .output()
+        .expect("Failed to run hyper");
+
+    assert!(output.status.success(), "Should exit 0");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("skipping My-card in --flat-exports"),
+        "{stderr}"
+    );
+    let init = std::fs::read_to_string(templates.join("__init__.py")).unwrap();
+    assert_eq!(
+        init,
+        "# Generated by hyper --flat-exports. Do not edit.\n\
+         from .Button import Button\n\
+         from .Layout import Header\n\
+         from .Layout import Layout\n\
+         from .cards.Card import Card\n\
+         from .cards.deep.Badge import Badge\n\
+         \n\
+         __all__ = [\"Badge\", \"Button\", \"Card\", \"Header\", \"Layout\"]\n"
+    );
+    assert!(!templates.join("cards/__init__.py").exists());
+
+    // Regenerating replaces its own file but not a hand-written one
+    let regenerate = || {
+        Command::new(hyper_bin())
+            .args(["generate", templates.to_str().unwrap(), "--flat-exports"])
+            .output()
+            .expect("Failed to run hyper")
+    };
+    assert!(regenerate().status.success(), "Should exit 0");
+    std::fs::write(templates.join("__init__.py"), "VERSION = 1\n").unwrap();
+    let output = regenerate();
+    assert!(!output.status.success(), "Should exit non-zero");
+    assert_eq!(
+        std::fs::read_to_string(templates.join("__init__.py")).unwrap(),
+        "VERSION = 1\n"
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn flat_exports_collision_names_both_sources() {
+    let dir = scratch_dir("flat-exports-collision");
+    for sub in ["a", "b", "c"] {
+        std::fs::create_dir_all(dir.join(sub)).unwrap();
+        std::fs::write(dir.join(sub).join("Card.hyper"), "<p>card</p>\n").unwrap();
+    }
+
+    let output = Command::new(hyper_bin())
+        .args(["generate", dir.to_str().unwrap(), "--flat-exports"])
+        .output()
+        .expect("Failed to run hyper");
+
+    assert!(!output.status.success(), "Should exit non-zero");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let collisions: Vec<_> = stderr
+        .lines()
+        .filter(|line| line.contains("would both export Card"))
+        .collect();
+    assert_eq!(collisions.len(), 2, "{stderr}");
+    assert!(collisions[0].contains("a/Card.hyper") && collisions[0].contains("b/Card.hyper"));
+    assert!(collisions[1].contains("a/Card.hyper") && collisions[1].contains("c/Card.hyper"));
+    assert!(!dir.join("__init__.py").exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+// ========================================================================
+// --diagnostics
+// ========================================================================
+
+#[test]
+fn diagnostics_are_json_lines_on_stdout() {
+    let dir = scratch_dir("diagnostics");
+    std::fs::write(dir.join("good.hyper"), "<p>good</p>\n").unwrap();
+    std::fs::write(dir.join("bad.hyper"), "<div>unclosed\n").unwrap();
+
+    let output = Command::new(hyper_bin())
+        .args(["generate", dir.to_str().unwrap(), "--diagnostics"])
+        .output()
+        .expect("Failed to run hyper");
+
+    assert!(!output.status.success(), "Should exit non-zero");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 1, "One line per error: {stdout}");
+    let diagnostic: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+    assert!(diagnostic["file"].as_str().unwrap().ends_with("bad.hyper"));
+    assert_eq!(diagnostic["kind"], "Unclosed element");
+    assert!(diagnostic["message"].as_str().unwrap().contains("<div>"));
+    assert!(diagnostic["help"].is_string());
+    for end in ["start", "end"] {
+        assert!(diagnostic["span"][end]["line"].is_u64());
+        assert!(diagnostic["span"][end]["col"].is_u64());
+    }
+    assert!(dir.join("good.py").is_file());
+
+    std::fs::remove_file(dir.join("bad.hyper")).unwrap();
+    let output = Command::new(hyper_bin())
+        .args(["generate", dir.to_str().unwrap(), "--diagnostics"])
+        .output()
+        .expect("Failed to run hyper");
+    assert!(output.status.success(), "Should exit 0 without errors");
+    assert!(output.stdout.is_empty());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+// ========================================================================
+// --check
+// ========================================================================
+
+#[test]
+fn check_reports_errors_without_writing() {
+    let dir = scratch_dir("check");
+    std::fs::write(dir.join("good.hyper"), "<p>good</p>\n").unwrap();
+    std::fs::write(dir.join("bad.hyper"), "<div>unclosed\n").unwrap();
+
+    let output = Command::new(hyper_bin())
+        .args(["generate", dir.to_str().unwrap(), "--check"])
+        .output()
+        .expect("Failed to run hyper");
+
+    assert!(!output.status.success(), "Should exit non-zero");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 of 2 files failed"), "{stderr}");
+    assert!(stderr.contains("bad.hyper"), "{stderr}");
+    assert!(!dir.join("good.py").exists(), "Nothing should be written");
+
+    std::fs::remove_file(dir.join("bad.hyper")).unwrap();
+    let output = Command::new(hyper_bin())
+        .args(["generate", dir.to_str().unwrap(), "--check"])
+        .output()
+        .expect("Failed to run hyper");
+    assert!(output.status.success(), "Should exit 0 without errors");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 file OK"));
+    assert!(!dir.join("good.py").exists());
+
+    let output = Command::new(hyper_bin())
+        .args(["generate", dir.to_str().unwrap(), "--check", "--out-dir"])
+        .arg(dir.join("out"))
+        .output()
+        .expect("Failed to run hyper");
+    assert!(!output.status.success(), "--check and --out-dir conflict");
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+// ========================================================================
+// --quiet / --verbose
+// ========================================================================
+
+fn generate_card(name: &str, flags: &[&str]) -> String {
+    let dir = scratch_dir(name);
+    std::fs::write(dir.join("card.hyper"), "<p>{title}</p>\n").unwrap();
+
+    let output = Command::new(hyper_bin())
+        .arg("generate")
+        .arg(&dir)
+        .args(flags)
+        .output()
+        .expect("Failed to run hyper");
+
+    assert!(output.status.success(), "Should exit 0");
+    let _ = std::fs::remove_dir_all(&dir);
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn default_output_lists_each_file() {
+    let stderr = generate_card("default-output", &[]);
+
+    assert!(stderr.contains("✓ "), "{stderr}");
+    assert!(stderr.contains("card.py\n"), "{stderr}");
+    assert!(stderr.contains("Generated 1 file"), "{stderr}");
+}
+
+#[test]
+fn quiet_prints_only_the_summary() {
+    let stderr = generate_card("quiet", &["--quiet"]);
+
+    assert!(!stderr.contains("card.py"), "{stderr}");
+    assert!(stderr.contains("Generated 1 file"), "{stderr}");
+}
+
+#[test]
+fn verbose_adds_time_and_mapping_count() {
+    let stderr = generate_card("verbose", &["--verbose"]);
+
+    assert!(stderr.contains("card.py ("), "{stderr}");
+    assert!(stderr.contains(" mappings)"), "{stderr}");
+}
+
+#[test]
+fn quiet_and_verbose_conflict() {
+    let output = Command::new(hyper_bin())
+        .args(["generate", "--quiet", "--verbose"])
+        .output()
+        .expect("Failed to run hyper");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be used with"));
+}
+
+// ========================================================================
+// --watch mode
+// ========================================================================
+
+#[test]
+fn watch_regenerates_changed_file_and_survives_errors() {
+    let dir = scratch_dir("watch");
+    let source = dir.join("card.hyper");
+    let output = dir.join("card.py");
+    std::fs::write(&source, "<p>first</p>\n").unwrap();
+
+    let mut child = Command::new(hyper_bin())
+        .args(["generate", dir.to_str().unwrap(), "--watch"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start hyper");
+
+    assert!(
+        wait_for_contents(&output, "first"),
+        "Initial generation should run"
+    );
+
+    // A compile error is reported, not fatal
+    std::fs::write(&source, "<div>unclosed\n").unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(400));
+    assert!(
+        child.try_wait().unwrap().is_none(),
+        "Watcher should keep running after a compile error"
+    );
+
+    std::fs::write(&source, "<p>second</p>\n").unwrap();
+    let regenerated = wait_for_contents(&output, "second");
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(regenerated, "Changed file should be regenerated");
+}
+
+#[test]
+fn watch_picks_up_new_files_and_directories() {
+    let dir = scratch_dir("watch_new");
+    std::fs::write(dir.join(".hyperignore"), "drafts/\n").unwrap();
+    std::fs::write(dir.join("card.hyper"), "<p>card</p>\n").unwrap();
+
+    let mut child = Command::new(hyper_bin())
+        .args(["generate", dir.to_str().unwrap(), "--watch"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start hyper");
+    assert!(wait_for_contents(&dir.join("card.py"), "card"));
+
+    std::fs::create_dir_all(dir.join("pages")).unwrap();
+    std::fs::write(dir.join("pages/home.hyper"), "<p>home</p>\n").unwrap();
+    std::fs::create_dir_all(dir.join("drafts")).unwrap();
+    std::fs::write(dir.join("drafts/wip.hyper"), "<p>wip</p>\n").unwrap();
+    let generated = wait_for_contents(&dir.join("pages/home.py"), "home");
+    std::thread::sleep(std::time::Duration::from_millis(400));
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+    let ignored = !dir.join("drafts/wip.py").exists();
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(generated, "A file in a new directory should be generated");
+    assert!(ignored, "An ignored directory should stay ignored");
+}
@@ -18,6 +18,7 @@ pub fn compile_with_ranges(source: &str, name: &str) -> CompileResult {
         &CompileOptions {
             function_name: Some(name.to_string()),
             include_ranges: true,
+            ..Default::default()
         },
     )
     .unwrap()
@@ -38,3 +39,11 @@ pub fn html_segments(result: &CompileResult) -> Vec<&Segment> {
         .filter(|s| s.language == Language::Html)
         .collect()
 }
+
+pub fn template_segments(result: &CompileResult) -> Vec<&Segment> {
+    result
+        .segments
+        .iter()
+        .filter(|s| s.language == Language::Template)
+        .collect()
+}
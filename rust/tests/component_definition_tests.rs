@@ -1,4 +1,5 @@
-use hyper::{CompileOptions, compile};
+use hyper::error::ErrorKind;
+use hyper::{CompileError, CompileOptions, compile};
 
 #[test]
 fn explicit_component_is_hoisted_and_attached() {
@@ -15,6 +16,7 @@ end
         &CompileOptions {
             function_name: Some("Page".to_string()),
             include_ranges: false,
+            ..Default::default()
         },
     )
     .expect("component should compile");
@@ -160,6 +162,27 @@ fn duplicate_named_slot_fills_are_rejected() {
 
     assert!(message.contains("`actions` slot is filled more than once"));
     assert!(message.contains("first fill"));
+    let CompileError::Parse(error) = error else {
+        panic!("expected a parse error, got {error:?}");
+    };
+    assert_eq!(error.kind, ErrorKind::DuplicateSlot);
+    assert_eq!(error.range.start.line, 3);
+    assert_eq!(error.related_range.map(|range| range.start.line), Some(2));
+}
+
+#[test]
+fn distinct_named_slot_fills_are_allowed() {
+    let code = compile_code(
+        r#"---
+<{Card}>
+    <p {...header}>Top</p>
+    <p {...footer}>Bottom</p>
+</{Card}>
+"#,
+    );
+
+    assert!(code.contains("header=_card_header()"));
+    assert!(code.contains("footer=_card_footer()"));
 }
 
 #[test]
@@ -215,8 +238,208 @@ fn compile_code(source: &str) -> String {
         &CompileOptions {
             function_name: Some("Page".to_string()),
             include_ranges: false,
+            ..Default::default()
         },
     )
     .expect("component should compile")
     .code
 }
+
+#[test]
+fn unknown_prop_on_local_component_warns() {
+    let source = r#"title: str
+---
+component Header(*, title: str):
+    <header>{title}</header>
+end
+
+<{Header} titel={title} />
+<{Header} title={title} />
+"#;
+    let result = compile(source, &CompileOptions::default()).expect("warnings don't fail");
+
+    assert_eq!(result.warnings.len(), 1, "{:?}", result.warnings);
+    let warning = &result.warnings[0];
+    assert_eq!(warning.message, "<{Header}> has no prop `titel`.");
+    assert_eq!(
+        &source[warning.range.start.byte..warning.range.end.byte],
+        "titel={title}"
+    );
+}
+
+#[test]
+fn unknown_prop_on_registered_component_warns() {
+    let source = "<{Card} title=\"Hi\" class=\"wide\" {**extra} />\n<{Other} anything />\n";
+    let mut options = CompileOptions::default();
+    options
+        .component_props
+        .insert("Card".to_string(), vec!["title".to_string()]);
+
+    let result = compile(source, &options).unwrap();
+    let messages: Vec<_> = result.warnings.iter().map(|w| w.message.as_str()).collect();
+    assert_eq!(messages, ["<{Card}> has no prop `class`."]);
+
+    options.component_props.insert(
+        "Card".to_string(),
+        vec!["title".to_string(), "class".to_string()],
+    );
+    assert!(compile(source, &options).unwrap().warnings.is_empty());
+}
+
+#[test]
+fn component_taking_kwargs_accepts_any_prop() {
+    let source = r#"component Button(**attrs):
+    <button {**attrs}>Go</button>
+end
+
+<{Button} type="submit" />
+"#;
+    let result = compile(source, &CompileOptions::default()).unwrap();
+    assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+}
+
+#[test]
+fn empty_component_block_suggests_self_closing() {
+    let source = "<{Card}></{Card}>\n<{Card}>\n</{Card}>\n<{Card} />\n<{Card}>Hi</{Card}>\n";
+    let result = compile(source, &CompileOptions::default()).expect("warnings don't fail");
+
+    let messages: Vec<_> = result.warnings.iter().map(|w| w.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        ["<{Card}> has no children, so it passes empty content; write <{Card} /> to pass none."; 2]
+    );
+    let warning = &result.warnings[0];
+    assert_eq!(
+        &source[warning.range.start.byte..warning.range.end.byte],
+        "<{Card}>"
+    );
+}
+
+#[test]
+fn empty_component_block_passes_empty_content() {
+    let source = "<{Card}>\n</{Card}>\n<{Card} />\n";
+    let code = compile(source, &CompileOptions::default()).unwrap().code;
+
+    assert!(
+        code.contains(
+            "    def _card_content():\n        yield from ()\n    yield from Card.stream(content=_card_content())\n"
+        ),
+        "{code}"
+    );
+    assert!(code.contains("    yield from Card.stream()\n"), "{code}");
+}
+
+#[test]
+fn control_flow_after_for_stays_inside_component_content() {
+    let source = "items: list\nx: int\n---\n<{List}>\n    for item in items:\n        <li>{item}</li>\n    end\n    if x == 1:\n        <li>a</li>\n    elif x == 2:\n        <li>b</li>\n    else:\n        <li>c</li>\n    end\n</{List}>\n";
+    let code = compile(source, &CompileOptions::default()).unwrap().code;
+
+    assert!(
+        code.contains(concat!(
+            "    def _list_content():\n",
+            "        for item in items:\n",
+            "            yield f\"\"\"<li>{escape(item)}</li>\"\"\"\n",
+            "        if x == 1:\n",
+            "            yield \"\"\"<li>a</li>\"\"\"\n",
+            "        elif x == 2:\n",
+            "            yield \"\"\"<li>b</li>\"\"\"\n",
+            "        else:\n",
+            "            yield \"\"\"<li>c</li>\"\"\"\n",
+            "    yield from List.stream(content=_list_content())\n",
+        )),
+        "{code}"
+    );
+    assert_eq!(code.matches("List.stream(").count(), 1, "{code}");
+}
+
+#[test]
+fn decorator_option_suppresses_component_decorator() {
+    let source = "title: str\n---\n<h1>{title}</h1>\n";
+    let options = CompileOptions {
+        function_name: Some("Page".to_string()),
+        decorator: None,
+        ..Default::default()
+    };
+
+    let result = compile(source, &options).unwrap();
+    assert!(result.code.starts_with("from hyperhtml import escape\n"));
+    assert!(!result.code.contains("@component"), "{}", result.code);
+    assert!(result.code.contains("\ndef Page("));
+}
+
+#[test]
+fn decorator_option_none_calls_defined_components_directly() {
+    let source = r#"from widgets import Badge
+---
+component Header(*, title: str):
+    <header>{title}</header>
+end
+
+<{Header} title="Site" />
+<{Badge} />
+"#;
+    let options = CompileOptions {
+        function_name: Some("Page".to_string()),
+        decorator: None,
+        ..Default::default()
+    };
+
+    let code = compile(source, &options).unwrap().code;
+    assert!(code.contains("\ndef Header("), "{code}");
+    assert!(code.contains("yield from Header(title=\"Site\")"), "{code}");
+    assert!(code.contains("yield from Badge.stream()"), "{code}");
+}
+
+#[test]
+fn decorator_option_swaps_in_custom_decorator() {
+    let source = r#"from layouts import layout
+---
+component Header():
+    <header>Site</header>
+end
+
+<{Header} />
+"#;
+    let options = CompileOptions {
+        function_name: Some("Page".to_string()),
+        decorator: Some("layout".to_string()),
+        ..Default::default()
+    };
+
+    let result = compile(source, &options).unwrap();
+    assert!(result.code.contains("@layout\ndef Header("));
+    assert!(result.code.contains("@layout\ndef Page("));
+    assert!(!result.code.contains("import component"), "{}", result.code);
+}
+
+#[test]
+fn decorator_option_accepts_leading_at() {
+    let source = r#"component Header():
+    <header>Site</header>
+end
+
+<{Header} />
+"#;
+    let compile_with = |decorator: &str| {
+        let options = CompileOptions {
+            function_name: Some("Page".to_string()),
+            decorator: Some(decorator.to_string()),
+            ..Default::default()
+        };
+        compile(source, &options).unwrap().code
+    };
+
+    let code = compile_with("@component");
+    assert_eq!(code, compile_with("component"));
+    assert!(
+        code.starts_with("from hyperhtml import component"),
+        "{}",
+        code
+    );
+    assert!(
+        code.contains("@component(subcomponents=[Header])\ndef Page("),
+        "{}",
+        code
+    );
+    assert!(!code.contains("@@"), "{}", code);
+}
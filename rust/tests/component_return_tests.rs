@@ -6,6 +6,7 @@ fn compile_source(source: &str) -> Result<String, String> {
         &CompileOptions {
             function_name: Some("Page".to_string()),
             include_ranges: false,
+            ..Default::default()
         },
     )
     .map(|result| result.code)
@@ -41,6 +42,41 @@ end
     assert!(error.contains("cannot return a value"));
 }
 
+#[test]
+fn bare_return_stops_template_rendering() {
+    let code = compile_source(
+        r#"user: str | None
+---
+if not user:
+    return
+end
+<p>{user}</p>
+"#,
+    )
+    .expect("bare return should compile");
+
+    assert!(
+        code.contains("    if not user:\n        return\n"),
+        "{code}"
+    );
+}
+
+#[test]
+fn template_return_value_is_rejected() {
+    let error = compile_source(
+        r#"user: str | None
+---
+if not user:
+    return "Nobody"
+end
+<p>{user}</p>
+"#,
+    )
+    .expect_err("return values should fail");
+
+    assert!(error.contains("cannot return a value"), "{error}");
+}
+
 #[test]
 fn component_yield_is_rejected() {
     let error = compile_source(
@@ -0,0 +1,44 @@
+from app.components import Badge, Card, Feed
+from hyperhtml import component, escape
+
+
+@component
+async def Avatar(
+        *,
+        user_id: int,
+):
+    yield f"""<img src="{escape(await avatar_url(user_id))}">"""
+
+
+@component
+async def Banner():
+    yield """<p>Welcome</p>"""
+
+
+@component
+async def Profile(
+        *,
+        user_id: int,
+):
+    async for _chunk in Avatar.stream(user_id=user_id):
+        yield _chunk
+    for _chunk in Badge.stream(label="Member"):
+        yield _chunk
+
+
+@component(subcomponents=[Avatar, Banner, Profile])
+async def AsyncCalls(
+        *,
+        user_id: int,
+):
+    async for _chunk in Banner.stream():
+        yield _chunk
+    # <{Card}>
+    async def _card_content():
+        async for _chunk in Profile.stream(user_id=user_id):
+            yield _chunk
+    async for _chunk in Card.stream(content=_card_content()):
+        yield _chunk
+    # </{Card}>
+    async for _chunk in Feed.stream(user_id=user_id):
+        yield _chunk
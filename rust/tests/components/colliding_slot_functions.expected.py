@@ -0,0 +1,25 @@
+from app.components import CardList, Card_list
+from hyperhtml import component
+
+
+@component
+def CollidingSlotFunctions():
+    # <{CardList}>
+    def _card_list_content():
+        yield """<p>First</p>"""
+    yield from CardList.stream(content=_card_list_content())
+    # </{CardList}>
+    # <{Card_list}>
+    def _card_list_content_2():
+        yield """<p>Second</p>"""
+    yield from Card_list.stream(content=_card_list_content_2())
+    # </{Card_list}>
+    # <{CardList}>
+    def _card_list_content_3():
+        # <{CardList}>
+        def _card_list_content():
+            yield """<p>Nested</p>"""
+        yield from CardList.stream(content=_card_list_content())
+        # </{CardList}>
+    yield from CardList.stream(content=_card_list_content_3())
+    # </{CardList}>
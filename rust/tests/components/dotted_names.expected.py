@@ -0,0 +1,19 @@
+from app import ui
+from hyperhtml import component
+
+
+@component
+def DottedNames():
+    # <{ui.Button}>
+    def _ui_button_content():
+        yield """Content"""
+    yield from ui.Button.stream(content=_ui_button_content(), size="lg")
+    # </{ui.Button}>
+    # <{ui.forms.TextField}>
+    def _ui_forms_text_field_content():
+        pass
+    def _ui_forms_text_field_label():
+        yield """Email"""
+    yield from ui.forms.TextField.stream(content=_ui_forms_text_field_content(), label=_ui_forms_text_field_label(), name="email")
+    # </{ui.forms.TextField}>
+    yield from ui.Icon.stream()
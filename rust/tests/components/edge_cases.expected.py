@@ -18,12 +18,16 @@ def EdgeCases(
     # </{components['Card']}>
 
     # Empty component (not self-closing)
-    yield from Wrapper.stream()
+    # <{Wrapper}>
+    def _wrapper_content():
+        yield from ()
+    yield from Wrapper.stream(content=_wrapper_content())
+    # </{Wrapper}>
 
     # Component with only whitespace
     # <{Container}>
     def _container_content():
-        pass
+        yield from ()
     yield from Container.stream(content=_container_content())
     # </{Container}>
 
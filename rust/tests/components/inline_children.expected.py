@@ -0,0 +1,28 @@
+from hyperhtml import component, escape
+
+
+@component
+def InlineChildren(
+        *,
+        name: str,
+):
+    # Children on the same line as the component tags
+    # <{Card}>
+    def _card_content():
+        yield f"""Hello {escape(name)}"""
+    yield from Card.stream(content=_card_content())
+    # </{Card}>
+
+    # Components inside other content on one line
+    yield """<p>"""
+    yield """Signed in as """
+    yield from Badge.stream(label=name)
+    yield """ today"""
+    yield """</p>"""
+    yield """<li>"""
+    # <{Link}>
+    def _link_content():
+        yield """Home"""
+    yield from Link.stream(content=_link_content(), href="/home")
+    # </{Link}>
+    yield """</li>"""
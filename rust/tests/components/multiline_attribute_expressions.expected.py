@@ -0,0 +1,24 @@
+from hyperhtml import component, escape, render_class, spread_attrs
+
+
+@component
+def MultilineAttributeExpressions(
+        *,
+        active: bool,
+        title: str,
+):
+    # Attribute expressions may span lines until their closing brace
+    yield f"""\
+<div class="{render_class("active" if active else "idle")}">Status</div>
+
+<p data-config="{escape({"a": 1,
+     "b": title})}">Config</p>"""
+
+    # Component props and spreads too
+    yield from Card.stream(title=
+    title
+)
+
+    yield f"""\
+<div{spread_attrs({
+    "id": title})}>Spread</div>"""
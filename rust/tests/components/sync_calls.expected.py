@@ -0,0 +1,11 @@
+from app.components import Badge, Card
+from hyperhtml import component
+
+
+@component
+def SyncCalls():
+    # <{Card}>
+    def _card_content():
+        yield from Badge.stream(label="New")
+    yield from Card.stream(content=_card_content())
+    # </{Card}>
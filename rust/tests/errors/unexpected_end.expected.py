@@ -1,6 +0,0 @@
-from hyperhtml import component
-
-
-@component
-def UnexpectedEnd():
-    yield """<div>Content</div>"""
@@ -76,6 +76,7 @@ fn run_output_test(path: &PathBuf) -> Result<(), Failed> {
     let options = CompileOptions {
         function_name: Some(name.to_string()),
         include_ranges: false,
+        ..Default::default()
     };
 
     match compile(&source, &options) {
@@ -109,6 +110,7 @@ fn run_injection_test(path: &PathBuf) -> Result<(), Failed> {
     let options = CompileOptions {
         function_name: Some(name.to_string()),
         include_ranges: true,
+        ..Default::default()
     };
 
     match compile(&source, &options) {
@@ -147,6 +149,7 @@ fn run_error_test(path: &PathBuf) -> Result<(), Failed> {
     let options = CompileOptions {
         function_name: Some(name.to_string()),
         include_ranges: false,
+        ..Default::default()
     };
 
     match compile(&source, &options) {
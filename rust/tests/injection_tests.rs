@@ -1,6 +1,6 @@
 mod common;
 
-use common::{compile_with_ranges, html_segments, python_segments};
+use common::{compile, compile_with_ranges, html_segments, python_segments, template_segments};
 use hyper::CompileOptions;
 use hyper::generate::Language;
 
@@ -210,6 +210,16 @@ fn test_shorthand_attribute_segment() {
     assert_eq!(source_text, "disabled");
 }
 
+#[test]
+fn test_renamed_shorthand_is_not_injected() {
+    // `{data-user-id}` reads `data_user_id` and `{class}` reads `class_`, so
+    // neither source name is the Python code
+    let source = r#"<div {data-user-id} {class}>Content</div>"#;
+    let result = compile_with_ranges(source, "Test");
+
+    assert!(python_segments(&result).is_empty(), "{:?}", result.segments);
+}
+
 #[test]
 fn test_all_expression_contexts() {
     let source = r#"name: str
@@ -307,6 +317,29 @@ fn test_text_expression_range_excludes_braces() {
     );
 }
 
+#[test]
+fn test_expression_range_after_astral_character_is_utf16() {
+    // 🎉 is one char, four UTF-8 bytes, and two UTF-16 code units
+    let source = "<p title=\"🎉\">🎉 {name}</p>";
+    let result = compile_with_ranges(source, "Test");
+
+    let source_utf16: Vec<u16> = source.encode_utf16().collect();
+    let compiled_utf16: Vec<u16> = result.code.encode_utf16().collect();
+    let py = python_segments(&result);
+    assert_eq!(py.len(), 1);
+
+    let seg = py[0];
+    let source_text = String::from_utf16(&source_utf16[seg.source_start..seg.source_end]).unwrap();
+    let compiled_text =
+        String::from_utf16(&compiled_utf16[seg.compiled_start..seg.compiled_end]).unwrap();
+    assert_eq!(source_text, "name");
+    assert_eq!(compiled_text, "name");
+
+    let braces = &result.expression_braces[0];
+    assert_eq!(source_utf16[braces.open], u16::from(b'{'));
+    assert_eq!(source_utf16[braces.close], u16::from(b'}'));
+}
+
 #[test]
 fn test_complex_expression_range_excludes_braces() {
     let source = r#"<div>{count + 1}</div>"#;
@@ -363,6 +396,38 @@ fn test_error_has_position_for_nested_interactive() {
     }
 }
 
+#[test]
+fn test_block_in_paragraph_points_at_paragraph() {
+    let source = "<p>\n    <div>x</div>\n</p>\n";
+
+    match hyper::compile(source, &CompileOptions::default()).unwrap_err() {
+        hyper::CompileError::Parse(parse_err) => {
+            assert_eq!(parse_err.kind, hyper::error::ErrorKind::InvalidNesting);
+            assert_eq!(parse_err.range.start.line, 1);
+            let related = parse_err.related_range.expect("should point at the <p>");
+            assert_eq!((related.start.line, related.start.col), (0, 0));
+        }
+        err => panic!("Expected ParseError, got {:?}", err),
+    }
+}
+
+#[test]
+fn test_valid_nesting_compiles() {
+    for source in [
+        "<ul>\n    <li>One</li>\n</ul>\n",
+        "<ol>\n    for item in items:\n        <li>{item}</li>\n    end\n</ol>\n",
+        "<table><tr><th>A</th><td>1</td></tr></table>\n",
+        // The parent of a component's root or slotted children isn't known here
+        "<li>Item</li>\n",
+        "<div>\n    <{List}>\n        <li>Item</li>\n    </{List}>\n</div>\n",
+    ] {
+        assert!(
+            hyper::compile(source, &CompileOptions::default()).is_ok(),
+            "should compile:\n{source}"
+        );
+    }
+}
+
 #[test]
 fn test_error_has_position_for_duplicate_attribute() {
     let source = r#"<div class={x} class={y}>text</div>"#;
@@ -406,6 +471,116 @@ fn test_html_range_source_text_simple() {
     }
 }
 
+#[test]
+fn test_element_with_expression_has_python_and_html_ranges() {
+    let source = "<div>Hello {name}</div>";
+    let result = compile_with_ranges(source, "Test");
+
+    let python: Vec<_> = python_segments(&result)
+        .iter()
+        .map(|s| &source[s.source_start..s.source_end])
+        .collect();
+    assert_eq!(python, ["name"]);
+
+    let html: Vec<_> = html_segments(&result)
+        .iter()
+        .map(|s| &source[s.source_start..s.source_end])
+        .collect();
+    assert_eq!(html, ["<div>", "</div>"]);
+}
+
+#[test]
+fn test_raw_expression_skips_escape() {
+    let source = "<div>{!html}</div>\n<div>{html}</div>";
+    let result = compile_with_ranges(source, "Test");
+
+    assert!(
+        result
+            .code
+            .contains("<div>{html}</div>\n<div>{escape(html)}</div>"),
+        "{{!html}} should be unescaped and {{html}} escaped. Got:\n{}",
+        result.code
+    );
+    let python: Vec<_> = python_segments(&result)
+        .iter()
+        .map(|s| &source[s.source_start..s.source_end])
+        .collect();
+    assert_eq!(python, ["html", "html"]);
+}
+
+#[test]
+fn test_text_entities_pass_through() {
+    let source = r#"<p>&copy; {name} &amp; &#169;</p>
+<a title="&quot;{t}&quot;">x</a>"#;
+    let code = compile(source);
+
+    assert!(
+        code.contains(r#"<p>&copy; {escape(name)} &amp; &#169;</p>"#),
+        "Entities in text should stay literal; only {{name}} is escaped. Got:\n{code}"
+    );
+    assert!(
+        code.contains(r#"<a title="&quot;{escape(t)}&quot;">"#),
+        "Entities in attribute values should stay literal. Got:\n{code}"
+    );
+}
+
+#[test]
+fn test_attribute_expressions_are_escaped() {
+    let source = r#"<a title={x} class={x} style={x} hidden={x}>t</a>"#;
+    let code = compile(source);
+
+    assert!(
+        code.contains(
+            r#"<a title="{escape(x)}" class="{render_class(x)}" style="{render_style(x)}"{render_attr("hidden", x)}>"#
+        ),
+        "title should be escaped; class, style and boolean attributes keep their helpers. Got:\n{code}"
+    );
+}
+
+#[test]
+fn test_optional_attribute_renders_conditionally() {
+    let source = r#"<a data-x?={value} class?={extra}>t</a>"#;
+    let result = compile_with_ranges(source, "Test");
+
+    assert!(
+        result.code.contains(
+            r#"<a{render_optional_attr("data-x", value)}{render_optional_attr("class", extra)}>"#
+        ),
+        "Optional attributes should go through render_optional_attr. Got:\n{}",
+        result.code
+    );
+    assert!(
+        result
+            .code
+            .contains("import component, render_optional_attr"),
+        "{}",
+        result.code
+    );
+    let mapped: Vec<_> = python_segments(&result)
+        .iter()
+        .map(|s| &source[s.source_start..s.source_end])
+        .collect();
+    assert_eq!(mapped, ["value", "extra"]);
+}
+
+#[test]
+fn test_raw_attribute_expression_skips_escape() {
+    let source = r#"<a title={!x} href="/{!y}">t</a>"#;
+    let result = compile_with_ranges(source, "Test");
+
+    assert!(
+        result.code.contains(r#"<a title="{x}" href="/{y}">"#),
+        "{{!x}} in an attribute should be unescaped. Got:\n{}",
+        result.code
+    );
+    assert!(!result.code.contains("escape"), "{}", result.code);
+    let python: Vec<_> = python_segments(&result)
+        .iter()
+        .map(|s| &source[s.source_start..s.source_end])
+        .collect();
+    assert_eq!(python, ["x", "y"]);
+}
+
 #[test]
 fn test_html_range_source_text_with_attributes() {
     let source = r#"<div class={active} id="main">Content</div>"#;
@@ -587,6 +762,29 @@ fn test_except_clause_has_python_range() {
     );
 }
 
+#[test]
+fn test_except_tuple_range_includes_binding() {
+    let source =
+        "try:\n    {x}\nexcept (ValueError, KeyError) as e:\n    {e}\nexcept:\n    {x}\nend";
+    let result = compile_with_ranges(source, "Test");
+
+    let py = python_segments(&result);
+    let texts: Vec<_> = py
+        .iter()
+        .map(|r| &source[r.source_start..r.source_end])
+        .collect();
+    assert!(
+        texts.contains(&"(ValueError, KeyError) as e"),
+        "except range should cover the types and the binding. Ranges: {:?}",
+        texts
+    );
+    assert!(
+        !texts.iter().any(|text| text.ends_with(':')),
+        "except range should stop before the colon. Ranges: {:?}",
+        texts
+    );
+}
+
 #[test]
 fn test_html_ranges_basic() {
     let source = "<div>Hello</div>";
@@ -1059,6 +1257,59 @@ fn test_dict_in_attribute_expression() {
     );
 }
 
+#[test]
+fn test_conditional_class_list_is_one_render_class_call() {
+    let source = r#"<div class={["a", cond and "b"]}>text</div>"#;
+    let result = compile_with_ranges(source, "Test");
+
+    assert!(
+        result
+            .code
+            .contains(r#"<div class="{render_class(["a", cond and "b"])}">"#),
+        "Got:\n{}",
+        result.code
+    );
+    assert_eq!(result.code.matches("render_class(").count(), 1);
+    let python: Vec<_> = python_segments(&result)
+        .iter()
+        .map(|s| &source[s.source_start..s.source_end])
+        .collect();
+    assert_eq!(python, [r#"["a", cond and "b"]"#]);
+}
+
+#[test]
+fn test_class_dict_literal_range_covers_whole_dict() {
+    let source = r#"<div class={{"active": flag}}>text</div>"#;
+    let result = compile_with_ranges(source, "Test");
+
+    assert!(
+        result
+            .code
+            .contains(r#"<div class="{render_class({"active": flag})}">"#),
+        "The `:` in a dict is not a format spec. Got:\n{}",
+        result.code
+    );
+    let python: Vec<_> = python_segments(&result)
+        .iter()
+        .map(|s| &source[s.source_start..s.source_end])
+        .collect();
+    assert_eq!(python, [r#"{"active": flag}"#]);
+}
+
+#[test]
+fn test_class_dict_renames_reserved_keywords() {
+    let source = "class: str\n---\n<div class={{\"x\": class, \"class\": flag}}>text</div>";
+    let result = compile_with_ranges(source, "Test");
+
+    assert!(
+        result
+            .code
+            .contains(r#"render_class({"x": class_, "class": flag})"#),
+        "Got:\n{}",
+        result.code
+    );
+}
+
 #[test]
 fn test_string_with_brace_in_attribute_expression() {
     let source = r#"<div class={get_class("}")}>text</div>"#;
@@ -1101,6 +1352,20 @@ fn test_shorthand_on_html_element() {
     );
 }
 
+#[test]
+fn test_boolean_attribute_expression_uses_render_attr() {
+    let source = r#"<button disabled={flag} title={label}>Click</button>"#;
+    let result = compile_with_ranges(source, "Test");
+
+    assert!(
+        result
+            .code
+            .contains(r#"<button{render_attr("disabled", flag)} title="{escape(label)}">"#),
+        "Boolean attribute should render via render_attr, others as name=\"value\". Got:\n{}",
+        result.code
+    );
+}
+
 #[test]
 fn test_shorthand_on_component() {
     let source = r#"<{Button} {disabled} />"#;
@@ -1397,3 +1662,86 @@ fn test_component_no_attrs_has_tag_prefix() {
             .collect::<Vec<_>>()
     );
 }
+
+#[test]
+fn test_crlf_source_maps_like_lf() {
+    let lf = "items: list\n---\n<ul class={[\n    \"a\",\n]}>\n    for item in items:\n        <li>{\n            item\n        }</li>\n    end\n</ul>\n";
+    let crlf = lf.replace('\n', "\r\n");
+    let lf_result = compile_with_ranges(lf, "Test");
+    let crlf_result = compile_with_ranges(&crlf, "Test");
+
+    assert_eq!(crlf_result.code, lf_result.code);
+    let texts = |source: &str, result: &hyper::CompileResult| -> Vec<String> {
+        python_segments(result)
+            .iter()
+            .map(|s| source[s.source_start..s.source_end].replace("\r\n", "\n"))
+            .collect()
+    };
+    assert_eq!(texts(&crlf, &crlf_result), texts(lf, &lf_result));
+}
+
+#[test]
+fn test_text_between_expressions_has_template_segment() {
+    let source = "a: str\nb: str\n---\n<p>{a} and then {b}</p>\n";
+    let result = compile_with_ranges(source, "Test");
+
+    let template = template_segments(&result);
+    assert_eq!(template.len(), 1, "{template:?}");
+    let segment = template[0];
+    assert_eq!(
+        &source[segment.source_start..segment.source_end],
+        "and then"
+    );
+    assert_eq!(
+        &result.code[segment.compiled_start..segment.compiled_end],
+        "and then"
+    );
+    assert!(!segment.needs_injection);
+}
+
+#[test]
+fn test_touching_segments_are_coalesced() {
+    use hyper::generate::{Output, Segment};
+
+    let segment = |language, source_start, source_end, compiled_start, compiled_end| Segment {
+        language,
+        source_start,
+        source_end,
+        compiled_start,
+        compiled_end,
+        needs_injection: true,
+        html_prefix: None,
+        bytes: None,
+    };
+    let mut output = Output::new();
+    // Three expressions whose spans touch in both source and compiled code
+    output.add_segment(segment(Language::Python, 10, 13, 40, 43));
+    output.add_segment(segment(Language::Python, 13, 15, 43, 45));
+    output.add_segment(segment(Language::Python, 15, 20, 45, 50));
+    // Touches in source only
+    output.add_segment(segment(Language::Python, 20, 22, 60, 62));
+    // Touches, but is HTML
+    output.add_segment(segment(Language::Html, 22, 25, 62, 65));
+
+    let (_, segments) = output.finish();
+    let spans: Vec<_> = segments
+        .iter()
+        .map(|s| {
+            (
+                s.language,
+                s.source_start,
+                s.source_end,
+                s.compiled_start,
+                s.compiled_end,
+            )
+        })
+        .collect();
+    assert_eq!(
+        spans,
+        vec![
+            (Language::Python, 10, 20, 40, 50),
+            (Language::Python, 20, 22, 60, 62),
+            (Language::Html, 22, 25, 62, 65),
+        ]
+    );
+}
@@ -1,15 +1,16 @@
 use crate::helpers::{byte_to_utf16, compile};
 use hyper::generate::Language;
 use hyper::parse::tokenizer::{Token, tokenize};
-use hyper::plugins::rename_reserved_keywords;
+use hyper::plugins::{lower_ternary, rename_reserved_keywords};
 use libtest_mimic::Failed;
 use std::fs;
 use std::path::PathBuf;
 
-/// True when the generator renames the expression (`class` to `class_`), so its
-/// injection range is intentionally dropped (source differs from compiled).
+/// True when the generator rewrites the expression (`class` to `class_`, or
+/// `a ? b : c` to `(b) if (a) else (c)`), so its injection range is
+/// intentionally dropped (source differs from compiled).
 fn is_renamed(expr: &str) -> bool {
-    rename_reserved_keywords(expr) != expr
+    rename_reserved_keywords(expr) != expr || lower_ternary(expr).is_some()
 }
 
 pub fn run(path: &PathBuf) -> Result<(), Failed> {
@@ -52,6 +53,35 @@ pub fn run(path: &PathBuf) -> Result<(), Failed> {
         start + trimmed.len()
     }
 
+    /// Trim an f-string format spec or conversion (`x:.2f`, `x!r`) from an
+    /// interpolation: only the expression before it is Python code.
+    fn trim_format_suffix(source: &str, start: usize, end: usize) -> usize {
+        let text = &source[start..end];
+        let mut depth = 0;
+        let mut quote = None;
+        let mut expr_end = text.len();
+        for (i, ch) in text.char_indices() {
+            match (quote, ch) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(ch),
+                (None, '(' | '[' | '{') => depth += 1,
+                (None, ')' | ']' | '}') => depth -= 1,
+                (None, ':') if depth == 0 => {
+                    expr_end = i;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let expr = text[..expr_end].trim_end();
+        let expr = ["!r", "!s", "!a"]
+            .iter()
+            .find_map(|flag| expr.strip_suffix(flag))
+            .unwrap_or(expr);
+        start + expr.len()
+    }
+
     for token in &tokens {
         match token {
             Token::Decorator { range, .. }
@@ -82,15 +112,36 @@ pub fn run(path: &PathBuf) -> Result<(), Failed> {
                 )
                 .into());
             }
-            Token::Expression { range, .. } if in_body(range.start.byte) => {
+            Token::Expression {
+                range,
+                raw,
+                trim_left,
+                trim_right,
+                ..
+            } if in_body(range.start.byte) => {
                 // Skip slot expressions ({...} / {...name}) — tokenizer converts
                 // these to {children} / {children_name} but source still has "..."
                 let inner = &source[range.start.byte + 1..range.end.byte - 1];
                 if inner.trim().starts_with("...") {
                     continue;
                 }
-                let inner_start = range.start.byte + 1;
-                let inner_end = range.end.byte - 1;
+                // Raw ({!x}) and whitespace-control ({- x -}) markers are not
+                // part of the code, nor is the whitespace after `{-` or before `-}`
+                let mut inner_start =
+                    range.start.byte + 1 + usize::from(*raw) + usize::from(*trim_left);
+                let mut inner_end = range.end.byte - 1 - usize::from(*trim_right);
+                if *trim_left {
+                    let code = &source[inner_start..inner_end];
+                    inner_start += code.len() - code.trim_start().len();
+                }
+                if *trim_right {
+                    let code = &source[inner_start..inner_end];
+                    inner_end -= code.len() - code.trim_end().len();
+                }
+                if is_renamed(&source[inner_start..inner_end]) {
+                    continue;
+                }
+                let inner_end = trim_format_suffix(&source, inner_start, inner_end);
                 if inner_start < inner_end && !is_covered(inner_start, inner_end) {
                     return Err(format!(
                         "expression at [{},{}] has no Python range: {:?}",
@@ -177,12 +228,21 @@ pub fn run(path: &PathBuf) -> Result<(), Failed> {
                     use hyper::parse::tokenizer::AttributeValue;
                     // Get (inner_start, inner_end) excluding delimiters
                     let inner = match &attr.value {
-                        // class={expr}: inner skips { and }.
+                        // class={expr}: the range already excludes the braces,
+                        // and the whitespace around a multi-line expression
+                        // isn't code.
                         AttributeValue::Expression(expr, s) if !is_renamed(expr) => {
-                            Some((s.start.byte + 1, s.end.byte - 1))
+                            let start = s.start.byte + usize::from(expr.starts_with('!'));
+                            let text = &source[start..s.end.byte];
+                            let start = start + text.len() - text.trim_start().len();
+                            let end = s.end.byte - (text.len() - text.trim_end().len());
+                            Some((start, trim_format_suffix(&source, start, end)))
                         }
                         // {name}: inner skips { (range.end is before }, so no -1).
-                        AttributeValue::Shorthand(name, s) if !is_renamed(name) => {
+                        // `{data-id}` reads `data_id`, a rename like `{class}`.
+                        AttributeValue::Shorthand(name, s)
+                            if !is_renamed(name) && !name.contains('-') =>
+                        {
                             Some((s.start.byte + 1, s.end.byte))
                         }
                         _ => None,
@@ -14,6 +14,7 @@ pub fn compile(path: &PathBuf) -> Result<CompileResult, Failed> {
     let options = CompileOptions {
         function_name: Some(name.to_string()),
         include_ranges: true,
+        ..Default::default()
     };
 
     hyper::compile(&source, &options).map_err(|e| format!("Compile error: {}", e).into())
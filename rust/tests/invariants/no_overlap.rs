@@ -6,10 +6,11 @@ use std::path::PathBuf;
 pub fn run(path: &PathBuf) -> Result<(), Failed> {
     let result = compile(path)?;
 
-    for language in [Language::Python, Language::Html] {
+    for language in [Language::Python, Language::Html, Language::Template] {
         let type_name = match language {
             Language::Python => "Python",
             Language::Html => "HTML",
+            Language::Template => "Template",
         };
 
         let mut typed: Vec<_> = result
@@ -491,8 +491,8 @@ Text after elements"""
 <span>{escape(items[0])}</span>
 <span>{escape(items[1:3])}</span>
 <span>{escape(len(items))}</span>
-<span>{count:03d}</span>
-<span>{3.14159:.2f}</span>"""
+<span>{escape(format(count, "03d"))}</span>
+<span>{escape(format(3.14159, ".2f"))}</span>"""
 
 
     ########################################
@@ -564,7 +564,7 @@ Text after elements"""
     yield f"""\
 <span>{escape("positive" if value > 0 else "zero" if value == 0 else "negative")}</span>
 <span>{value=}</span>
-<span>{items!r}</span>"""
+<span>{escape(repr(items))}</span>"""
 
 
     ########################################
@@ -1,6 +1,6 @@
 mod common;
 
-use common::compile;
+use common::{compile, compile_with_ranges, python_segments};
 
 #[test]
 fn test_selective_helper_imports() {
@@ -32,6 +32,41 @@ end"#;
     assert!(code.contains("async def Render"));
 }
 
+#[test]
+fn test_await_scope_decides_async() {
+    let cases = [
+        // Top level, a control-flow body and an attribute run in the function
+        ("<p>{await load()}</p>", true),
+        (
+            "for item in items:\n    <p>{await item.load()}</p>\nend",
+            true,
+        ),
+        ("<a href={await url()}>x</a>", true),
+        ("<{Card} title={await title()} />", true),
+        // A nested def runs elsewhere
+        (
+            "def helper():\n    return await load()\nend\n<p>{helper}</p>",
+            false,
+        ),
+    ];
+    for (source, is_async) in cases {
+        let code = compile(source);
+        assert_eq!(
+            code.contains("async def Render"),
+            is_async,
+            "{source}\n---\n{code}"
+        );
+    }
+
+    // Component children run in their own generator, which awaiting makes
+    // async. Only an async component can read it.
+    let code = compile("<{Card} async>\n    <p>{await load()}</p>\n</{Card}>");
+    assert!(code.contains("async def _card_content():"), "{code}");
+    let source = "<{Card}>\n    <p>{await load()}</p>\n</{Card}>";
+    let err = hyper::compile(source, &hyper::CompileOptions::default()).unwrap_err();
+    assert!(err.to_string().contains("isn't"), "{err}");
+}
+
 #[test]
 fn test_non_async_template() {
     // Template without await/async
@@ -124,3 +159,757 @@ end"#;
     assert!(code.contains("url: str"));
     assert!(code.contains("items: list"));
 }
+
+#[test]
+fn test_public_api_streams_with_yield() {
+    // Every public entry point emits a generator; there is no buffered
+    // `_parts.append(...)` / `"".join(_parts)` mode.
+    let source = "name: str\n---\n<p>Hello {name}</p>\n";
+
+    let code = hyper::compile_to_python(source, Some("greeting.hyper")).unwrap();
+    assert!(code.contains("def Greeting("));
+    assert!(code.contains("yield f\"\"\"<p>Hello {escape(name)}</p>\"\"\""));
+    assert!(!code.contains("_parts"));
+
+    // Mappings are populated alongside streamed output
+    let result = compile_with_ranges(source, "Greeting");
+    assert!(result.code.contains("yield"));
+    assert!(!python_segments(&result).is_empty());
+}
+
+#[test]
+fn test_custom_indent_unit() {
+    let source = "items: list\n---\nfor item in items:\n    if item:\n        <li>{item}</li>\n    end\nend\n";
+    let options = hyper::CompileOptions {
+        indent: "  ".to_string(),
+        ..Default::default()
+    };
+    let code = hyper::compile(source, &options).unwrap().code;
+
+    assert!(code.contains("\n    *,\n    items: list,\n"), "{code}");
+    assert!(
+        code.contains("\n  for item in items:\n    if item:\n      yield f"),
+        "{code}"
+    );
+    assert!(
+        !code.contains("        "),
+        "no four-space levels remain:\n{code}"
+    );
+}
+
+#[test]
+fn test_banner_precedes_imports_and_keeps_ranges() {
+    let source = "import os\nname: str\n---\n<p>{name} {os.sep}</p>\n";
+    let banner = "# Generated by hyper from café.hyper. Do not edit.";
+    let options = hyper::CompileOptions {
+        include_ranges: true,
+        banner: Some(banner.to_string()),
+        ..Default::default()
+    };
+    let result = hyper::compile(source, &options).unwrap();
+
+    assert!(
+        result.code.starts_with(&format!("{banner}\nimport os\n")),
+        "{}",
+        result.code
+    );
+    let compiled: Vec<u16> = result.code.encode_utf16().collect();
+    let mapped: Vec<_> = python_segments(&result)
+        .iter()
+        .map(|s| String::from_utf16(&compiled[s.compiled_start..s.compiled_end]).unwrap())
+        .collect();
+    assert_eq!(mapped, ["import os", "name: str", "name", "os.sep"]);
+}
+
+#[test]
+fn test_segment_byte_offsets_slice_source_and_compiled() {
+    let source = "名前: str\n---\n<p>🎉 {名前.upper()}</p>\n";
+    let result = compile_with_ranges(source, "Greeting");
+
+    let segment = python_segments(&result)
+        .into_iter()
+        .find(|s| {
+            s.bytes
+                .is_some_and(|b| &source[b.source_byte_start..b.source_byte_end] == "名前.upper()")
+        })
+        .expect("expression segment");
+    let bytes = segment.bytes.unwrap();
+    assert_eq!(
+        &result.code[bytes.compiled_byte_start..bytes.compiled_byte_end],
+        "名前.upper()"
+    );
+    // UTF-16 offsets differ from the byte ones for the same span
+    assert_ne!(segment.source_start, bytes.source_byte_start);
+    assert_ne!(segment.compiled_start, bytes.compiled_byte_start);
+}
+
+#[test]
+fn test_trailing_newline_option() {
+    let sources = [
+        "<p>Hi</p>\n",
+        "<p>Hi</p>",
+        "<p>Hi</p>\n\n\n",
+        "component Card():\n    <div>Card</div>\nend\n",
+        "component Card():\n    <div>Card</div>\nend",
+    ];
+    for source in sources {
+        for trailing_newline in [true, false] {
+            let options = hyper::CompileOptions {
+                trailing_newline,
+                ..Default::default()
+            };
+            let code = hyper::compile(source, &options).unwrap().code;
+            let body = code.trim_end_matches('\n');
+
+            assert!(!body.is_empty());
+            if trailing_newline {
+                assert_eq!(code, format!("{body}\n"), "{source:?}");
+            } else {
+                assert_eq!(code, body, "{source:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_map_generated_to_source() {
+    use hyper::generate::map_generated_to_source;
+
+    let source = "items: list\n---\nfor item in items:\n    <li>{item.name}</li>\nend\n";
+    let result = compile_with_ranges(source, "List");
+    let lookup =
+        |line, col| map_generated_to_source(source, &result.code, &result.segments, line, col);
+    let (yield_line, yield_text) = result
+        .code
+        .lines()
+        .enumerate()
+        .find(|(_, line)| line.contains("yield"))
+        .unwrap();
+    let expr_col = yield_text.find("item.name").unwrap();
+
+    // Exact: the start of `item.name`, and a character inside it
+    assert_eq!(lookup(yield_line, expr_col), Some((3, 9)));
+    assert_eq!(lookup(yield_line, expr_col + 5), Some((3, 14)));
+    // Between: `yield` precedes any segment on its line, so it maps to the
+    // first one after it rather than the loop header on the line above
+    assert_eq!(lookup(yield_line, 0), Some((3, 9)));
+    // Out of range: before the first segment, past the last line or column
+    assert_eq!(lookup(0, 0), None);
+    assert_eq!(lookup(result.code.lines().count() + 1, 0), None);
+    assert_eq!(lookup(yield_line, yield_text.len() + 1), None);
+}
+
+#[test]
+fn test_map_generated_to_source_component_call() {
+    use hyper::generate::map_generated_to_source;
+
+    let source = "from app import Card\n---\n<p>Intro</p>\n<{Card} title=\"Hi\">\n    <p>Body</p>\n</{Card}>\n";
+    let result = compile_with_ranges(source, "Page");
+    let lookup =
+        |line, col| map_generated_to_source(source, &result.code, &result.segments, line, col);
+    let (call_line, _) = result
+        .code
+        .lines()
+        .enumerate()
+        .find(|(_, line)| line.contains("Card.stream("))
+        .unwrap();
+
+    // The whole generated call line points at the component tag
+    assert_eq!(lookup(call_line, 0), Some((3, 2)));
+}
+
+#[test]
+fn test_map_generated_to_source_multiline_content() {
+    use hyper::generate::map_generated_to_source;
+
+    // One yielded string spans all five lines; each expression keeps its own line
+    let source = "<ul>\n    <li>{first}</li>\n    <li>{second}</li>\n    <li>{third}</li>\n</ul>\n";
+    let result = compile_with_ranges(source, "List");
+    let lines: Vec<_> = python_segments(&result)
+        .iter()
+        .map(|s| source[..s.source_start].matches('\n').count())
+        .collect();
+    assert_eq!(lines, [1, 2, 3]);
+
+    for (name, source_line) in [("first", 1), ("second", 2), ("third", 3)] {
+        let (gen_line, gen_text) = result
+            .code
+            .lines()
+            .enumerate()
+            .find(|(_, line)| line.contains(&format!("escape({name})")))
+            .unwrap();
+        let gen_col = gen_text.find(name).unwrap();
+        assert_eq!(
+            map_generated_to_source(source, &result.code, &result.segments, gen_line, gen_col),
+            Some((source_line, 9)),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn test_parse_errors_are_structured() {
+    use hyper::error::ErrorKind;
+
+    let err = match hyper::compile("<p><div>x</div></p>\n", &Default::default()) {
+        Err(hyper::CompileError::Parse(err)) => err,
+        other => panic!("expected a parse error, got {other:?}"),
+    };
+    assert_eq!(err.kind, ErrorKind::InvalidNesting);
+
+    let json = serde_json::to_value(&*err).unwrap();
+    assert_eq!(json["kind"], "InvalidNesting");
+    assert_eq!(json["range"]["start"]["col"], 3);
+    assert!(json["help"].is_string());
+}
+
+#[test]
+fn test_wrap_in_class() {
+    let options = hyper::CompileOptions {
+        wrap_in_class: Some("Page".to_string()),
+        ..Default::default()
+    };
+    let source = "title: str\n---\n<h1>{title}</h1>\n";
+    let code = hyper::compile(source, &options).unwrap().code;
+    assert_eq!(
+        code,
+        "from hyperhtml import component, escape\n\
+         \n\
+         \n\
+         class Page:\n\
+         \x20   @component\n\
+         \x20   def render(\n\
+         \x20           self,\n\
+         \x20           *,\n\
+         \x20           title: str,\n\
+         \x20   ):\n\
+         \x20       yield f\"\"\"<h1>{escape(title)}</h1>\"\"\"\n"
+    );
+
+    let code = hyper::compile("<p>Hi</p>\n", &options).unwrap().code;
+    assert!(
+        code.contains("class Page:\n    @component\n    def render(self):\n        yield"),
+        "{code}"
+    );
+
+    let options = hyper::CompileOptions {
+        stub: true,
+        ..options
+    };
+    let stub = hyper::compile(source, &options).unwrap().stub.unwrap();
+    assert!(
+        stub.ends_with(
+            "class _PageRender(Protocol):\n\
+             \x20   def __call__(self, *, title: str) -> HtmlResult: ...\n\
+             \x20   def stream(self, *, title: str) -> Iterable[str]: ...\n\
+             \n\
+             \n\
+             class Page:\n\
+             \x20   render: _PageRender\n"
+        ),
+        "{stub}"
+    );
+}
+
+#[test]
+fn test_stub() {
+    let options = hyper::CompileOptions {
+        function_name: Some("card".to_string()),
+        stub: true,
+        ..Default::default()
+    };
+    let source = "from datetime import date\ntitle: str\nwhen: date | None = None\n---\n<h1>{title} {when}</h1>\n{...}\n";
+    let stub = hyper::compile(source, &options).unwrap().stub.unwrap();
+    assert_eq!(
+        stub,
+        "from datetime import date\n\
+         from typing import Protocol\n\
+         from collections.abc import Iterable\n\
+         from hyperhtml.decorators import HtmlResult\n\
+         \n\
+         \n\
+         class _Card(Protocol):\n\
+         \x20   def __call__(self, *, title: str, when: date | None = ..., content: Iterable[str] | None = ...) -> HtmlResult: ...\n\
+         \x20   def stream(self, *, title: str, when: date | None = ..., content: Iterable[str] | None = ...) -> Iterable[str]: ...\n\
+         \n\
+         \n\
+         Card: _Card\n"
+    );
+
+    let result = hyper::compile(source, &hyper::CompileOptions::default()).unwrap();
+    assert!(result.stub.is_none());
+}
+
+#[test]
+fn test_stub_of_async_and_defined_components() {
+    let options = hyper::CompileOptions {
+        stub: true,
+        ..Default::default()
+    };
+    let source = "component Badge(*, label: str, **attrs):\n    <span {**attrs}>{label}</span>\nend\n\ncomponent Feed():\n    for item in await load():\n        <p>{item}</p>\n    end\nend\n";
+    let stub = hyper::compile(source, &options).unwrap().stub.unwrap();
+    assert!(
+        stub.contains("def stream(self, *, label: str, **attrs) -> Iterable[str]: ...\n"),
+        "{stub}"
+    );
+    assert!(
+        stub.contains("def __call__(self) -> Awaitable[HtmlResult]: ...\n"),
+        "{stub}"
+    );
+    assert!(
+        stub.contains("def stream(self) -> AsyncIterable[str]: ...\n"),
+        "{stub}"
+    );
+    assert!(
+        stub.contains("from collections.abc import AsyncIterable, Awaitable, Iterable\n"),
+        "{stub}"
+    );
+    assert!(stub.contains("\nBadge: _Badge\n\n"), "{stub}");
+}
+
+#[test]
+fn test_indent_must_be_whitespace() {
+    for indent in ["", "--", " x"] {
+        let options = hyper::CompileOptions {
+            indent: indent.to_string(),
+            ..Default::default()
+        };
+        let Err(hyper::CompileError::Generate(message)) = hyper::compile("<p>Hi</p>", &options)
+        else {
+            panic!("indent {indent:?} should be rejected");
+        };
+        assert!(message.contains("indent"), "{message}");
+    }
+}
+
+#[test]
+fn test_strict_indent_flags_mixed_tabs_and_spaces() {
+    let source = "<ul>\n    <li>a</li>\n \t<li>b</li>\n\t\t<li>c</li>\n</ul>\n";
+
+    let lenient = hyper::compile(source, &hyper::CompileOptions::default()).unwrap();
+    assert!(lenient.warnings.is_empty(), "{:?}", lenient.warnings);
+
+    let options = hyper::CompileOptions {
+        strict_indent: true,
+        ..Default::default()
+    };
+    let strict = hyper::compile(source, &options).unwrap();
+    assert_eq!(strict.code, lenient.code);
+    assert_eq!(strict.warnings.len(), 1, "{:?}", strict.warnings);
+    let warning = &strict.warnings[0];
+    assert_eq!(
+        warning.message,
+        "This line's indentation mixes tabs and spaces."
+    );
+    assert_eq!(warning.range.start.line, 2);
+    assert_eq!(
+        &source[warning.range.start.byte..warning.range.end.byte],
+        " \t"
+    );
+}
+
+#[test]
+fn test_compile_many_names_components_by_file_stem() {
+    let files = [
+        ("components/Card.hyper".to_string(), "<div>Card</div>\n"),
+        ("pages/Broken.hyper".to_string(), "<div>\n"),
+        ("pages/Home.hyper".to_string(), "<{Card} />\n"),
+    ];
+    let results = hyper::compile_many(&files, &hyper::CompileOptions::default());
+
+    let names: Vec<_> = results.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "components/Card.hyper",
+            "pages/Broken.hyper",
+            "pages/Home.hyper"
+        ]
+    );
+    let card = results[0].1.as_ref().unwrap();
+    assert_eq!(card.component_name.as_deref(), Some("Card"));
+    assert!(card.code.contains("def Card("), "{}", card.code);
+    assert!(results[1].1.is_err());
+    assert!(results[2].1.as_ref().unwrap().code.contains("def Home("));
+}
+
+#[test]
+fn test_compile_json_shape() {
+    let options = hyper::CompileOptions {
+        include_ranges: true,
+        ..Default::default()
+    };
+    let ok: serde_json::Value =
+        serde_json::from_str(&hyper::json::compile_json("<p>{name}</p>\n", &options)).unwrap();
+    assert!(ok["compiled"].as_str().unwrap().contains("yield"));
+    assert!(ok["segments"].is_array());
+    assert!(ok["expression_braces"].is_array());
+
+    let without_ranges: serde_json::Value = serde_json::from_str(&hyper::json::compile_json(
+        "<p>Hi</p>\n",
+        &hyper::CompileOptions::default(),
+    ))
+    .unwrap();
+    assert!(without_ranges.get("segments").is_none());
+    assert_eq!(without_ranges["warnings"], serde_json::json!([]));
+
+    let warned: serde_json::Value = serde_json::from_str(&hyper::json::compile_json(
+        "<p key={x}>Hi</p>\n",
+        &hyper::CompileOptions::default(),
+    ))
+    .unwrap();
+    let warning = &warned["warnings"][0];
+    assert!(
+        warning["message"].as_str().unwrap().contains("key"),
+        "{warned}"
+    );
+    assert_eq!(warning["range"]["start"]["col"], 3, "{warned}");
+
+    let err: serde_json::Value =
+        serde_json::from_str(&hyper::json::compile_json("<div>\n", &options)).unwrap();
+    assert!(err["error"].is_string());
+    assert!(err["error_line"].is_u64());
+    assert!(err.get("compiled").is_none());
+}
+
+#[test]
+fn test_transpile_json_shape() {
+    let ok: serde_json::Value =
+        serde_json::from_str(&hyper::json::transpile_json("<p>{name}</p>\n", true)).unwrap();
+    assert!(ok["compiled"].as_str().unwrap().contains("yield"));
+    let segment = &ok["segments"][0];
+    for field in [
+        "source_start",
+        "source_end",
+        "compiled_start",
+        "compiled_end",
+    ] {
+        assert!(segment[field].is_u64(), "{field}: {segment}");
+    }
+
+    let plain: serde_json::Value =
+        serde_json::from_str(&hyper::json::transpile_json("<p>{name}</p>\n", false)).unwrap();
+    assert!(plain.get("segments").is_none());
+    assert!(plain.get("expression_braces").is_none());
+
+    // Astral-plane text around an error still yields an error object
+    let err: serde_json::Value =
+        serde_json::from_str(&hyper::json::transpile_json("<p>😀 <div>\n", true)).unwrap();
+    assert!(err["error"].is_string(), "{err}");
+    assert!(err.get("compiled").is_none());
+}
+
+#[test]
+fn test_key_outside_loop_warns() {
+    let source = "<p key={x}>Hi</p>\n";
+    let result =
+        hyper::compile(source, &hyper::CompileOptions::default()).expect("warnings don't fail");
+
+    assert!(
+        result.code.contains(r#"<p key="{escape(x)}">"#),
+        "{}",
+        result.code
+    );
+    assert_eq!(result.warnings.len(), 1, "{:?}", result.warnings);
+    let warning = &result.warnings[0];
+    assert_eq!(
+        warning.message,
+        "`key` only becomes `data-key` on the first element of a `for` body."
+    );
+    assert_eq!(
+        &source[warning.range.start.byte..warning.range.end.byte],
+        "key={x}"
+    );
+}
+
+#[test]
+fn test_constant_loop_key_warns() {
+    let source = "for item in items:\n    <li key=\"row\">{item}</li>\nend\n";
+    let result =
+        hyper::compile(source, &hyper::CompileOptions::default()).expect("warnings don't fail");
+
+    assert!(
+        result.code.contains(r#"<li data-key="row">"#),
+        "{}",
+        result.code
+    );
+    assert_eq!(result.warnings.len(), 1, "{:?}", result.warnings);
+    let warning = &result.warnings[0];
+    assert_eq!(
+        warning.message,
+        "This `key` is the same for every item; give it a per-item value like `key={item.id}`."
+    );
+    assert_eq!(
+        &source[warning.range.start.byte..warning.range.end.byte],
+        "key=\"row\""
+    );
+
+    let source = "for item in items:\n    <li key=\"row-{item.id}\">{item}</li>\nend\n";
+    let result = hyper::compile(source, &hyper::CompileOptions::default()).unwrap();
+    assert!(
+        result.code.contains("<li data-key=\"row-"),
+        "{}",
+        result.code
+    );
+    assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+}
+
+#[test]
+fn test_escaped_dict_literal_warns() {
+    let source = "<p>{{\"a\": 1}} {{k: v for k, v in d.items()}}</p>\n";
+    let result =
+        hyper::compile(source, &hyper::CompileOptions::default()).expect("warnings don't fail");
+
+    // `{{` always escapes: both print as text
+    assert!(
+        result
+            .code
+            .contains(r#"<p>{"a": 1} {k: v for k, v in d.items()}</p>"#),
+        "{}",
+        result.code
+    );
+    let flagged: Vec<_> = result
+        .warnings
+        .iter()
+        .map(|w| &source[w.range.start.byte..w.range.end.byte])
+        .collect();
+    assert_eq!(flagged, [r#"{{"a": 1}}"#, "{{k: v for k, v in d.items()}}"]);
+}
+
+#[test]
+fn test_spaced_braces_render_a_dict() {
+    let source = "<p>{ {\"a\": 1} }</p>\n";
+    let result = hyper::compile(source, &hyper::CompileOptions::default()).unwrap();
+
+    assert!(
+        result.code.contains(r#"{escape( {"a": 1} )}"#),
+        "{}",
+        result.code
+    );
+    assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+}
+
+#[test]
+fn test_escaped_placeholder_does_not_warn() {
+    // Other template languages' syntax, shown literally in docs
+    for source in [
+        "<p>{{ name }}</p>\n",
+        "<p>{{ x + 1 }}</p>\n",
+        "<p>{{}}</p>\n",
+    ] {
+        let result = hyper::compile(source, &hyper::CompileOptions::default()).unwrap();
+        assert!(
+            result.warnings.is_empty(),
+            "{source}: {:?}",
+            result.warnings
+        );
+    }
+}
+
+#[test]
+fn test_error_render_points_caret_at_tag() {
+    let source = "<main>\n    <p>Hi</p> <div>unclosed\n</main>\n";
+    let Err(error) = hyper::compile(source, &hyper::CompileOptions::default()) else {
+        panic!("unclosed <div> should fail");
+    };
+    let rendered = error.render(source, "Page.hyper");
+
+    assert!(rendered.contains(" file: Page.hyper:2:15\n"), "{rendered}");
+    let lines: Vec<&str> = rendered.lines().collect();
+    // The opener is shown as a secondary note under the error's own line
+    let opener = lines
+        .iter()
+        .position(|line| line.ends_with("<div>unclosed"))
+        .expect("opener line is shown");
+    let (source_line, caret_line) = (lines[opener], lines[opener + 1]);
+    assert_eq!(
+        caret_line.find('^'),
+        source_line.find("<div>"),
+        "{rendered}"
+    );
+    assert!(caret_line.ends_with("^^^^^ opened here"), "{rendered}");
+    assert!(rendered.contains("help: "), "{rendered}");
+}
+
+#[test]
+fn test_minify_collapses_text_whitespace() {
+    let source = "a: str\nb: str\n---\n<div>\n    <p>\n        Hello   {a} {b}\n        world\n    </p>\n</div>\n";
+    let options = hyper::CompileOptions {
+        minify: true,
+        ..Default::default()
+    };
+    let code = hyper::compile(source, &options).unwrap().code;
+
+    // The space between two expressions is kept; whitespace against tags goes
+    assert!(
+        code.contains("yield f\"\"\"<div><p>Hello {escape(a)} {escape(b)} world</p></div>\"\"\""),
+        "{code}"
+    );
+}
+
+#[test]
+fn test_minify_keeps_preformatted_whitespace() {
+    let source =
+        "<div>\n    <pre>\n  a   b\n    </pre>\n    <textarea>  x   y  </textarea>\n</div>\n";
+    let options = hyper::CompileOptions {
+        minify: true,
+        ..Default::default()
+    };
+    let code = hyper::compile(source, &options).unwrap().code;
+
+    assert!(code.contains("<div><pre>\n  a   b\n    </pre>"), "{code}");
+    assert!(
+        code.contains("<textarea>  x   y  </textarea></div>"),
+        "{code}"
+    );
+}
+
+#[test]
+fn test_default_escape_off() {
+    let source = "title: str\nbody: str\nn: int\n---\n<a title={title} href=\"/p/{n}\">{body}</a>\n<b>{n:03d}</b>\n{title}\n";
+    let options = hyper::CompileOptions {
+        default_escape: false,
+        ..Default::default()
+    };
+    let code = hyper::compile(source, &options).unwrap().code;
+
+    assert!(!code.contains("escape"), "{code}");
+    assert!(
+        code.contains(r#"<a title="{title}" href="/p/{n}">{body}</a>"#),
+        "{code}"
+    );
+    assert!(code.contains("<b>{n:03d}</b>\n{title}"), "{code}");
+}
+
+#[test]
+fn test_default_escape_off_keeps_escape_marker() {
+    let source = "title: str\nbody: str\n---\n<a title={&title} href=\"/{&body}\">{&body} {body}</a>\n{&title}\n";
+    let options = hyper::CompileOptions {
+        default_escape: false,
+        ..Default::default()
+    };
+    let code = hyper::compile(source, &options).unwrap().code;
+
+    assert!(
+        code.contains(
+            r#"<a title="{escape(title)}" href="/{escape(body)}">{escape(body)} {body}</a>"#
+        ),
+        "{code}"
+    );
+    assert!(code.contains("</a>\n{escape(title)}"), "{code}");
+    assert!(code.contains("import component, escape"), "{code}");
+
+    // With escaping on, the marker changes nothing
+    let escaped = hyper::compile(source, &Default::default()).unwrap().code;
+    assert!(
+        escaped.contains("{escape(body)} {escape(body)}"),
+        "{escaped}"
+    );
+}
+
+#[test]
+fn test_front_matter_is_kept_but_not_compiled() {
+    let source = "---\ntitle: Home\nlayout: base\n---\nname: str\n---\n<p>{name}</p>\n";
+    let result = hyper::compile(source, &Default::default()).unwrap();
+
+    assert_eq!(
+        result.front_matter.as_deref(),
+        Some("title: Home\nlayout: base")
+    );
+    assert!(!result.code.contains("title"), "{}", result.code);
+    assert!(result.code.contains("name: str"), "{}", result.code);
+}
+
+#[test]
+fn test_without_front_matter() {
+    let result = hyper::compile("---\n<p>hi</p>\n", &Default::default()).unwrap();
+
+    assert_eq!(result.front_matter, None);
+    assert!(result.code.contains("<p>hi</p>"), "{}", result.code);
+}
+
+#[test]
+fn test_attribute_braces_in_strings_and_escapes() {
+    let source =
+        "d: dict\n---\n<p a={d[\"}\"]} b=\"{d['}']}!\" c=\"{{ open: false }}\" e=\"a}\">x</p>\n";
+    let code = compile(source);
+
+    assert!(
+        code.contains(
+            r#"<p a="{escape(d["}"])}" b="{escape(d['}'])}!" c="{{ open: false }}" e="a}}">x</p>"#
+        ),
+        "{code}"
+    );
+}
+
+#[test]
+fn test_escaped_braces_in_static_attribute() {
+    let code = compile("<div x-data=\"{{ open: false }}\">x</div>\n");
+
+    assert!(
+        code.contains(r#"yield """<div x-data="{ open: false }">x</div>""""#),
+        "{code}"
+    );
+}
+
+#[test]
+fn test_referenced_components() {
+    let source = "show: bool\n---\n<{Card}>\n    if show:\n        <{Button} label=\"Go\" />\n    end\n</{Card}>\n<{Card} />\n";
+    let result = hyper::compile(source, &Default::default()).unwrap();
+
+    let names: Vec<_> = result
+        .referenced_components
+        .iter()
+        .map(String::as_str)
+        .collect();
+    assert_eq!(names, ["Button", "Card"]);
+}
+
+#[test]
+fn test_referenced_components_inside_definitions() {
+    let source =
+        "component Page():\n    <{ui.Layout}>\n        <p>Hi</p>\n    </{ui.Layout}>\nend\n";
+    let result = hyper::compile(source, &Default::default()).unwrap();
+
+    let names: Vec<_> = result
+        .referenced_components
+        .iter()
+        .map(String::as_str)
+        .collect();
+    assert_eq!(names, ["ui.Layout"]);
+}
+
+#[test]
+fn test_warning_ranges_utf16() {
+    // 🎉 is four UTF-8 bytes and two UTF-16 code units
+    let source = "x: int\n---\n<p>🎉 <b key={x}>{x}</b></p>\n";
+    let result = hyper::compile(source, &hyper::CompileOptions::default()).unwrap();
+    assert_eq!(result.warnings.len(), 1, "{:?}", result.warnings);
+    let byte_range = result.warnings[0].range;
+
+    let ranges = result.warning_ranges_utf16(source);
+    let range = ranges[0].expect("warning has a source range");
+    let utf16: Vec<u16> = source.encode_utf16().collect();
+    let text = String::from_utf16(&utf16[range.start.offset..range.end.offset]).unwrap();
+    assert_eq!(text, &source[byte_range.start.byte..byte_range.end.byte]);
+
+    assert_eq!(range.start.offset, byte_range.start.byte - 2);
+    assert_eq!(range.start.line, 2);
+    assert_eq!(range.start.character, byte_range.start.col + 1);
+}
+
+#[test]
+fn test_minify_keeps_spaces_around_inline_elements() {
+    let source = "<p>\n    Hello <b>world</b> and <a href=\"/\"> the  link </a>!\n</p>\n<ul>\n    <li> x </li>\n</ul>\n";
+    let options = hyper::CompileOptions {
+        minify: true,
+        ..Default::default()
+    };
+    let code = hyper::compile(source, &options).unwrap().code;
+
+    assert!(
+        code.contains("<p>Hello <b>world</b> and <a href=\"/\"> the link </a>!</p>"),
+        "{code}"
+    );
+    assert!(code.contains("<ul><li>x</li></ul>"), "{code}");
+}
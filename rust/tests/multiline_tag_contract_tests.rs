@@ -1,5 +1,6 @@
 use hyper::{CompileOptions, compile};
 
+#[allow(clippy::needless_range_loop)]
 fn assert_opening_tag_ranges_cover_source(source: &str, opening_start: usize, opening_end: usize) {
     assert!(source.is_ascii());
 
@@ -8,6 +9,7 @@ fn assert_opening_tag_ranges_cover_source(source: &str, opening_start: usize, op
         &CompileOptions {
             function_name: Some("Template".to_string()),
             include_ranges: true,
+            ..Default::default()
         },
     )
     .expect("multiline opening tag should compile");
@@ -0,0 +1,163 @@
+use hyper::ast::{Node, ParameterNode};
+use hyper::parse::{HyperParser, Parser};
+
+fn parameters(source: &str) -> Vec<ParameterNode> {
+    HyperParser::new()
+        .parse(source)
+        .expect("source should parse")
+        .into_iter()
+        .filter_map(|node| match node {
+            Node::Parameter(param) => Some(param),
+            _ => None,
+        })
+        .collect()
+}
+
+fn assert_parameter(line: &str, name: &str, type_hint: &str, default: &str) {
+    let params = parameters(&format!("{line}\n---\n<p>Hi</p>\n"));
+    assert_eq!(params.len(), 1, "{line:?} should be one parameter");
+    assert_eq!(params[0].name, name);
+    assert_eq!(params[0].type_hint.as_deref(), Some(type_hint));
+    assert_eq!(params[0].default.as_deref(), Some(default));
+}
+
+#[test]
+fn default_with_empty_list() {
+    assert_parameter("items: list = []", "items", "list", "[]");
+}
+
+#[test]
+fn default_containing_colon() {
+    assert_parameter(r#"m: dict = {"k": 1}"#, "m", "dict", r#"{"k": 1}"#);
+}
+
+#[test]
+fn default_with_call() {
+    assert_parameter("x: int = f(1, 2)", "x", "int", "f(1, 2)");
+}
+
+#[test]
+fn annotation_containing_equals() {
+    assert_parameter(
+        "age: Annotated[int, Field(gt=0)] = 18",
+        "age",
+        "Annotated[int, Field(gt=0)]",
+        "18",
+    );
+    assert_parameter(
+        r#"mode: Literal["a=b"] = "a=b""#,
+        "mode",
+        r#"Literal["a=b"]"#,
+        r#""a=b""#,
+    );
+}
+
+#[test]
+fn default_with_comparison() {
+    assert_parameter("flag: bool = a == b", "flag", "bool", "a == b");
+}
+
+#[test]
+fn generic_annotations_kept_verbatim() {
+    let params = parameters("cb: Callable[[int], str]\n---\n<p>Hi</p>\n");
+    assert_eq!(params.len(), 1);
+    assert_eq!(params[0].name, "cb");
+    assert_eq!(params[0].type_hint.as_deref(), Some("Callable[[int], str]"));
+    assert_eq!(params[0].default, None);
+
+    assert_parameter("m: Dict[str, int] = {}", "m", "Dict[str, int]", "{}");
+    assert_parameter("opt: int | None = None", "opt", "int | None", "None");
+}
+
+#[test]
+fn generic_annotations_in_signature() {
+    let source = "from typing import Callable, Dict\n\
+                  cb: Callable[[int], str]\n\
+                  m: Dict[str, int] = {}\n\
+                  opt: int | None = None\n\
+                  ---\n<p>{cb(opt)}</p>\n";
+    let code = hyper::compile(source, &Default::default())
+        .expect("source should compile")
+        .code;
+
+    assert!(
+        code.contains("        cb: Callable[[int], str],\n"),
+        "{code}"
+    );
+    assert!(code.contains("        m: Dict[str, int] = {},\n"), "{code}");
+    assert!(code.contains("        opt: int | None = None,\n"), "{code}");
+    // The user's import already binds Callable, so it isn't imported again
+    assert_eq!(code.matches("from typing import").count(), 1, "{code}");
+}
+
+#[test]
+fn typing_imports_match_whole_names() {
+    let source = "company: Company\nhandler: MyCallable\n---\n<p>{company}</p>\n";
+    let code = hyper::compile(source, &Default::default())
+        .expect("source should compile")
+        .code;
+
+    assert!(!code.contains("from typing import"), "{code}");
+}
+
+#[test]
+fn unicode_parameter_name() {
+    assert_parameter("café: str = \"noir\"", "café", "str", "\"noir\"");
+}
+
+#[test]
+fn unicode_parameter_flows_into_expressions() {
+    let source = "café: str\n---\n<p {café}>{café}</p>\n";
+    let code = hyper::compile(source, &Default::default())
+        .expect("source should compile")
+        .code;
+
+    assert!(code.contains("        café: str,\n"), "{code}");
+    assert!(
+        code.contains(r#"<p{render_attr("café", café)}>{escape(café)}</p>"#),
+        "{code}"
+    );
+}
+
+#[test]
+fn walrus_in_header_is_not_a_parameter() {
+    let params = parameters("name: str\n(greeting := f\"Hi {name}\")\n---\n<p>{greeting}</p>\n");
+    assert_eq!(params.len(), 1);
+    assert_eq!(params[0].name, "name");
+}
+
+fn unused_warnings(source: &str) -> Vec<String> {
+    hyper::compile(source, &Default::default())
+        .expect("warnings don't fail")
+        .warnings
+        .into_iter()
+        .map(|warning| warning.message)
+        .collect()
+}
+
+#[test]
+fn used_parameter_is_not_flagged() {
+    let source = "name: str\nitems: list\nactive: bool\nmodule: object\n---\n\
+                  <p class=\"note {active}\">{name}</p>\n\
+                  for item in items:\n    <{module.Row} {item} />\nend\n";
+    assert!(unused_warnings(source).is_empty());
+}
+
+#[test]
+fn unused_parameter_is_flagged() {
+    let source = "name: str\nunused: int = 0\n**attrs\n---\n<p>{name}</p>\n";
+    let result = hyper::compile(source, &Default::default()).unwrap();
+
+    let messages: Vec<_> = result.warnings.iter().map(|w| w.message.as_str()).collect();
+    assert_eq!(messages, ["`unused` is declared but never used."]);
+    let range = result.warnings[0].range;
+    assert_eq!(&source[range.start.byte..range.end.byte], "unused: int = 0");
+}
+
+#[test]
+fn parameter_named_inside_another_word_is_flagged() {
+    assert_eq!(
+        unused_warnings("user: str\n---\n<p>{username}</p>\n"),
+        ["`user` is declared but never used."]
+    );
+}
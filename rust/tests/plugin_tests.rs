@@ -0,0 +1,54 @@
+use hyper::{CompileError, CompileOptions, Flow, Node, Plugin, compile_with_plugins};
+
+/// Uppercases every text node and counts how many it changed.
+#[derive(Default)]
+struct Shout {
+    changed: usize,
+}
+
+impl Plugin for Shout {
+    fn enter(&mut self, node: &mut Node) -> Result<Flow, CompileError> {
+        if let Node::Text(text) = node
+            && !text.content.trim().is_empty()
+        {
+            text.content = text.content.to_uppercase();
+            self.changed += 1;
+        }
+        Ok(Flow::Continue)
+    }
+}
+
+#[test]
+fn extra_plugin_rewrites_text() {
+    let source = "<p>hello {name}</p>\n";
+    let mut shout = Shout::default();
+    let result =
+        compile_with_plugins(source, &CompileOptions::default(), &mut [&mut shout]).unwrap();
+
+    assert!(
+        result.code.contains("<p>HELLO {escape(name)}</p>"),
+        "{}",
+        result.code
+    );
+    assert_eq!(shout.changed, 1);
+}
+
+#[test]
+fn extra_plugin_sees_every_component() {
+    let source = "\
+component Card():
+    <p>card</p>
+end
+
+component Badge():
+    <b>badge</b>
+end
+";
+    let mut shout = Shout::default();
+    let result =
+        compile_with_plugins(source, &CompileOptions::default(), &mut [&mut shout]).unwrap();
+
+    assert!(result.code.contains("<p>CARD</p>"), "{}", result.code);
+    assert!(result.code.contains("<b>BADGE</b>"), "{}", result.code);
+    assert_eq!(shout.changed, 2);
+}